@@ -33,6 +33,20 @@ const referenceValueSchema = z.object({
   high_info: z.string().default(''),
   low_info: z.string().default(''),
   recommendations: z.string().default(''),
+  age_ranges: z
+    .array(
+      z.object({
+        min_age: z.number().min(0).optional(),
+        max_age: z.number().positive().optional(),
+        ref_min: z.number().optional(),
+        ref_max: z.number().optional(),
+        ref_min_female: z.number().optional(),
+        ref_max_female: z.number().optional(),
+        ref_min_male: z.number().optional(),
+        ref_max_male: z.number().optional(),
+      })
+    )
+    .optional(),
 });
 
 function slugify(name: string): string {
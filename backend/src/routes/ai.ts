@@ -1,10 +1,10 @@
-import { Router } from 'express';
+import { Router, type Response } from 'express';
 import { v4 as uuidv4 } from 'uuid';
 import { z } from 'zod';
 import multer from 'multer';
 import { requireAuth, asyncHandler } from '../middleware/requireAuth';
-import { getUserData, getChatHistory, saveChatHistory, checkAndIncrementAIRate } from '../services/fileStore';
-import { chat, analyzeBloodTestImage } from '../services/llm';
+import { getUserData, getChatHistory, saveChatHistory, checkAndIncrementAIRate, getAIRateResetAt } from '../services/fileStore';
+import { chat, chatStream, analyzeBloodTestImage } from '../services/llm';
 import type { LLMMessage, ChatMessage } from '../types';
 
 export const aiRouter = Router();
@@ -27,6 +27,18 @@ const upload = multer({
   },
 });
 
+function sendRateLimited(res: Response, userId: string) {
+  const resetAt = getAIRateResetAt(userId);
+  if (resetAt !== null) {
+    res.set('Retry-After', String(Math.max(0, Math.ceil((resetAt - Date.now()) / 1000))));
+  }
+  return res.status(429).json({
+    error: 'Rate limit exceeded',
+    message: 'Du hast das tägliche Limit von 50 KI-Anfragen erreicht. Versuche es morgen wieder.',
+    resetAt: resetAt !== null ? new Date(resetAt).toISOString() : undefined,
+  });
+}
+
 // GET /api/ai/history – get chat history
 aiRouter.get(
   '/history',
@@ -61,10 +73,7 @@ aiRouter.post(
     // Rate limiting: max 50 AI requests per user per day
     const allowed = checkAndIncrementAIRate(userId, 50);
     if (!allowed) {
-      return res.status(429).json({
-        error: 'Rate limit exceeded',
-        message: 'Du hast das tägliche Limit von 50 KI-Anfragen erreicht. Versuche es morgen wieder.',
-      });
+      return sendRateLimited(res, userId);
     }
 
     const userData = getUserData(userId);
@@ -110,6 +119,95 @@ aiRouter.post(
   })
 );
 
+// POST /api/ai/chat/stream – send message, answer streamed as server-sent events
+//
+// Events: `token` ({ text }) for each generated fragment, then either
+// `done` ({ message, userMessage }) or `error` ({ error }).
+aiRouter.post(
+  '/chat/stream',
+  asyncHandler(async (req, res) => {
+    const userId = req.session.userId!;
+    const parsed = messageSchema.safeParse(req.body);
+
+    if (!parsed.success) {
+      return res.status(400).json({ error: 'Validation error', details: parsed.error.format() });
+    }
+
+    // Same daily limit as the non-streaming endpoint
+    const allowed = checkAndIncrementAIRate(userId, 50);
+    if (!allowed) {
+      return sendRateLimited(res, userId);
+    }
+
+    const userData = getUserData(userId);
+    const chatHistory = getChatHistory(userId);
+
+    const contextMessages: LLMMessage[] = chatHistory.messages
+      .slice(-20)
+      .map((m) => ({ role: m.role as 'user' | 'assistant', content: m.content }));
+
+    contextMessages.push({ role: 'user', content: parsed.data.message });
+
+    const userMessage: ChatMessage = {
+      id: uuidv4(),
+      role: 'user',
+      content: parsed.data.message,
+      timestamp: new Date().toISOString(),
+    };
+    chatHistory.messages.push(userMessage);
+
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream; charset=utf-8',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+      'X-Accel-Buffering': 'no', // disable proxy buffering (nginx)
+    });
+
+    const send = (event: string, data: unknown) => {
+      res.write(`event: ${event}\ndata: ${JSON.stringify(data)}\n\n`);
+    };
+
+    // Client pressed "Stop" or went away: cancel the LLM request
+    const controller = new AbortController();
+    res.on('close', () => {
+      if (!res.writableEnded) controller.abort();
+    });
+
+    const response = await chatStream(
+      contextMessages,
+      userData,
+      (text) => send('token', { text }),
+      controller.signal
+    );
+
+    if (response.error) {
+      send('error', { error: response.error });
+      res.end();
+      return;
+    }
+
+    // Keep what was generated before a stop, so the history matches the UI
+    if (response.content) {
+      const assistantMessage: ChatMessage = {
+        id: uuidv4(),
+        role: 'assistant',
+        content: response.content,
+        timestamp: new Date().toISOString(),
+      };
+      chatHistory.messages.push(assistantMessage);
+      saveChatHistory(userId, chatHistory);
+
+      if (!controller.signal.aborted) {
+        send('done', { message: assistantMessage, userMessage });
+      }
+    } else if (!controller.signal.aborted) {
+      send('error', { error: 'KI-Doktor hat keine Antwort geliefert.' });
+    }
+
+    if (!res.writableEnded) res.end();
+  })
+);
+
 // POST /api/ai/scan – analyze blood test image
 aiRouter.post(
   '/scan',
@@ -124,10 +222,7 @@ aiRouter.post(
     // Rate limiting: share the same daily limit with chat
     const allowed = checkAndIncrementAIRate(userId, 50);
     if (!allowed) {
-      return res.status(429).json({
-        error: 'Rate limit exceeded',
-        message: 'Du hast das tägliche Limit von 50 KI-Anfragen erreicht. Versuche es morgen wieder.',
-      });
+      return sendRateLimited(res, userId);
     }
 
     try {
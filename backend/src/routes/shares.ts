@@ -166,6 +166,7 @@ sharesRouter.get(
       user_id: ownerData.user_id,
       display_name: ownerData.display_name,
       gender: ownerData.gender,
+      birth_date: ownerData.birth_date,
       entries: ownerData.entries,
     });
   })
@@ -10,13 +10,21 @@ tokensRouter.use(requireAuth);
 
 const MAX_TOKENS = 10;
 
-// GET /api/tokens – list all tokens (without exposing the token value)
+// GET /api/tokens – list all tokens (without exposing the token value).
+// `current` marks the token the request was authenticated with.
 tokensRouter.get(
   '/',
   asyncHandler(async (req, res) => {
     const userId = req.session.userId!;
     const data = getUserData(userId);
-    const tokens = (data.api_tokens ?? []).map(({ id, name, created_at }) => ({ id, name, created_at }));
+    const authHeader = req.headers['authorization'];
+    const bearer = authHeader?.startsWith('Bearer ') ? authHeader.slice(7) : undefined;
+    const tokens = (data.api_tokens ?? []).map(({ id, name, created_at, token }) => ({
+      id,
+      name,
+      created_at,
+      current: token === bearer,
+    }));
     res.json(tokens);
   })
 );
@@ -16,6 +16,8 @@ const lifestyleSchema = z.object({
 
 const profileSchema = z.object({
   gender: z.enum(['male', 'female']).optional(),
+  // null removes it
+  birth_date: z.string().regex(/^\d{4}-\d{2}-\d{2}$/).nullable().optional(),
   diagnoses: z.array(z.string().min(1).max(200)).max(50).optional(),
   medications: z.array(z.string().min(1).max(200)).max(50).optional(),
   lifestyle: lifestyleSchema,
@@ -31,6 +33,8 @@ userRouter.patch(
 
     const data = getUserData(userId);
     if (parsed.gender !== undefined) data.gender = parsed.gender;
+    if (parsed.birth_date === null) delete data.birth_date;
+    else if (parsed.birth_date !== undefined) data.birth_date = parsed.birth_date;
     if (parsed.diagnoses !== undefined) data.diagnoses = parsed.diagnoses;
     if (parsed.medications !== undefined) data.medications = parsed.medications;
     if (parsed.lifestyle !== undefined) data.lifestyle = parsed.lifestyle;
@@ -39,6 +43,7 @@ userRouter.patch(
     res.json({
       success: true,
       gender: data.gender,
+      birth_date: data.birth_date ?? null,
       diagnoses: data.diagnoses,
       medications: data.medications,
       lifestyle: data.lifestyle,
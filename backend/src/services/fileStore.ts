@@ -292,3 +292,9 @@ export function checkAndIncrementAIRate(userId: string, maxPerDay = 50): boolean
   writeJSON(filePath, records);
   return true;
 }
+
+/** When the daily AI limit of a user resets (unix timestamp ms), if a record exists. */
+export function getAIRateResetAt(userId: string): number | null {
+  const records = readJSON<Record<string, RateRecord>>(aiRateLimitFile(), {});
+  return records[userId]?.resetAt ?? null;
+}
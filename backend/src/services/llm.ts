@@ -263,14 +263,18 @@ async function callOllama(
 
 // ─── Main Chat Function ───────────────────────────────────────────────────────
 
+function buildSystemPrompt(userData: UserData): string {
+  const userContext = buildUserContext(userData);
+  return `${SYSTEM_PROMPT}\n\n---\nKontext - Aktuelle Nutzerdaten:\n${userContext}`;
+}
+
 export async function chat(
   messages: LLMMessage[],
   userData: UserData
 ): Promise<LLMResponse> {
   const config = getConfig();
 
-  const userContext = buildUserContext(userData);
-  const fullSystemPrompt = `${SYSTEM_PROMPT}\n\n---\nKontext - Aktuelle Nutzerdaten:\n${userContext}`;
+  const fullSystemPrompt = buildSystemPrompt(userData);
 
   try {
     let content = '';
@@ -339,6 +343,304 @@ export async function chat(
   }
 }
 
+// ─── Streaming ────────────────────────────────────────────────────────────────
+
+type TokenHandler = (text: string) => void;
+
+/** Yields the lines of a streamed response body as they arrive. */
+async function* readLines(response: Response): AsyncGenerator<string> {
+  if (!response.body) return;
+  const reader = response.body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = '';
+
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    buffer += decoder.decode(value, { stream: true });
+
+    let newline = buffer.indexOf('\n');
+    while (newline >= 0) {
+      yield buffer.slice(0, newline).replace(/\r$/, '');
+      buffer = buffer.slice(newline + 1);
+      newline = buffer.indexOf('\n');
+    }
+  }
+
+  buffer += decoder.decode();
+  if (buffer) yield buffer;
+}
+
+/** Yields the `data:` payloads of a server-sent events stream. */
+async function* readSseData(response: Response): AsyncGenerator<string> {
+  for await (const line of readLines(response)) {
+    if (line.startsWith('data:')) yield line.slice(5).trim();
+  }
+}
+
+async function streamGemini(
+  messages: LLMMessage[],
+  systemPrompt: string,
+  model: string,
+  apiKey: string,
+  baseUrl: string | undefined,
+  onToken: TokenHandler,
+  signal: AbortSignal
+): Promise<void> {
+  const base = (baseUrl || 'https://generativelanguage.googleapis.com/v1beta').replace(/\/$/, '');
+  const url = `${base}/models/${model}:streamGenerateContent?alt=sse&key=${apiKey}`;
+
+  const contents = messages.map((m) => ({
+    role: m.role === 'assistant' ? 'model' : 'user',
+    parts: [{ text: m.content }],
+  }));
+
+  const response = await fetch(url, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      system_instruction: { parts: [{ text: systemPrompt }] },
+      contents,
+      generationConfig: { temperature: 0.7, maxOutputTokens: 4096 },
+    }),
+    signal,
+  });
+
+  if (!response.ok) {
+    const err = await response.text();
+    throw new Error(`Gemini API error ${response.status}: ${err}`);
+  }
+
+  for await (const data of readSseData(response)) {
+    const chunk = JSON.parse(data) as {
+      candidates?: Array<{ content?: { parts?: Array<{ text?: string }> } }>;
+      error?: { message: string };
+    };
+    if (chunk.error) throw new Error(`Gemini error: ${chunk.error.message}`);
+    const text = chunk.candidates?.[0]?.content?.parts?.map((p) => p.text ?? '').join('') ?? '';
+    if (text) onToken(text);
+  }
+}
+
+async function streamOpenAI(
+  messages: LLMMessage[],
+  systemPrompt: string,
+  model: string,
+  apiKey: string,
+  baseUrl: string,
+  onToken: TokenHandler,
+  signal: AbortSignal
+): Promise<void> {
+  const url = `${baseUrl.replace(/\/$/, '')}/chat/completions`;
+
+  const allMessages = [
+    { role: 'system', content: systemPrompt },
+    ...messages.map((m) => ({ role: m.role, content: m.content })),
+  ];
+
+  const response = await fetch(url, {
+    method: 'POST',
+    headers: {
+      'Content-Type': 'application/json',
+      Authorization: `Bearer ${apiKey}`,
+    },
+    body: JSON.stringify({ model, messages: allMessages, max_tokens: 4096, stream: true }),
+    signal,
+  });
+
+  if (!response.ok) {
+    const err = await response.text();
+    throw new Error(`OpenAI API error ${response.status}: ${err}`);
+  }
+
+  for await (const data of readSseData(response)) {
+    if (data === '[DONE]') break;
+    const chunk = JSON.parse(data) as {
+      choices?: Array<{ delta?: { content?: string } }>;
+      error?: { message: string };
+    };
+    if (chunk.error) throw new Error(`OpenAI error: ${chunk.error.message}`);
+    const text = chunk.choices?.[0]?.delta?.content;
+    if (text) onToken(text);
+  }
+}
+
+async function streamAnthropic(
+  messages: LLMMessage[],
+  systemPrompt: string,
+  model: string,
+  apiKey: string,
+  onToken: TokenHandler,
+  signal: AbortSignal
+): Promise<void> {
+  const response = await fetch('https://api.anthropic.com/v1/messages', {
+    method: 'POST',
+    headers: {
+      'Content-Type': 'application/json',
+      'x-api-key': apiKey,
+      'anthropic-version': '2023-06-01',
+    },
+    body: JSON.stringify({
+      model,
+      system: systemPrompt,
+      messages: messages.map((m) => ({ role: m.role, content: m.content })),
+      max_tokens: 4096,
+      stream: true,
+    }),
+    signal,
+  });
+
+  if (!response.ok) {
+    const err = await response.text();
+    throw new Error(`Anthropic API error ${response.status}: ${err}`);
+  }
+
+  for await (const data of readSseData(response)) {
+    const event = JSON.parse(data) as {
+      type: string;
+      delta?: { type?: string; text?: string };
+      error?: { message: string };
+    };
+    if (event.type === 'error') throw new Error(`Anthropic error: ${event.error?.message}`);
+    if (event.type === 'content_block_delta' && event.delta?.text) onToken(event.delta.text);
+  }
+}
+
+async function streamOllama(
+  messages: LLMMessage[],
+  systemPrompt: string,
+  model: string,
+  baseUrl: string,
+  onToken: TokenHandler,
+  signal: AbortSignal
+): Promise<void> {
+  const url = `${baseUrl.replace(/\/$/, '')}/api/chat`;
+
+  const allMessages = [
+    { role: 'system', content: systemPrompt },
+    ...messages.map((m) => ({ role: m.role, content: m.content })),
+  ];
+
+  const response = await fetch(url, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ model, messages: allMessages, stream: true }),
+    signal,
+  });
+
+  if (!response.ok) {
+    const err = await response.text();
+    throw new Error(`Ollama API error ${response.status}: ${err}`);
+  }
+
+  // Ollama streams newline-delimited JSON instead of SSE
+  for await (const line of readLines(response)) {
+    if (!line.trim()) continue;
+    const chunk = JSON.parse(line) as { message?: { content?: string }; done?: boolean; error?: string };
+    if (chunk.error) throw new Error(`Ollama error: ${chunk.error}`);
+    if (chunk.message?.content) onToken(chunk.message.content);
+    if (chunk.done) break;
+  }
+}
+
+/**
+ * Like `chat`, but hands every generated text fragment to `onToken` as soon as
+ * the provider sends it. Aborting `signal` stops generation and returns the
+ * text produced so far.
+ */
+export async function chatStream(
+  messages: LLMMessage[],
+  userData: UserData,
+  onToken: TokenHandler,
+  signal: AbortSignal
+): Promise<LLMResponse> {
+  const config = getConfig();
+  const fullSystemPrompt = buildSystemPrompt(userData);
+
+  let content = '';
+  const handleToken = (text: string) => {
+    content += text;
+    onToken(text);
+  };
+
+  try {
+    switch (config.LLM_PROVIDER) {
+      case 'gemini':
+        await streamGemini(
+          messages,
+          fullSystemPrompt,
+          config.LLM_MODEL,
+          config.LLM_API_KEY || '',
+          config.LLM_API_URL,
+          handleToken,
+          signal
+        );
+        break;
+
+      case 'openai':
+        await streamOpenAI(
+          messages,
+          fullSystemPrompt,
+          config.LLM_MODEL,
+          config.LLM_API_KEY || '',
+          'https://api.openai.com/v1',
+          handleToken,
+          signal
+        );
+        break;
+
+      case 'openai_compatible':
+        await streamOpenAI(
+          messages,
+          fullSystemPrompt,
+          config.LLM_MODEL,
+          config.LLM_API_KEY || '',
+          config.LLM_API_URL || 'http://localhost:8080/v1',
+          handleToken,
+          signal
+        );
+        break;
+
+      case 'anthropic':
+        await streamAnthropic(
+          messages,
+          fullSystemPrompt,
+          config.LLM_MODEL,
+          config.LLM_API_KEY || '',
+          handleToken,
+          signal
+        );
+        break;
+
+      case 'ollama':
+        await streamOllama(
+          messages,
+          fullSystemPrompt,
+          config.LLM_MODEL,
+          config.LLM_API_URL || 'http://localhost:11434',
+          handleToken,
+          signal
+        );
+        break;
+
+      default:
+        throw new Error(`Unknown LLM provider: ${config.LLM_PROVIDER}`);
+    }
+
+    return { content };
+  } catch (error) {
+    if (signal.aborted) {
+      return { content, aborted: true };
+    }
+    const msg = error instanceof Error ? error.message : String(error);
+    console.error('LLM stream error:', msg);
+    return {
+      content,
+      error: `KI-Doktor ist momentan nicht verfügbar: ${msg}`,
+    };
+  }
+}
+
 // ─── Blood Test Scan Analysis ─────────────────────────────────────────────────
 
 const SCAN_PROMPT = `Du bist ein Experte für die Analyse von Laborberichten. Extrahiere ALLE Blutwerte aus dem Bild/Dokument.
@@ -52,6 +52,8 @@ export interface UserData {
   display_name: string;
   email: string;
   gender?: Gender;
+  /** YYYY-MM-DD */
+  birth_date?: string;
   diagnoses?: string[];
   medications?: string[];
   lifestyle?: Lifestyle;
@@ -100,6 +102,21 @@ export interface ReferenceValue {
   high_info: string;
   low_info: string;
   recommendations: string;
+  /** Ranges that differ by age; omitted bounds fall back to the general ones */
+  age_ranges?: AgeRange[];
+}
+
+export interface AgeRange {
+  /** Years, inclusive */
+  min_age?: number;
+  /** Years, exclusive */
+  max_age?: number;
+  ref_min?: number;
+  ref_max?: number;
+  ref_min_female?: number;
+  ref_max_female?: number;
+  ref_min_male?: number;
+  ref_max_male?: number;
 }
 
 export interface ReferenceDatabase {
@@ -178,4 +195,5 @@ export interface LLMMessage {
 export interface LLMResponse {
   content: string;
   error?: string;
+  aborted?: boolean; // streaming was cancelled by the client
 }
@@ -1,22 +1,87 @@
 use anyhow::{anyhow, Result};
-use reqwest::{Client, StatusCode};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::time::Duration;
 
+use super::error::{api_error, ApiError, ApiResult};
+use super::sse::SseParser;
 use super::types::*;
 
+/// True if the request never got an HTTP answer (server unreachable, DNS, timeout).
+pub fn is_network_error(e: &anyhow::Error) -> bool {
+    api_error(e).is_some_and(ApiError::is_offline)
+}
+
+/// Timeouts and retry policy, read from the `[network]` table of config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientOptions {
+    /// Time to establish a connection
+    pub connect_timeout_secs: u64,
+    /// Total time for a normal request
+    pub timeout_secs: u64,
+    /// Total time for requests answered by the LLM (chat, scan); for the
+    /// streamed chat the longest wait for the answer or its next chunk
+    pub ai_timeout_secs: u64,
+    /// Extra attempts for GET requests after a network error or 502–504
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub retry_delay_ms: u64,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            ai_timeout_secs: 180,
+            max_retries: 3,
+            retry_delay_ms: 500,
+        }
+    }
+}
+
+/// Longest wait between two attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+impl ClientOptions {
+    /// Wait before retry number `attempt` (0-based): exponential, capped.
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+        Duration::from_millis(self.retry_delay_ms.saturating_mul(factor)).min(MAX_RETRY_DELAY)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    fn ai_timeout(&self) -> Duration {
+        Duration::from_secs(self.ai_timeout_secs)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     client: Client,
     base_url: String,
     token: String,
+    options: ClientOptions,
 }
 
 impl ApiClient {
     pub fn new(base_url: String, token: String) -> Result<Self> {
+        Self::with_options(base_url, token, ClientOptions::default())
+    }
+
+    pub fn with_options(base_url: String, token: String, options: ClientOptions) -> Result<Self> {
         let client = Client::builder()
+            .connect_timeout(Duration::from_secs(options.connect_timeout_secs))
             .build()
             .map_err(|e| anyhow!("Failed to create HTTP client: {e}"))?;
-        Ok(Self { client, base_url, token })
+        Ok(Self { client, base_url, token, options })
     }
 
     fn url(&self, path: &str) -> String {
@@ -27,108 +92,291 @@ impl ApiClient {
         format!("Bearer {}", self.token)
     }
 
-    pub async fn get_me(&self) -> Result<AuthUser> {
-        let resp = self
-            .client
-            .get(self.url("/api/auth/me"))
+    /// Sends an authorized request and turns error statuses into [`ApiError`].
+    async fn send(&self, request: RequestBuilder) -> ApiResult<Response> {
+        let resp = request
             .header("Authorization", self.auth_header())
             .send()
             .await?;
-
         if !resp.status().is_success() {
-            return Err(anyhow!("Auth failed: HTTP {}", resp.status()));
+            return Err(ApiError::from_response(resp).await);
         }
-        Ok(resp.json().await?)
+        Ok(resp)
     }
 
-    pub async fn get_blood_values(&self) -> Result<UserData> {
-        let resp = self
-            .client
-            .get(self.url("/api/bloodvalues"))
-            .header("Authorization", self.auth_header())
-            .send()
-            .await?;
+    async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> ApiResult<T> {
+        Ok(self.send(request).await?.json().await?)
+    }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to fetch blood values: HTTP {}", resp.status()));
+    /// GET with the normal timeout, retried with exponential backoff while
+    /// the error is transient.
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> ApiResult<T> {
+        let mut attempt = 0;
+        loop {
+            let request = self.client.get(self.url(path)).timeout(self.options.timeout());
+            match self.send_json(request).await {
+                Err(e) if e.is_transient() && attempt < self.options.max_retries => {
+                    tokio::time::sleep(self.options.retry_delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
         }
-        Ok(resp.json().await?)
     }
 
-    pub async fn get_history(&self, name: &str) -> Result<ValueHistory> {
+    pub async fn get_me(&self) -> ApiResult<AuthUser> {
+        self.get_json("/api/auth/me").await
+    }
+
+    pub async fn get_blood_values(&self) -> ApiResult<UserData> {
+        self.get_json("/api/bloodvalues").await
+    }
+
+    pub async fn create_entry(&self, entry: &BloodEntryInput) -> ApiResult<BloodEntry> {
+        let request = self
+            .client
+            .post(self.url("/api/bloodvalues"))
+            .timeout(self.options.timeout())
+            .json(entry);
+        self.send_json(request).await
+    }
+
+    pub async fn update_entry(&self, id: &str, entry: &BloodEntryInput) -> ApiResult<BloodEntry> {
+        let encoded = urlencoding::encode(id);
+        let request = self
+            .client
+            .put(self.url(&format!("/api/bloodvalues/{encoded}")))
+            .timeout(self.options.timeout())
+            .json(entry);
+        self.send_json(request).await
+    }
+
+    pub async fn delete_entry(&self, id: &str) -> ApiResult<()> {
+        let encoded = urlencoding::encode(id);
+        let request = self
+            .client
+            .delete(self.url(&format!("/api/bloodvalues/{encoded}")))
+            .timeout(self.options.timeout());
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Saves gender, diagnoses, medications and lifestyle, returning the stored profile.
+    pub async fn update_profile(&self, profile: &HealthProfile) -> ApiResult<HealthProfile> {
+        let request = self
+            .client
+            .patch(self.url("/api/user/profile"))
+            .timeout(self.options.timeout())
+            .json(profile);
+        self.send_json(request).await
+    }
+
+    pub async fn get_history(&self, name: &str) -> ApiResult<ValueHistory> {
         let encoded = urlencoding::encode(name);
-        let resp = self
+        self.get_json(&format!("/api/bloodvalues/history/{encoded}")).await
+    }
+
+    pub async fn get_reference(&self) -> ApiResult<ReferenceDatabase> {
+        self.get_json("/api/reference").await
+    }
+
+    pub async fn list_tokens(&self) -> ApiResult<Vec<ApiToken>> {
+        self.get_json("/api/tokens").await
+    }
+
+    pub async fn create_token(&self, name: &str) -> ApiResult<CreatedApiToken> {
+        let request = self
             .client
-            .get(self.url(&format!("/api/bloodvalues/history/{encoded}")))
-            .header("Authorization", self.auth_header())
-            .send()
-            .await?;
+            .post(self.url("/api/tokens"))
+            .timeout(self.options.timeout())
+            .json(&json!({ "name": name }));
+        self.send_json(request).await
+    }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to fetch history: HTTP {}", resp.status()));
-        }
-        Ok(resp.json().await?)
+    pub async fn delete_token(&self, id: &str) -> ApiResult<()> {
+        let encoded = urlencoding::encode(id);
+        let request = self
+            .client
+            .delete(self.url(&format!("/api/tokens/{encoded}")))
+            .timeout(self.options.timeout());
+        self.send(request).await?;
+        Ok(())
     }
 
-    pub async fn get_reference(&self) -> Result<ReferenceDatabase> {
-        let resp = self
+    /// Shares I have given that have not expired.
+    pub async fn get_given_shares(&self) -> ApiResult<Vec<Share>> {
+        self.get_json("/api/shares/given").await
+    }
+
+    pub async fn create_share(&self, share: &ShareInput) -> ApiResult<Share> {
+        let request = self
             .client
-            .get(self.url("/api/reference"))
-            .header("Authorization", self.auth_header())
-            .send()
-            .await?;
+            .post(self.url("/api/shares"))
+            .timeout(self.options.timeout())
+            .json(share);
+        self.send_json(request).await
+    }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to fetch reference DB: HTTP {}", resp.status()));
-        }
-        Ok(resp.json().await?)
+    pub async fn delete_share(&self, id: &str) -> ApiResult<()> {
+        let encoded = urlencoding::encode(id);
+        let request = self
+            .client
+            .delete(self.url(&format!("/api/shares/{encoded}")))
+            .timeout(self.options.timeout());
+        self.send(request).await?;
+        Ok(())
     }
 
-    pub async fn get_chat_history(&self) -> Result<ChatHistory> {
-        let resp = self
+    /// Shares other users have given me.
+    pub async fn get_received_shares(&self) -> ApiResult<Vec<ReceivedShare>> {
+        self.get_json("/api/shares/received").await
+    }
+
+    /// Values of the owner of a received share.
+    pub async fn get_shared_data(&self, share_id: &str) -> ApiResult<UserData> {
+        let encoded = urlencoding::encode(share_id);
+        self.get_json(&format!("/api/shares/received/{encoded}/data")).await
+    }
+
+    pub async fn get_shared_reference(&self, share_id: &str) -> ApiResult<ReferenceDatabase> {
+        let encoded = urlencoding::encode(share_id);
+        self.get_json(&format!("/api/shares/received/{encoded}/reference")).await
+    }
+
+    /// Adds a reference value (admins only). The server derives the id from the name.
+    pub async fn create_reference_value(&self, value: &ReferenceValue) -> ApiResult<ReferenceValue> {
+        let request = self
             .client
-            .get(self.url("/api/ai/history"))
-            .header("Authorization", self.auth_header())
-            .send()
-            .await?;
+            .post(self.url("/api/admin/reference"))
+            .timeout(self.options.timeout())
+            .json(value);
+        self.send_json(request).await
+    }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to fetch chat history: HTTP {}", resp.status()));
-        }
-        Ok(resp.json().await?)
+    pub async fn update_reference_value(&self, value: &ReferenceValue) -> ApiResult<ReferenceValue> {
+        let encoded = urlencoding::encode(&value.id);
+        let request = self
+            .client
+            .put(self.url(&format!("/api/admin/reference/{encoded}")))
+            .timeout(self.options.timeout())
+            .json(value);
+        self.send_json(request).await
     }
 
-    pub async fn clear_chat_history(&self) -> Result<()> {
-        let resp = self
+    pub async fn delete_reference_value(&self, id: &str) -> ApiResult<()> {
+        let encoded = urlencoding::encode(id);
+        let request = self
             .client
-            .delete(self.url("/api/ai/history"))
-            .header("Authorization", self.auth_header())
-            .send()
-            .await?;
+            .delete(self.url(&format!("/api/admin/reference/{encoded}")))
+            .timeout(self.options.timeout());
+        self.send(request).await?;
+        Ok(())
+    }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to clear chat history: HTTP {}", resp.status()));
-        }
+    pub async fn get_chat_history(&self) -> ApiResult<ChatHistory> {
+        self.get_json("/api/ai/history").await
+    }
+
+    pub async fn clear_chat_history(&self) -> ApiResult<()> {
+        let request = self
+            .client
+            .delete(self.url("/api/ai/history"))
+            .timeout(self.options.timeout());
+        self.send(request).await?;
         Ok(())
     }
 
-    pub async fn send_chat(&self, message: &str) -> Result<ChatResponse> {
-        let resp = self
+    pub async fn send_chat(&self, message: &str) -> ApiResult<ChatResponse> {
+        let request = self
             .client
             .post(self.url("/api/ai/chat"))
-            .header("Authorization", self.auth_header())
-            .json(&json!({ "message": message }))
-            .send()
-            .await?;
+            .timeout(self.options.ai_timeout())
+            .json(&json!({ "message": message }));
+        self.send_json(request).await
+    }
 
-        match resp.status() {
-            StatusCode::TOO_MANY_REQUESTS => {
-                Err(anyhow!("RATE_LIMIT: Tägliches Limit erreicht (50 Anfragen/Tag). Bitte versuche es morgen wieder."))
+    /// Sends a chat message and reports the answer token by token through
+    /// `on_event`. Falls back to [`ApiClient::send_chat`] on servers without
+    /// the streaming endpoint. Dropping the future cancels generation.
+    pub async fn send_chat_stream(
+        &self,
+        message: &str,
+        mut on_event: impl FnMut(ChatStreamEvent),
+    ) -> ApiResult<()> {
+        let request = self
+            .client
+            .post(self.url("/api/ai/chat/stream"))
+            .header("Accept", "text/event-stream")
+            .json(&json!({ "message": message }));
+        // No request timeout: it would cover the whole stream, and a long
+        // answer may take longer than that. Each wait is limited instead.
+        let sent = tokio::time::timeout(self.options.ai_timeout(), self.send(request))
+            .await
+            .map_err(|_| ApiError::Timeout)?;
+        let mut resp = match sent {
+            Err(ApiError::NotFound(_)) => {
+                let chat = self.send_chat(message).await?;
+                on_event(ChatStreamEvent::Token(chat.message.content.clone()));
+                on_event(ChatStreamEvent::Done(chat));
+                return Ok(());
             }
-            s if !s.is_success() => {
-                Err(anyhow!("Chat request failed: HTTP {s}"))
+            result => result?,
+        };
+
+        #[derive(Deserialize)]
+        struct TokenData {
+            text: String,
+        }
+        #[derive(Deserialize)]
+        struct ErrorData {
+            error: String,
+        }
+
+        let mut parser = SseParser::default();
+        while let Some(chunk) = tokio::time::timeout(self.options.ai_timeout(), resp.chunk())
+            .await
+            .map_err(|_| ApiError::Timeout)??
+        {
+            for event in parser.push(&chunk) {
+                match event.event.as_str() {
+                    "token" => {
+                        let token: TokenData = serde_json::from_str(&event.data)?;
+                        on_event(ChatStreamEvent::Token(token.text));
+                    }
+                    "done" => {
+                        let done: ChatResponse = serde_json::from_str(&event.data)?;
+                        on_event(ChatStreamEvent::Done(done));
+                        return Ok(());
+                    }
+                    "error" => {
+                        let err: ErrorData = serde_json::from_str(&event.data)?;
+                        return Err(ApiError::ServerError {
+                            status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                            message: err.error,
+                        });
+                    }
+                    _ => {}
+                }
             }
-            _ => Ok(resp.json().await?),
         }
+
+        Err(ApiError::Network("Chat-Stream wurde unerwartet beendet".to_string()))
+    }
+
+    /// Uploads a photo or PDF of a lab report and returns the values the LLM
+    /// extracted. An unreadable report is a [`ApiError::Validation`].
+    pub async fn scan_report(&self, file_name: &str, mime_type: &str, data: Vec<u8>) -> ApiResult<ScanResult> {
+        let part = Part::bytes(data)
+            .file_name(file_name.to_string())
+            .mime_str(mime_type)
+            .map_err(|e| ApiError::Validation(e.to_string()))?;
+        let form = Form::new().part("file", part);
+
+        let request = self
+            .client
+            .post(self.url("/api/ai/scan"))
+            .timeout(self.options.ai_timeout())
+            .multipart(form);
+        self.send_json(request).await
     }
 }
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Local, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Everything an [`ApiClient`](super::ApiClient) call can fail with.
+/// `Display` gives a German message that can be shown as is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// 401: token missing, revoked or expired
+    Unauthorized,
+    /// 403: the token is valid but may not do this
+    Forbidden(String),
+    /// 429: daily AI limit reached
+    RateLimited { reset_at: Option<DateTime<Utc>> },
+    /// 404
+    NotFound(String),
+    /// 400, 409, 410, 422: the server rejected the input, with its message
+    Validation(String),
+    /// No HTTP answer at all (server unreachable, DNS, TLS, connection reset)
+    Network(String),
+    /// No answer within the configured timeout
+    Timeout,
+    /// 5xx, or a response that could not be read
+    ServerError { status: u16, message: String },
+}
+
+/// Body of the backend's error responses.
+#[derive(Deserialize, Default)]
+struct ErrorBody {
+    error: Option<String>,
+    message: Option<String>,
+    #[serde(rename = "resetAt")]
+    reset_at: Option<DateTime<Utc>>,
+}
+
+impl ApiError {
+    /// Maps a non-success response to an error, reading the server's
+    /// message from the JSON body if there is one.
+    pub async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let retry_after = retry_after(resp.headers());
+        let body: ErrorBody = resp.json().await.unwrap_or_default();
+        Self::from_parts(status, retry_after, body)
+    }
+
+    fn from_parts(status: StatusCode, retry_after: Option<DateTime<Utc>>, body: ErrorBody) -> Self {
+        let message = body
+            .message
+            .or(body.error)
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or("Unbekannter Fehler").to_string());
+
+        match status {
+            StatusCode::UNAUTHORIZED => ApiError::Unauthorized,
+            StatusCode::FORBIDDEN => ApiError::Forbidden(message),
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited {
+                reset_at: body.reset_at.or(retry_after),
+            },
+            StatusCode::NOT_FOUND => ApiError::NotFound(message),
+            StatusCode::BAD_REQUEST
+            | StatusCode::CONFLICT
+            | StatusCode::GONE
+            | StatusCode::UNPROCESSABLE_ENTITY => ApiError::Validation(message),
+            s => ApiError::ServerError { status: s.as_u16(), message },
+        }
+    }
+
+    /// The request never got an answer; cached data may be shown instead.
+    pub fn is_offline(&self) -> bool {
+        matches!(self, ApiError::Network(_) | ApiError::Timeout)
+    }
+
+    /// Worth trying again for idempotent requests.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Network(_) | ApiError::Timeout => true,
+            ApiError::ServerError { status, .. } => matches!(status, 502..=504),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ApiError::Timeout
+        } else if e.is_decode() {
+            ApiError::ServerError {
+                status: e.status().map_or(200, |s| s.as_u16()),
+                message: format!("Ungültige Antwort: {e}"),
+            }
+        } else if let Some(status) = e.status() {
+            Self::from_parts(status, None, ErrorBody::default())
+        } else {
+            ApiError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::ServerError { status: 200, message: format!("Ungültige Antwort: {e}") }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unauthorized => {
+                write!(f, "Der API-Token ist ungültig oder abgelaufen.")
+            }
+            ApiError::Forbidden(msg) => write!(f, "Keine Berechtigung: {msg}"),
+            ApiError::RateLimited { reset_at: Some(at) } => write!(
+                f,
+                "Tägliches Limit erreicht (50 Anfragen/Tag). Wieder möglich ab {}.",
+                at.with_timezone(&Local).format("%d.%m.%Y %H:%M")
+            ),
+            ApiError::RateLimited { reset_at: None } => write!(
+                f,
+                "Tägliches Limit erreicht (50 Anfragen/Tag). Bitte versuche es morgen wieder."
+            ),
+            ApiError::NotFound(msg) => write!(f, "Nicht gefunden: {msg}"),
+            ApiError::Validation(msg) => write!(f, "{msg}"),
+            ApiError::Network(msg) => write!(f, "Server nicht erreichbar: {msg}"),
+            ApiError::Timeout => write!(f, "Zeitüberschreitung: Der Server hat nicht rechtzeitig geantwortet."),
+            ApiError::ServerError { status, message } => {
+                write!(f, "Serverfehler (HTTP {status}): {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// `Retry-After` as either delay seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<i64>() {
+        return Some(Utc::now() + chrono::Duration::seconds(secs));
+    }
+    DateTime::parse_from_rfc2822(value).ok().map(|d| d.with_timezone(&Utc))
+}
+
+/// The [`ApiError`] behind an `anyhow` error, if any.
+pub fn api_error(e: &anyhow::Error) -> Option<&ApiError> {
+    e.downcast_ref::<ApiError>()
+}
@@ -1,5 +1,8 @@
 pub mod client;
+pub mod error;
+pub mod sse;
 pub mod types;
 
 pub use client::ApiClient;
+pub use error::{ApiError, ApiResult};
 pub use types::*;
@@ -0,0 +1,118 @@
+/// One server-sent event.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies that arrive in arbitrary
+/// chunks (an event, or even a UTF-8 sequence, may be split across chunks).
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    /// Feeds a chunk and returns all events completed by it.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend(chunk.iter().filter(|&&b| b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            if let Some(event) = parse_block(&String::from_utf8_lossy(&block)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+fn parse_block(block: &str) -> Option<SseEvent> {
+    let mut event = SseEvent {
+        event: "message".to_string(),
+        data: String::new(),
+    };
+    let mut has_data = false;
+
+    for line in block.lines() {
+        if line.starts_with(':') {
+            continue; // comment / keep-alive
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => event.event = value.to_string(),
+            "data" => {
+                if has_data {
+                    event.data.push('\n');
+                }
+                event.data.push_str(value);
+                has_data = true;
+            }
+            _ => {}
+        }
+    }
+
+    has_data.then_some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, data: &str) -> SseEvent {
+        SseEvent { event: event.to_string(), data: data.to_string() }
+    }
+
+    #[test]
+    fn events_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(b"event: tok"), []);
+        assert_eq!(parser.push(b"en\ndata: {\"text\":\"Hal"), []);
+        assert_eq!(parser.push(b"lo\"}\n"), []);
+        assert_eq!(
+            parser.push(b"\ndata: zwei\n\nda"),
+            [event("token", "{\"text\":\"Hallo\"}"), event("message", "zwei")]
+        );
+        assert_eq!(parser.push(b"ta: drei\n\n"), [event("message", "drei")]);
+    }
+
+    #[test]
+    fn utf8_split_across_chunks() {
+        let bytes = "data: Blutzuckermessgerät\n\n".as_bytes();
+        let split = bytes.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(&bytes[..split]), []);
+        assert_eq!(parser.push(&bytes[split..]), [event("message", "Blutzuckermessgerät")]);
+    }
+
+    #[test]
+    fn multi_line_data_is_joined_with_newlines() {
+        let mut parser = SseParser::default();
+        assert_eq!(
+            parser.push(b"event: token\ndata: erste\ndata:zweite\ndata:\n\n"),
+            [event("token", "erste\nzweite\n")]
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(b"event: done\r\ndata: {}\r\n\r"), []);
+        assert_eq!(parser.push(b"\ndata: x\r\n\r\n"), [event("done", "{}"), event("message", "x")]);
+    }
+
+    #[test]
+    fn comments_and_events_without_data_are_skipped() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push(b": keep-alive\n\n"), []);
+        assert_eq!(parser.push(b"event: ping\n\n"), []);
+        assert_eq!(
+            parser.push(b": comment\nevent: token\n: between\ndata: a\nid: 7\n\n"),
+            [event("token", "a")]
+        );
+    }
+}
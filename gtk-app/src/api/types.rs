@@ -1,8 +1,9 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 // ─── Auth ─────────────────────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct AuthUser {
     pub authenticated: bool,
     #[serde(rename = "userId")]
@@ -17,17 +18,22 @@ pub struct AuthUser {
 
 // ─── Blood Values ──────────────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct BloodValue {
     pub name: String,
     pub value: f64,
     pub unit: String,
     pub category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub short_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub long_name: Option<String>,
+    /// Calculated by the app from other values, never sent to the server
+    #[serde(skip)]
+    pub derived: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct BloodEntry {
     pub id: String,
     pub date: String,
@@ -36,43 +42,244 @@ pub struct BloodEntry {
     pub values: Vec<BloodValue>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Request body for creating or updating an entry (the server assigns the id).
+#[derive(Debug, Clone, Serialize)]
+pub struct BloodEntryInput {
+    pub date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lab_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub values: Vec<BloodValue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct UserData {
     pub user_id: String,
     pub display_name: String,
+    /// Not included in data shared by other users
+    #[serde(default)]
     pub email: String,
     pub gender: Option<String>,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    pub birth_date: Option<String>,
+    #[serde(default)]
+    pub diagnoses: Vec<String>,
+    #[serde(default)]
+    pub medications: Vec<String>,
+    #[serde(default)]
+    pub lifestyle: Option<Lifestyle>,
     pub entries: Vec<BloodEntry>,
 }
 
-// ─── Reference Values ─────────────────────────────────────────────────────────
+impl UserData {
+    pub fn health_profile(&self) -> HealthProfile {
+        HealthProfile {
+            gender: self.gender.clone(),
+            birth_date: self.birth_date.clone(),
+            diagnoses: self.diagnoses.clone(),
+            medications: self.medications.clone(),
+            lifestyle: self.lifestyle.clone(),
+        }
+    }
+
+    pub fn apply_health_profile(&mut self, profile: HealthProfile) {
+        self.gender = profile.gender;
+        self.birth_date = profile.birth_date;
+        self.diagnoses = profile.diagnoses;
+        self.medications = profile.medications;
+        self.lifestyle = profile.lifestyle;
+    }
+}
 
+// ─── Health Profile ───────────────────────────────────────────────────────────
+
+/// Lifestyle answers; the values are the API's enum strings
+/// (e.g. `smoking: "former"`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Lifestyle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoking: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alcohol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exercise: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sleep_hours: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stress_level: Option<String>,
+}
+
+/// Body and answer of `PATCH /api/user/profile`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct HealthProfile {
+    /// "male" or "female"; the server keeps the old value if absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    /// YYYY-MM-DD; sent as `null` to remove it
+    #[serde(default)]
+    pub birth_date: Option<String>,
+    #[serde(default)]
+    pub diagnoses: Vec<String>,
+    #[serde(default)]
+    pub medications: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lifestyle: Option<Lifestyle>,
+}
+
+// ─── API Tokens ───────────────────────────────────────────────────────────────
+
+/// An API token as listed by the server, without its value.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    /// The token this request was made with (older servers omit it)
+    #[serde(default)]
+    pub current: bool,
+}
+
+/// A newly created token; the value is only returned this once.
 #[derive(Debug, Clone, Deserialize)]
+pub struct CreatedApiToken {
+    pub id: String,
+    pub name: String,
+    pub token: String,
+    pub created_at: String,
+}
+
+// ─── Shares ───────────────────────────────────────────────────────────────────
+
+/// Read access to my values that I gave to another user.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Share {
+    pub id: String,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub shared_with_email: String,
+    pub shared_with_user_id: Option<String>,
+    pub permission: String,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}
+
+/// Request body for creating a share.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareInput {
+    pub email: String,
+    /// RFC 3339 in UTC; no expiry if absent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+/// Someone who shares their values with me (read-only).
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ReceivedShare {
+    pub share_id: String,
+    pub owner_user_id: String,
+    pub owner_display_name: String,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}
+
+// ─── Scan Import ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtractedBloodValue {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+    pub category: Option<String>,
+    pub short_name: Option<String>,
+    pub long_name: Option<String>,
+    pub ref_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanResult {
+    pub date: Option<String>,
+    pub lab_name: Option<String>,
+    pub values: Vec<ExtractedBloodValue>,
+}
+
+// ─── Reference Values ─────────────────────────────────────────────────────────
+
+/// Options are left out when serializing, since the admin API rejects `null`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 pub struct ReferenceValue {
     pub id: String,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub short_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub long_name: Option<String>,
     pub aliases: Vec<String>,
     pub category: String,
     pub unit: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_min_female: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_max_female: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_min_male: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_max_male: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub optimal_min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub optimal_max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub critical_low: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub critical_high: Option<f64>,
     pub description: String,
     pub high_info: String,
     pub low_info: String,
     pub recommendations: String,
+    /// Ranges that differ by age, e.g. for children or the elderly
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub age_ranges: Vec<AgeRange>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Reference range of an age band. Bounds left out fall back to the
+/// general ones of the reference value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct AgeRange {
+    /// Years, inclusive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_age: Option<f64>,
+    /// Years, exclusive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_min_female: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_max_female: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_min_male: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_max_male: Option<f64>,
+}
+
+impl AgeRange {
+    pub fn contains(&self, age: f64) -> bool {
+        self.min_age.is_none_or(|min| age >= min) && self.max_age.is_none_or(|max| age < max)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ReferenceDatabase {
     pub version: String,
     pub updated: String,
@@ -81,9 +288,36 @@ pub struct ReferenceDatabase {
 
 // ─── Value Status ─────────────────────────────────────────────────────────────
 
+/// How values within the reference range are graded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvaluationMode {
+    /// The outer 10% of the reference range are "Grenzwertig"
+    #[default]
+    Reference,
+    /// Optimal ranges, where a value has one, split the reference range
+    /// into optimal and suboptimal
+    Optimal,
+}
+
+impl EvaluationMode {
+    pub const ALL: [EvaluationMode; 2] = [EvaluationMode::Reference, EvaluationMode::Optimal];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EvaluationMode::Reference => "Referenzbereich",
+            EvaluationMode::Optimal => "Optimalbereich",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValueStatus {
     Normal,
+    /// Within the optimal range
+    Optimal,
+    /// Within the reference range, but outside the optimal range
+    Suboptimal,
     Warning,
     High,
     Low,
@@ -96,6 +330,8 @@ impl ValueStatus {
     pub fn label(&self) -> &'static str {
         match self {
             ValueStatus::Normal => "Normal",
+            ValueStatus::Optimal => "Optimal",
+            ValueStatus::Suboptimal => "Suboptimal",
             ValueStatus::Warning => "Grenzwertig",
             ValueStatus::High => "Erhöht",
             ValueStatus::Low => "Erniedrigt",
@@ -108,7 +344,8 @@ impl ValueStatus {
     /// RGB color for status indicator
     pub fn color(&self) -> (f64, f64, f64) {
         match self {
-            ValueStatus::Normal => (0.133, 0.773, 0.369),      // green-500
+            ValueStatus::Normal | ValueStatus::Optimal => (0.133, 0.773, 0.369), // green-500
+            ValueStatus::Suboptimal => (0.918, 0.702, 0.031), // yellow-500
             ValueStatus::Warning => (0.961, 0.620, 0.043),     // amber-500
             ValueStatus::High | ValueStatus::Low => (0.937, 0.267, 0.267), // red-500
             ValueStatus::CriticalHigh | ValueStatus::CriticalLow => (0.498, 0.110, 0.110), // red-800
@@ -117,26 +354,30 @@ impl ValueStatus {
     }
 }
 
+/// Normal range for the gender and age (in years). An age band that
+/// contains `age` takes precedence over the general range; bounds it leaves
+/// out fall back to the general ones.
 pub fn get_effective_range(
     ref_val: &ReferenceValue,
     gender: Option<&str>,
+    age: Option<f64>,
 ) -> (Option<f64>, Option<f64>) {
-    let mut min = ref_val.ref_min;
-    let mut max = ref_val.ref_max;
+    let pick = |general: Option<f64>, female: Option<f64>, male: Option<f64>| match gender {
+        Some("female") => female.or(general),
+        Some("male") => male.or(general),
+        _ => general,
+    };
 
-    if gender == Some("female") {
-        if ref_val.ref_min_female.is_some() {
-            min = ref_val.ref_min_female;
-        }
-        if ref_val.ref_max_female.is_some() {
-            max = ref_val.ref_max_female;
-        }
-    } else if gender == Some("male") {
-        if ref_val.ref_min_male.is_some() {
-            min = ref_val.ref_min_male;
+    let mut min = pick(ref_val.ref_min, ref_val.ref_min_female, ref_val.ref_min_male);
+    let mut max = pick(ref_val.ref_max, ref_val.ref_max_female, ref_val.ref_max_male);
+
+    let band = age.and_then(|age| ref_val.age_ranges.iter().find(|r| r.contains(age)));
+    if let Some(band) = band {
+        if let Some(band_min) = pick(band.ref_min, band.ref_min_female, band.ref_min_male) {
+            min = Some(band_min);
         }
-        if ref_val.ref_max_male.is_some() {
-            max = ref_val.ref_max_male;
+        if let Some(band_max) = pick(band.ref_max, band.ref_max_female, band.ref_max_male) {
+            max = Some(band_max);
         }
     }
 
@@ -147,6 +388,8 @@ pub fn get_value_status(
     value: f64,
     ref_val: &ReferenceValue,
     gender: Option<&str>,
+    age: Option<f64>,
+    mode: EvaluationMode,
 ) -> ValueStatus {
     if let Some(cl) = ref_val.critical_low {
         if value <= cl {
@@ -159,7 +402,11 @@ pub fn get_value_status(
         }
     }
 
-    let (min, max) = get_effective_range(ref_val, gender);
+    let (min, max) = get_effective_range(ref_val, gender, age);
+
+    if mode == EvaluationMode::Optimal && (ref_val.optimal_min.is_some() || ref_val.optimal_max.is_some()) {
+        return get_optimal_status(value, ref_val, min, max);
+    }
 
     match (min, max) {
         (Some(min), Some(max)) => {
@@ -194,6 +441,72 @@ pub fn get_value_status(
     }
 }
 
+/// Status by the optimal range, for a value that is not critical. Outside
+/// the reference range it is still high or low.
+fn get_optimal_status(
+    value: f64,
+    ref_val: &ReferenceValue,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> ValueStatus {
+    if min.is_some_and(|min| value < min) {
+        return ValueStatus::Low;
+    }
+    if max.is_some_and(|max| value > max) {
+        return ValueStatus::High;
+    }
+    let below = ref_val.optimal_min.is_some_and(|o| value < o);
+    let above = ref_val.optimal_max.is_some_and(|o| value > o);
+    if below || above {
+        ValueStatus::Suboptimal
+    } else {
+        ValueStatus::Optimal
+    }
+}
+
+/// What values are judged by: the patient's gender and date of birth and
+/// the evaluation mode. Ranges depend on the age on the measurement date.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Evaluation {
+    pub gender: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub mode: EvaluationMode,
+}
+
+impl Evaluation {
+    /// `birth_date` as YYYY-MM-DD; an invalid one counts as unknown.
+    pub fn new(gender: Option<String>, birth_date: Option<&str>, mode: EvaluationMode) -> Self {
+        Self {
+            gender,
+            birth_date: birth_date.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+            mode,
+        }
+    }
+
+    pub fn gender(&self) -> Option<&str> {
+        self.gender.as_deref()
+    }
+
+    /// Age in years on `date` (YYYY-MM-DD, a time part is ignored);
+    /// `None` without a date of birth.
+    pub fn age_on(&self, date: &str) -> Option<f64> {
+        let birth = self.birth_date?;
+        let date = NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()?;
+        let days = (date - birth).num_days();
+        (days >= 0).then(|| days as f64 / 365.25)
+    }
+
+    /// Normal range of `ref_val` for a measurement on `date`.
+    pub fn range(&self, ref_val: &ReferenceValue, date: &str) -> (Option<f64>, Option<f64>) {
+        get_effective_range(ref_val, self.gender(), self.age_on(date))
+    }
+
+    /// Status of a measurement on `date`.
+    pub fn status(&self, value: f64, ref_val: &ReferenceValue, date: &str) -> ValueStatus {
+        get_value_status(value, ref_val, self.gender(), self.age_on(date), self.mode)
+    }
+}
+
 pub fn get_trend(history: &[ValueHistoryPoint]) -> Option<Trend> {
     if history.len() < 2 {
         return None;
@@ -222,13 +535,16 @@ pub enum Trend {
 
 // ─── History ──────────────────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct ValueHistoryPoint {
     pub date: String,
     pub value: f64,
     pub unit: String,
     #[serde(rename = "entryId")]
     pub entry_id: String,
+    /// See [`BloodValue::derived`]
+    #[serde(skip)]
+    pub derived: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -259,3 +575,53 @@ pub struct ChatResponse {
     #[serde(rename = "userMessage")]
     pub user_message: ChatMessage,
 }
+
+/// Progress of a streamed chat answer.
+#[derive(Debug, Clone)]
+pub enum ChatStreamEvent {
+    /// Next fragment of the assistant's answer
+    Token(String),
+    /// Answer complete and stored on the server
+    Done(ChatResponse),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(id: &str) -> ReferenceValue {
+        let db: ReferenceDatabase =
+            serde_json::from_str(include_str!("../../../data/reference_values.json")).expect("reference database");
+        db.values.into_iter().find(|r| r.id == id).expect("reference value")
+    }
+
+    #[test]
+    fn ferritin_depends_on_age_and_sex() {
+        let ferritin = reference("ferritin");
+        let range = |gender, age| get_effective_range(&ferritin, gender, Some(age));
+
+        // Children and adolescents, both sexes
+        assert_eq!(range(Some("female"), 10.0), (Some(7.0), Some(140.0)));
+        assert_eq!(range(Some("male"), 17.9), (Some(7.0), Some(140.0)));
+        // Adults from 18 (inclusive)
+        assert_eq!(range(Some("female"), 18.0), (Some(12.0), Some(150.0)));
+        assert_eq!(range(Some("male"), 18.0), (Some(30.0), Some(400.0)));
+        assert_eq!(range(Some("female"), 49.9), (Some(12.0), Some(150.0)));
+        // Women after menopause from 50; men keep the adult range
+        assert_eq!(range(Some("female"), 50.0), (Some(15.0), Some(300.0)));
+        assert_eq!(range(Some("male"), 70.0), (Some(30.0), Some(400.0)));
+        assert_eq!(range(None, 70.0), (Some(12.0), Some(400.0)));
+        // Without an age the adult range applies
+        assert_eq!(get_effective_range(&ferritin, Some("female"), None), (Some(12.0), Some(150.0)));
+    }
+
+    #[test]
+    fn age_bands_are_inclusive_below_and_exclusive_above() {
+        let band = AgeRange { min_age: Some(18.0), max_age: Some(50.0), ..Default::default() };
+        assert!(!band.contains(17.99));
+        assert!(band.contains(18.0));
+        assert!(band.contains(49.99));
+        assert!(!band.contains(50.0));
+        assert!(AgeRange::default().contains(0.0));
+    }
+}
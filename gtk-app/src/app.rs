@@ -1,9 +1,10 @@
 use libadwaita::prelude::*;
 use libadwaita as adw;
 
-use crate::config::{load_config, Config};
-use crate::state::init_tokio;
-use crate::ui::{setup_dialog::show_setup_dialog, window::build_ui};
+use crate::config::{load_config, load_tokens, save_config, Config};
+use crate::secrets::forget_passphrase;
+use crate::state::{init_tokio, spawn_task};
+use crate::ui::{passphrase_dialog::with_token_store, setup_dialog::show_setup_dialog, window::build_ui};
 
 pub fn run() -> glib::ExitCode {
     init_tokio();
@@ -22,24 +23,115 @@ pub fn run() -> glib::ExitCode {
 fn activate(app: &adw::Application) {
     let config = load_config().unwrap_or_default();
 
-    if config.is_configured() {
-        build_ui(app, config);
-    } else {
-        // Show setup dialog in a minimal window
-        let window = adw::ApplicationWindow::new(app);
-        window.set_title(Some("Blutwerte"));
-        window.set_default_size(480, 400);
-        window.set_visible(false); // hidden backdrop for dialog
+    // The secret store is asked off the main thread; the hold keeps the
+    // application running until a window is shown
+    let hold = app.hold();
+    let (tx, rx) = async_channel::bounded::<(Config, bool)>(1);
+    spawn_task(async move {
+        let needs_passphrase = config.needs_passphrase().await;
+        tx.send((config, needs_passphrase)).await.ok();
+    });
 
-        let placeholder = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
-        window.set_content(Some(&placeholder));
-        window.present();
+    let app = app.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let _hold = hold;
+        let Ok((config, needs_passphrase)) = rx.recv().await else { return };
+        if !needs_passphrase {
+            start(&app, config);
+            return;
+        }
 
+        // No keyring: unlock the encrypted token file first
+        let window = backdrop_window(&app);
         let app_clone = app.clone();
         let window_clone = window.clone();
-        show_setup_dialog(&window, "", "", move |saved_config| {
-            build_ui(&app_clone, saved_config);
+        with_token_store(&window, &config.clone(), move || {
+            start(&app_clone, config.clone());
             window_clone.close();
         });
-    }
+    });
+}
+
+fn start(app: &adw::Application, mut config: Config) {
+    let hold = app.hold();
+    let (tx, rx) = async_channel::bounded::<(Config, anyhow::Result<()>)>(1);
+    spawn_task(async move {
+        // Old config files keep the token in plain text
+        if config.has_unstored_tokens() {
+            if let Err(e) = save_config(&mut config).await {
+                eprintln!("Failed to move API tokens into the secret store: {e}");
+            }
+        }
+        let result = load_tokens(&mut config).await;
+        tx.send((config, result)).await.ok();
+    });
+
+    let app = app.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let _hold = hold;
+        let Ok((config, result)) = rx.recv().await else { return };
+
+        // A locked keyring or wrong passphrase must not look like a missing
+        // token: the setup dialog would then overwrite the profile
+        if let Err(e) = result {
+            eprintln!("Failed to load API tokens: {e:#}");
+            show_token_error(&app, &e);
+            return;
+        }
+
+        if config.is_configured() {
+            build_ui(&app, config);
+        } else {
+            // Show setup dialog in a minimal window
+            let window = backdrop_window(&app);
+
+            let app_clone = app.clone();
+            let window_clone = window.clone();
+            show_setup_dialog(&window, config, move |saved_config| {
+                build_ui(&app_clone, saved_config);
+                window_clone.close();
+            });
+        }
+    });
+}
+
+/// Shows why the tokens could not be read and starts over on "Erneut
+/// versuchen", asking for the passphrase again if the file store is used.
+fn show_token_error(app: &adw::Application, error: &anyhow::Error) {
+    let window = backdrop_window(app);
+    let alert = adw::AlertDialog::new(
+        Some("API-Tokens konnten nicht geladen werden"),
+        Some(&format!(
+            "Der Schlüsselbund ist gesperrt oder die Passphrase der Token-Datei ist falsch. \
+             Entsperre den Schlüsselbund und versuche es erneut.\n\n{error:#}"
+        )),
+    );
+    alert.add_response("quit", "Beenden");
+    alert.add_response("retry", "Erneut versuchen");
+    alert.set_response_appearance("retry", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("retry"));
+    alert.set_close_response("quit");
+
+    let app = app.clone();
+    let window_clone = window.clone();
+    alert.connect_response(None, move |_, response| {
+        if response == "retry" {
+            forget_passphrase();
+            activate(&app);
+        }
+        window_clone.close();
+    });
+    alert.present(Some(&window));
+}
+
+fn backdrop_window(app: &adw::Application) -> adw::ApplicationWindow {
+    let window = adw::ApplicationWindow::new(app);
+    window.set_title(Some("Blutwerte"));
+    window.set_default_size(480, 400);
+    window.set_visible(false); // hidden backdrop for dialog
+
+    let placeholder = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    window.set_content(Some(&placeholder));
+    window.present();
+    window
 }
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::api::types::*;
+use crate::config::Profile;
+use crate::state::DataBundle;
+
+/// Last successful load, used to start the app without a network connection.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub saved_at: DateTime<Utc>,
+    pub server_url: String,
+    #[serde(default)]
+    pub profile: String,
+    pub user: AuthUser,
+    pub user_data: UserData,
+    pub reference_db: ReferenceDatabase,
+}
+
+impl Snapshot {
+    pub fn into_bundle(self) -> DataBundle {
+        DataBundle {
+            user: self.user,
+            user_data: self.user_data,
+            reference_db: self.reference_db,
+        }
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    let base = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join("blutwerte-gtk")
+}
+
+/// One snapshot per profile and server, so switching profiles never shows
+/// foreign data.
+pub fn snapshot_path(profile: &Profile) -> PathBuf {
+    let key: String = format!("{}-{}", profile.name, profile.server_url.trim_end_matches('/'))
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    cache_dir().join(format!("snapshot-{key}.json"))
+}
+
+pub fn save_snapshot(profile: &Profile, bundle: &DataBundle) -> Result<()> {
+    #[derive(Serialize)]
+    struct SnapshotRef<'a> {
+        saved_at: DateTime<Utc>,
+        server_url: &'a str,
+        profile: &'a str,
+        user: &'a AuthUser,
+        user_data: &'a UserData,
+        reference_db: &'a ReferenceDatabase,
+    }
+
+    let path = snapshot_path(profile);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create cache directory")?;
+    }
+
+    let content = serde_json::to_vec(&SnapshotRef {
+        saved_at: Utc::now(),
+        server_url: &profile.server_url,
+        profile: &profile.name,
+        user: &bundle.user,
+        user_data: &bundle.user_data,
+        reference_db: &bundle.reference_db,
+    })
+    .context("Failed to serialize snapshot")?;
+
+    // Write to a temp file first so a crash never leaves a truncated snapshot
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)
+        .with_context(|| format!("Failed to write snapshot to {:?}", tmp))?;
+    restrict_permissions(&tmp)?;
+    std::fs::rename(&tmp, &path)
+        .with_context(|| format!("Failed to move snapshot to {:?}", path))?;
+    Ok(())
+}
+
+pub fn load_snapshot(profile: &Profile) -> Result<Option<Snapshot>> {
+    let path = snapshot_path(profile);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read(&path)
+        .with_context(|| format!("Failed to read snapshot from {:?}", path))?;
+    let snapshot: Snapshot =
+        serde_json::from_slice(&content).context("Failed to parse snapshot")?;
+    if snapshot.server_url != profile.server_url || snapshot.profile != profile.name {
+        return Ok(None);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Health data: readable by the owner only.
+#[cfg(unix)]
+fn restrict_permissions(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to set permissions on {:?}", path))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+/// Human readable age of a snapshot, e.g. "vor 3 Stunden".
+pub fn format_age(saved_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - saved_at).num_minutes().max(0);
+    match minutes {
+        0 => "gerade eben".to_string(),
+        1 => "vor 1 Minute".to_string(),
+        m if m < 60 => format!("vor {m} Minuten"),
+        m if m < 120 => "vor 1 Stunde".to_string(),
+        m if m < 60 * 24 => format!("vor {} Stunden", m / 60),
+        m if m < 60 * 48 => "vor 1 Tag".to_string(),
+        m => format!("vor {} Tagen", m / (60 * 24)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_home;
+    use chrono::TimeZone;
+
+    fn profile(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            server_url: "https://blut.example/".to_string(),
+            api_token: "token".to_string(),
+            token: None,
+        }
+    }
+
+    fn bundle() -> DataBundle {
+        DataBundle {
+            user: AuthUser { authenticated: true, display_name: Some("Erika".into()), ..Default::default() },
+            user_data: UserData {
+                user_id: "u1".into(),
+                display_name: "Erika".into(),
+                entries: vec![BloodEntry {
+                    id: "e1".into(),
+                    date: "2025-03-01".into(),
+                    lab_name: None,
+                    notes: None,
+                    values: Vec::new(),
+                }],
+                ..Default::default()
+            },
+            reference_db: ReferenceDatabase { version: "1".into(), updated: "2025-01-01".into(), values: Vec::new() },
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let _home = temp_home();
+        let profile = profile("Privat");
+        save_snapshot(&profile, &bundle()).unwrap();
+
+        let snapshot = load_snapshot(&profile).unwrap().expect("snapshot");
+        assert_eq!(snapshot.profile, "Privat");
+        assert_eq!(snapshot.server_url, profile.server_url);
+        assert!((Utc::now() - snapshot.saved_at).num_seconds() < 60);
+        let bundle = snapshot.into_bundle();
+        assert_eq!(bundle.user.display_name.as_deref(), Some("Erika"));
+        assert_eq!(bundle.user_data.entries[0].id, "e1");
+        assert_eq!(bundle.reference_db.version, "1");
+        assert!(!snapshot_path(&profile).with_extension("json.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn snapshot_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _home = temp_home();
+        let profile = profile("Privat");
+        save_snapshot(&profile, &bundle()).unwrap();
+        let mode = std::fs::metadata(snapshot_path(&profile)).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn snapshots_are_per_profile() {
+        let _home = temp_home();
+        let work = Profile { server_url: "https://labor.example".into(), ..profile("Arbeit") };
+        assert_ne!(snapshot_path(&profile("Privat")), snapshot_path(&work));
+
+        save_snapshot(&profile("Privat"), &bundle()).unwrap();
+        assert!(load_snapshot(&work).unwrap().is_none());
+    }
+
+    #[test]
+    fn missing_snapshot_is_none() {
+        let _home = temp_home();
+        assert!(load_snapshot(&profile("Privat")).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_snapshot_is_an_error() {
+        let _home = temp_home();
+        let profile = profile("Privat");
+        save_snapshot(&profile, &bundle()).unwrap();
+        let path = snapshot_path(&profile);
+
+        let content = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(load_snapshot(&profile).is_err());
+
+        std::fs::write(&path, b"\xff\xfe not json").unwrap();
+        assert!(load_snapshot(&profile).is_err());
+
+        std::fs::write(&path, b"{}").unwrap();
+        assert!(load_snapshot(&profile).is_err());
+    }
+
+    #[test]
+    fn formats_age() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let ago = |minutes| format_age(now - chrono::Duration::minutes(minutes), now);
+        assert_eq!(ago(-5), "gerade eben");
+        assert_eq!(ago(0), "gerade eben");
+        assert_eq!(ago(1), "vor 1 Minute");
+        assert_eq!(ago(59), "vor 59 Minuten");
+        assert_eq!(ago(60), "vor 1 Stunde");
+        assert_eq!(ago(150), "vor 2 Stunden");
+        assert_eq!(ago(60 * 24), "vor 1 Tag");
+        assert_eq!(ago(60 * 24 * 3), "vor 3 Tagen");
+    }
+}
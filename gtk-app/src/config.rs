@@ -2,23 +2,183 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct Config {
+use crate::api::client::ClientOptions;
+use crate::api::types::EvaluationMode;
+use crate::secrets::{self, TokenRef};
+use crate::units::UnitSystem;
+
+/// Name given to the profile created from an old single-server config.
+pub const DEFAULT_PROFILE_NAME: &str = "Standard";
+
+/// One server account.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
     pub server_url: String,
+    /// Held in memory only; read from config.toml just to migrate old files
+    #[serde(default, skip_serializing)]
     pub api_token: String,
+    /// Where the token is stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<TokenRef>,
 }
 
-impl Config {
+impl Profile {
     pub fn is_configured(&self) -> bool {
         !self.server_url.is_empty() && !self.api_token.is_empty()
     }
 }
 
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct Config {
+    pub active_profile: String,
+    #[serde(rename = "profile")]
+    pub profiles: Vec<Profile>,
+    /// Timeouts and retries, shared by all profiles
+    pub network: ClientOptions,
+    /// Units values are displayed in
+    pub units: UnitSystem,
+    /// Whether optimal ranges grade the values
+    pub evaluation: EvaluationMode,
+    /// Tokens of profiles removed with [`Config::remove_profile`], deleted
+    /// from the secret store by [`save_config`]
+    #[serde(skip)]
+    pub removed_tokens: Vec<TokenRef>,
+}
+
+/// On-disk format, accepting both the profile list and the old
+/// top-level `server_url` / `api_token` keys.
+#[derive(Deserialize, Default)]
+struct RawConfig {
+    #[serde(default)]
+    active_profile: String,
+    #[serde(default, rename = "profile")]
+    profiles: Vec<Profile>,
+    #[serde(default)]
+    network: ClientOptions,
+    #[serde(default)]
+    units: UnitSystem,
+    #[serde(default)]
+    evaluation: EvaluationMode,
+    server_url: Option<String>,
+    api_token: Option<String>,
+}
+
+impl Config {
+    /// Sets server and token of the active profile, creating a "Standard"
+    /// profile if there is none. Other profiles and settings stay as they are.
+    pub fn set_active_server(&mut self, server_url: String, api_token: String) {
+        let name = match self.active() {
+            Some(profile) => profile.name.clone(),
+            None => {
+                self.profiles.push(Profile { name: DEFAULT_PROFILE_NAME.to_string(), ..Profile::default() });
+                DEFAULT_PROFILE_NAME.to_string()
+            }
+        };
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.name == name) {
+            profile.server_url = server_url;
+            profile.api_token = api_token;
+        }
+        self.active_profile = name;
+    }
+
+    /// Removes the profile at `index`; its token is deleted on the next save.
+    pub fn remove_profile(&mut self, index: usize) -> Option<Profile> {
+        if index >= self.profiles.len() {
+            return None;
+        }
+        let profile = self.profiles.remove(index);
+        self.removed_tokens.extend(profile.token.clone());
+        Some(profile)
+    }
+
+    /// The selected profile, or the first one if the selection is stale.
+    pub fn active(&self) -> Option<&Profile> {
+        self.profile(&self.active_profile).or_else(|| self.profiles.first())
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn set_active(&mut self, name: &str) -> bool {
+        if self.profile(name).is_none() {
+            return false;
+        }
+        self.active_profile = name.to_string();
+        true
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.active().is_some_and(Profile::is_configured)
+    }
+
+    /// Tokens that are still only in memory (or in plain text in the file).
+    pub fn has_unstored_tokens(&self) -> bool {
+        self.profiles.iter().any(|p| p.token.is_none() && !p.api_token.is_empty())
+    }
+
+    /// Whether saving or loading tokens needs the passphrase of the
+    /// encrypted token file (i.e. there is no keyring).
+    pub async fn needs_passphrase(&self) -> bool {
+        if secrets::has_passphrase() {
+            return false;
+        }
+        let uses_file = self
+            .profiles
+            .iter()
+            .any(|p| p.token.as_ref().is_some_and(|t| t.store == secrets::TokenStore::File));
+        uses_file || (self.has_unstored_tokens() && !secrets::keyring_available().await)
+    }
+}
+
+impl RawConfig {
+    fn into_config(self) -> Config {
+        let mut config = Config {
+            active_profile: self.active_profile,
+            profiles: self.profiles,
+            network: self.network,
+            units: self.units,
+            evaluation: self.evaluation,
+            removed_tokens: Vec::new(),
+        };
+
+        let legacy_url = self.server_url.unwrap_or_default();
+        let legacy_token = self.api_token.unwrap_or_default();
+        let has_legacy = !legacy_url.is_empty() || !legacy_token.is_empty();
+        if has_legacy && config.profiles.is_empty() {
+            config.profiles.push(Profile {
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                server_url: legacy_url,
+                api_token: legacy_token,
+                token: None,
+            });
+            config.active_profile = DEFAULT_PROFILE_NAME.to_string();
+            return config;
+        }
+
+        if config.profile(&config.active_profile).is_none() {
+            if let Some(first) = config.profiles.first() {
+                config.active_profile = first.name.clone();
+            }
+        }
+        config
+    }
+}
+
 pub fn config_path() -> PathBuf {
     let base = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
     base.join("blutwerte-gtk").join("config.toml")
 }
 
+/// Loads the config, converting an old single-server file into a
+/// "Standard" profile on the way. Tokens are not resolved yet, see
+/// [`load_tokens`]; plain-text tokens of old files stay in memory until
+/// the next [`save_config`] moves them to the secret store.
 pub fn load_config() -> Result<Config> {
     let path = config_path();
     if !path.exists() {
@@ -26,11 +186,51 @@ pub fn load_config() -> Result<Config> {
     }
     let content = std::fs::read_to_string(&path)
         .with_context(|| format!("Failed to read config from {:?}", path))?;
-    let config: Config = toml::from_str(&content).context("Failed to parse config.toml")?;
-    Ok(config)
+    parse_config(&content)
+}
+
+/// Fills `api_token` of every profile from the secret store.
+pub async fn load_tokens(config: &mut Config) -> Result<()> {
+    for profile in &mut config.profiles {
+        if !profile.api_token.is_empty() {
+            continue;
+        }
+        if let Some(token_ref) = &profile.token {
+            profile.api_token = secrets::load_token(token_ref)
+                .await
+                .with_context(|| format!("Failed to load token of profile {:?}", profile.name))?
+                .unwrap_or_default();
+        }
+    }
+    Ok(())
 }
 
-pub fn save_config(config: &Config) -> Result<()> {
+pub fn parse_config(content: &str) -> Result<Config> {
+    let raw: RawConfig = toml::from_str(content).context("Failed to parse config.toml")?;
+    Ok(raw.into_config())
+}
+
+/// Moves the tokens into the secret store and writes config.toml, which
+/// then only holds references to them. Only tokens of explicitly removed
+/// profiles are deleted, never ones that are just missing from `config`.
+pub async fn save_config(config: &mut Config) -> Result<()> {
+    for profile in &mut config.profiles {
+        if profile.api_token.is_empty() {
+            continue;
+        }
+        let token_ref = secrets::store_token(profile.token.as_ref(), &profile.name, &profile.api_token).await?;
+        profile.token = Some(token_ref);
+    }
+
+    for old_ref in std::mem::take(&mut config.removed_tokens) {
+        if config.profiles.iter().any(|p| p.token.as_ref() == Some(&old_ref)) {
+            continue;
+        }
+        if let Err(e) = secrets::delete_token(&old_ref).await {
+            eprintln!("Failed to delete token of removed profile: {e}");
+        }
+    }
+
     let path = config_path();
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).context("Failed to create config directory")?;
@@ -40,3 +240,155 @@ pub fn save_config(config: &Config) -> Result<()> {
         .with_context(|| format!("Failed to write config to {:?}", path))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, server_url: &str, token: Option<TokenRef>) -> Profile {
+        Profile {
+            name: name.to_string(),
+            server_url: server_url.to_string(),
+            api_token: String::new(),
+            token,
+        }
+    }
+
+    fn keyring_ref(id: &str) -> Option<TokenRef> {
+        Some(TokenRef { store: secrets::TokenStore::Keyring, id: id.to_string() })
+    }
+
+    #[test]
+    fn old_single_server_file_becomes_standard_profile() {
+        let config = parse_config(
+            r#"
+            server_url = "https://blut.example"
+            api_token = "bw_alt"
+
+            [network]
+            timeout_secs = 60
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.active_profile, DEFAULT_PROFILE_NAME);
+        assert_eq!(
+            config.profiles,
+            [Profile {
+                api_token: "bw_alt".to_string(),
+                ..profile(DEFAULT_PROFILE_NAME, "https://blut.example", None)
+            }]
+        );
+        assert_eq!(config.network.timeout_secs, 60);
+        assert_eq!(config.network.ai_timeout_secs, ClientOptions::default().ai_timeout_secs);
+        assert!(config.has_unstored_tokens());
+        assert!(config.is_configured());
+    }
+
+    #[test]
+    fn profile_file_is_read_as_is() {
+        let config = parse_config(
+            r#"
+            active_profile = "Praxis"
+            units = "si"
+            evaluation = "optimal"
+
+            [[profile]]
+            name = "Privat"
+            server_url = "https://blut.example"
+            token = { store = "keyring", id = "a1" }
+
+            [[profile]]
+            name = "Praxis"
+            server_url = "https://praxis.example"
+            token = { store = "file", id = "b2" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.active_profile, "Praxis");
+        assert_eq!(
+            config.profiles,
+            [
+                profile("Privat", "https://blut.example", keyring_ref("a1")),
+                profile(
+                    "Praxis",
+                    "https://praxis.example",
+                    Some(TokenRef { store: secrets::TokenStore::File, id: "b2".to_string() }),
+                ),
+            ]
+        );
+        assert_eq!(config.units, UnitSystem::Si);
+        assert_eq!(config.evaluation, EvaluationMode::Optimal);
+        assert_eq!(config.network, ClientOptions::default());
+        assert!(!config.has_unstored_tokens());
+        // Tokens are only known after load_tokens
+        assert!(!config.is_configured());
+    }
+
+    #[test]
+    fn missing_active_profile_falls_back_to_the_first() {
+        let config = parse_config(
+            r#"
+            active_profile = "Gelöscht"
+
+            [[profile]]
+            name = "Privat"
+            server_url = "https://blut.example"
+
+            [[profile]]
+            name = "Praxis"
+            server_url = "https://praxis.example"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.active_profile, "Privat");
+        assert_eq!(config.active().map(|p| p.name.as_str()), Some("Privat"));
+
+        let config = parse_config(r#"active_profile = "Gelöscht""#).unwrap();
+        assert!(config.profiles.is_empty());
+        assert!(config.active().is_none());
+        assert!(!config.is_configured());
+    }
+
+    #[test]
+    fn legacy_keys_are_ignored_next_to_profiles() {
+        let config = parse_config(
+            r#"
+            server_url = "https://alt.example"
+            api_token = "bw_alt"
+
+            [[profile]]
+            name = "Privat"
+            server_url = "https://blut.example"
+            token = { store = "keyring", id = "a1" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.active_profile, "Privat");
+        assert_eq!(config.profiles, [profile("Privat", "https://blut.example", keyring_ref("a1"))]);
+    }
+
+    #[test]
+    fn saved_file_holds_token_references_only() {
+        let mut config = Config::default();
+        config.set_active_server("https://blut.example".to_string(), "bw_geheim".to_string());
+        config.profiles[0].token = keyring_ref("a1");
+
+        let content = toml::to_string(&config).unwrap();
+        assert!(!content.contains("bw_geheim"));
+
+        let reloaded = parse_config(&content).unwrap();
+        assert_eq!(reloaded.active_profile, DEFAULT_PROFILE_NAME);
+        assert_eq!(
+            reloaded.profiles,
+            [profile(DEFAULT_PROFILE_NAME, "https://blut.example", keyring_ref("a1"))]
+        );
+    }
+
+    #[test]
+    fn invalid_file_is_an_error() {
+        assert!(parse_config("[[profile]]\nname = 1").is_err());
+        assert_eq!(parse_config("").unwrap(), Config::default());
+    }
+}
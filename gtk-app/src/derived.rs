@@ -0,0 +1,378 @@
+//! Values calculated from other values of the same entry: eGFR, LDL
+//! (Friedewald), non-HDL cholesterol, LDL/HDL and TG/HDL ratios, HOMA-IR
+//! and transferrin saturation.
+//!
+//! The formulas take their inputs in the units they were published in;
+//! [`derive_values`] converts the measurements accordingly.
+
+use crate::api::types::*;
+use crate::ui::dashboard::find_reference;
+use crate::units::{self, is_missing_unit};
+
+/// Category of derived values that have no reference value.
+pub const DERIVED_CATEGORY: &str = "Berechnet";
+
+/// Above this triglyceride level (mg/dL) Friedewald is not valid.
+const FRIEDEWALD_MAX_TG: f64 = 400.0;
+
+/// eGFR in mL/min/1.73 m² by the race-free CKD-EPI equation (2021).
+/// `creatinine` in mg/dL, `age` in years.
+pub fn egfr_ckd_epi_2021(creatinine: f64, age: f64, female: bool) -> Option<f64> {
+    if creatinine <= 0.0 || age < 18.0 {
+        return None;
+    }
+    let (kappa, alpha) = if female { (0.7, -0.241) } else { (0.9, -0.302) };
+    let ratio = creatinine / kappa;
+    let egfr = 142.0
+        * ratio.min(1.0).powf(alpha)
+        * ratio.max(1.0).powf(-1.200)
+        * 0.9938f64.powf(age)
+        * if female { 1.012 } else { 1.0 };
+    Some(egfr)
+}
+
+/// LDL cholesterol in mg/dL by Friedewald: TC − HDL − TG/5. `None` for
+/// triglycerides of 400 mg/dL and more, where the estimate is unreliable.
+pub fn ldl_friedewald(total: f64, hdl: f64, triglycerides: f64) -> Option<f64> {
+    if triglycerides >= FRIEDEWALD_MAX_TG {
+        return None;
+    }
+    let ldl = total - hdl - triglycerides / 5.0;
+    (ldl > 0.0).then_some(ldl)
+}
+
+/// Non-HDL cholesterol: TC − HDL, in the unit of the inputs.
+pub fn non_hdl_cholesterol(total: f64, hdl: f64) -> Option<f64> {
+    let non_hdl = total - hdl;
+    (non_hdl > 0.0).then_some(non_hdl)
+}
+
+/// `numerator / denominator`, `None` for a denominator of zero or less.
+pub fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    (denominator > 0.0).then(|| numerator / denominator)
+}
+
+/// HOMA-IR: glucose (mg/dL) × insulin (µU/mL) / 405.
+pub fn homa_ir(glucose: f64, insulin: f64) -> Option<f64> {
+    (glucose > 0.0 && insulin > 0.0).then(|| glucose * insulin / 405.0)
+}
+
+/// Transferrin saturation in %: iron (µg/dL) × 70.9 / transferrin (mg/dL).
+pub fn transferrin_saturation(iron: f64, transferrin: f64) -> Option<f64> {
+    ratio(iron * 70.9, transferrin)
+}
+
+/// Where a formula input is found in an entry.
+struct Input {
+    /// Reference value ids
+    ids: &'static [&'static str],
+    /// Names of values without a reference value
+    names: &'static [&'static str],
+    /// Unit the formula expects
+    unit: &'static str,
+}
+
+const CREATININE: Input = Input { ids: &["kreatinin"], names: &[], unit: "mg/dL" };
+const TOTAL_CHOLESTEROL: Input = Input { ids: &["cholesterin"], names: &[], unit: "mg/dL" };
+const HDL: Input = Input { ids: &["hdl_cholesterin"], names: &[], unit: "mg/dL" };
+const LDL: Input = Input { ids: &["ldl_cholesterin"], names: &[], unit: "mg/dL" };
+const TRIGLYCERIDES: Input = Input { ids: &["triglyceride"], names: &[], unit: "mg/dL" };
+const GLUCOSE: Input = Input { ids: &["blutzucker_nuechtern"], names: &[], unit: "mg/dL" };
+const INSULIN: Input = Input { ids: &["insulin"], names: &["Insulin", "Insulin nüchtern"], unit: "µU/mL" };
+const IRON: Input = Input { ids: &["eisen"], names: &[], unit: "µg/dL" };
+const TRANSFERRIN: Input = Input { ids: &["transferrin"], names: &["Transferrin"], unit: "mg/dL" };
+
+/// A derived value and how to present it.
+struct Output {
+    /// Reference value id, if the database has one
+    id: &'static str,
+    name: &'static str,
+    value: f64,
+    unit: &'static str,
+}
+
+/// The values of `entry` that can be calculated from its measurements and
+/// are not measured themselves. `age` is the patient's age in years on the
+/// entry date; eGFR needs it.
+pub fn derive_values(
+    entry: &BloodEntry,
+    reference_db: &[ReferenceValue],
+    gender: Option<&str>,
+    age: Option<f64>,
+) -> Vec<BloodValue> {
+    let get = |input: &Input| find_input(entry, reference_db, input);
+    let mut outputs = Vec::new();
+
+    if let (Some(creatinine), Some(age), Some(gender)) = (get(&CREATININE), age, gender) {
+        if let Some(egfr) = egfr_ckd_epi_2021(creatinine, age, gender == "female") {
+            outputs.push(Output { id: "egfr", name: "eGFR (CKD-EPI 2021)", value: egfr, unit: "mL/min/1,73m²" });
+        }
+    }
+
+    let total = get(&TOTAL_CHOLESTEROL);
+    let hdl = get(&HDL);
+    let tg = get(&TRIGLYCERIDES);
+    let mut ldl = get(&LDL);
+    if let (Some(total), Some(hdl), Some(tg), None) = (total, hdl, tg, ldl) {
+        ldl = ldl_friedewald(total, hdl, tg);
+        if let Some(value) = ldl {
+            outputs.push(Output { id: "ldl_cholesterin", name: "LDL-Cholesterin (Friedewald)", value, unit: "mg/dL" });
+        }
+    }
+    if let Some(value) = total.zip(hdl).and_then(|(t, h)| non_hdl_cholesterol(t, h)) {
+        outputs.push(Output { id: "non_hdl_cholesterin", name: "Non-HDL-Cholesterin", value, unit: "mg/dL" });
+    }
+    if let Some(value) = ldl.zip(hdl).and_then(|(l, h)| ratio(l, h)) {
+        outputs.push(Output { id: "ldl_hdl_ratio", name: "LDL/HDL", value, unit: "" });
+    }
+    if let Some(value) = tg.zip(hdl).and_then(|(t, h)| ratio(t, h)) {
+        outputs.push(Output { id: "tg_hdl_ratio", name: "TG/HDL", value, unit: "" });
+    }
+    if let Some(value) = get(&GLUCOSE).zip(get(&INSULIN)).and_then(|(g, i)| homa_ir(g, i)) {
+        outputs.push(Output { id: "homa_ir", name: "HOMA-IR", value, unit: "" });
+    }
+    if let Some(value) = get(&IRON).zip(get(&TRANSFERRIN)).and_then(|(fe, tf)| transferrin_saturation(fe, tf)) {
+        outputs.push(Output { id: "transferrin_saettigung", name: "Transferrinsättigung", value, unit: "%" });
+    }
+
+    outputs
+        .into_iter()
+        .filter_map(|output| to_blood_value(entry, reference_db, output))
+        .collect()
+}
+
+/// Value of `input` in `entry`, converted to the unit of the formula.
+fn find_input(entry: &BloodEntry, reference_db: &[ReferenceValue], input: &Input) -> Option<f64> {
+    entry.values.iter().filter(|bv| !bv.derived).find_map(|bv| {
+        let reference = find_reference(reference_db, &bv.name);
+        let id = reference.map(|r| r.id.as_str());
+        let matches = id.is_some_and(|id| input.ids.contains(&id))
+            || input.names.iter().any(|n| n.eq_ignore_ascii_case(&bv.name));
+        if !matches {
+            return None;
+        }
+        let unit = match reference {
+            Some(r) if is_missing_unit(&bv.unit) => r.unit.as_str(),
+            _ => bv.unit.as_str(),
+        };
+        units::convert(bv.value, unit, input.unit, id.and_then(units::analyte))
+    })
+}
+
+/// The output as a value of the entry, named and in the unit of its
+/// reference value if there is one. `None` if the entry has it measured.
+fn to_blood_value(entry: &BloodEntry, reference_db: &[ReferenceValue], output: Output) -> Option<BloodValue> {
+    let reference = reference_db.iter().find(|r| r.id == output.id);
+    let measured = entry.values.iter().any(|bv| {
+        bv.name.eq_ignore_ascii_case(output.name)
+            || reference.is_some_and(|r| find_reference(std::slice::from_ref(r), &bv.name).is_some())
+    });
+    if measured {
+        return None;
+    }
+
+    let Some(r) = reference else {
+        return Some(BloodValue {
+            name: output.name.to_string(),
+            value: output.value,
+            unit: output.unit.to_string(),
+            category: DERIVED_CATEGORY.to_string(),
+            short_name: None,
+            long_name: None,
+            derived: true,
+        });
+    };
+    let (value, unit) = match units::convert(output.value, output.unit, &r.unit, units::analyte(&r.id)) {
+        Some(value) => (value, r.unit.clone()),
+        None => (output.value, output.unit.to_string()),
+    };
+    Some(BloodValue {
+        name: r.name.clone(),
+        value,
+        unit,
+        category: r.category.clone(),
+        short_name: r.short_name.clone(),
+        long_name: r.long_name.clone(),
+        derived: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64, tolerance: f64) {
+        let actual = actual.unwrap_or_else(|| panic!("expected {expected}, got None"));
+        assert!((actual - expected).abs() <= tolerance, "expected {expected} ± {tolerance}, got {actual}");
+    }
+
+    fn reference_db() -> Vec<ReferenceValue> {
+        let db: ReferenceDatabase =
+            serde_json::from_str(include_str!("../../data/reference_values.json")).expect("reference database");
+        db.values
+    }
+
+    fn value(name: &str, value: f64, unit: &str) -> BloodValue {
+        BloodValue {
+            name: name.to_string(),
+            value,
+            unit: unit.to_string(),
+            category: String::new(),
+            short_name: None,
+            long_name: None,
+            derived: false,
+        }
+    }
+
+    fn entry(values: Vec<BloodValue>) -> BloodEntry {
+        BloodEntry { id: "e1".to_string(), date: "2025-06-01".to_string(), lab_name: None, notes: None, values }
+    }
+
+    fn derived(values: Vec<BloodValue>, gender: Option<&str>, age: Option<f64>) -> Vec<BloodValue> {
+        derive_values(&entry(values), &reference_db(), gender, age)
+    }
+
+    fn find(values: &[BloodValue], name: &str) -> Option<f64> {
+        values.iter().find(|v| v.name == name).map(|v| v.value)
+    }
+
+    // Results of the NKF CKD-EPI 2021 calculator, which rounds to whole numbers
+    #[test]
+    fn egfr_matches_ckd_epi_2021_examples() {
+        // Below kappa (0.7 female, 0.9 male)
+        assert_close(egfr_ckd_epi_2021(0.6, 50.0, true), 109.0, 0.5);
+        assert_close(egfr_ckd_epi_2021(0.7, 60.0, false), 105.0, 0.5);
+        // Above kappa
+        assert_close(egfr_ckd_epi_2021(1.2, 50.0, true), 55.0, 0.5);
+        assert_close(egfr_ckd_epi_2021(1.5, 60.0, false), 53.0, 0.5);
+        assert_close(egfr_ckd_epi_2021(1.0, 50.0, false), 92.0, 0.5);
+        // At kappa both terms are 1
+        assert_close(egfr_ckd_epi_2021(0.7, 40.0, true), 142.0 * 0.9938f64.powf(40.0) * 1.012, 1e-9);
+        assert_close(egfr_ckd_epi_2021(0.9, 40.0, false), 142.0 * 0.9938f64.powf(40.0), 1e-9);
+    }
+
+    #[test]
+    fn egfr_needs_adult_and_creatinine() {
+        assert_eq!(egfr_ckd_epi_2021(1.0, 17.9, false), None);
+        assert_eq!(egfr_ckd_epi_2021(0.0, 50.0, true), None);
+        assert_eq!(egfr_ckd_epi_2021(-1.0, 50.0, true), None);
+    }
+
+    #[test]
+    fn friedewald_ldl() {
+        assert_close(ldl_friedewald(200.0, 50.0, 150.0), 120.0, 1e-9);
+        assert_close(ldl_friedewald(250.0, 40.0, 399.0), 130.2, 1e-9);
+        assert_eq!(ldl_friedewald(250.0, 40.0, 400.0), None);
+        assert_eq!(ldl_friedewald(250.0, 40.0, 650.0), None);
+        // Implausible inputs give no negative LDL
+        assert_eq!(ldl_friedewald(100.0, 90.0, 100.0), None);
+    }
+
+    #[test]
+    fn non_hdl_and_ratios() {
+        assert_close(non_hdl_cholesterol(200.0, 50.0), 150.0, 1e-9);
+        assert_eq!(non_hdl_cholesterol(50.0, 60.0), None);
+        assert_close(ratio(120.0, 50.0), 2.4, 1e-9);
+        assert_close(ratio(150.0, 50.0), 3.0, 1e-9);
+        assert_eq!(ratio(150.0, 0.0), None);
+        assert_eq!(ratio(150.0, -1.0), None);
+    }
+
+    #[test]
+    fn homa_ir_divides_by_405() {
+        assert_close(homa_ir(90.0, 10.0), 900.0 / 405.0, 1e-9);
+        assert_close(homa_ir(100.0, 20.0), 4.938, 0.001);
+        assert_eq!(homa_ir(0.0, 10.0), None);
+        assert_eq!(homa_ir(90.0, 0.0), None);
+    }
+
+    #[test]
+    fn transferrin_saturation_uses_70_9() {
+        assert_close(transferrin_saturation(100.0, 250.0), 28.36, 1e-9);
+        assert_close(transferrin_saturation(50.0, 354.5), 10.0, 1e-9);
+        assert_eq!(transferrin_saturation(100.0, 0.0), None);
+    }
+
+    #[test]
+    fn derives_lipid_values_of_an_entry() {
+        let values = derived(
+            vec![value("Cholesterin", 200.0, "mg/dL"), value("HDL", 50.0, "mg/dL"), value("Triglyceride", 150.0, "mg/dL")],
+            None,
+            None,
+        );
+        assert!(values.iter().all(|v| v.derived));
+        assert_close(find(&values, "LDL-Cholesterin"), 120.0, 1e-9);
+        assert_close(find(&values, "Non-HDL-Cholesterin"), 150.0, 1e-9);
+        assert_close(find(&values, "LDL/HDL"), 2.4, 1e-9);
+        assert_close(find(&values, "TG/HDL"), 3.0, 1e-9);
+    }
+
+    #[test]
+    fn no_friedewald_ldl_for_high_triglycerides() {
+        let values = derived(
+            vec![value("Cholesterin", 250.0, "mg/dL"), value("HDL", 40.0, "mg/dL"), value("Triglyceride", 450.0, "mg/dL")],
+            None,
+            None,
+        );
+        assert_eq!(find(&values, "LDL-Cholesterin"), None);
+        assert_eq!(find(&values, "LDL/HDL"), None);
+        assert_close(find(&values, "Non-HDL-Cholesterin"), 210.0, 1e-9);
+        assert_close(find(&values, "TG/HDL"), 11.25, 1e-9);
+    }
+
+    #[test]
+    fn measured_ldl_is_not_replaced() {
+        let values = derived(
+            vec![
+                value("Cholesterin", 200.0, "mg/dL"),
+                value("HDL", 50.0, "mg/dL"),
+                value("Triglyceride", 150.0, "mg/dL"),
+                value("LDL", 100.0, "mg/dL"),
+            ],
+            None,
+            None,
+        );
+        assert_eq!(find(&values, "LDL-Cholesterin"), None);
+        assert_close(find(&values, "LDL/HDL"), 2.0, 1e-9);
+    }
+
+    #[test]
+    fn derives_egfr_with_age_and_gender_only() {
+        // 88.4 µmol/L = 1.0 mg/dL
+        let creatinine = || vec![value("Kreatinin", 88.4, "µmol/L")];
+        let egfr = derived(creatinine(), Some("male"), Some(50.0));
+        assert_eq!(egfr.len(), 1);
+        assert_close(Some(egfr[0].value), 92.0, 0.5);
+
+        assert!(derived(creatinine(), Some("male"), None).is_empty());
+        assert!(derived(creatinine(), None, Some(50.0)).is_empty());
+        assert!(derived(creatinine(), Some("female"), Some(16.0)).is_empty());
+    }
+
+    #[test]
+    fn derives_homa_ir_and_transferrin_saturation() {
+        let values = derived(
+            vec![
+                value("Nüchternblutzucker", 90.0, "mg/dL"),
+                value("Insulin", 10.0, "µU/mL"),
+                value("Eisen", 100.0, "µg/dL"),
+                value("Transferrin", 250.0, "mg/dL"),
+            ],
+            None,
+            None,
+        );
+        assert_close(find(&values, "HOMA-IR"), 900.0 / 405.0, 1e-9);
+        assert_close(find(&values, "Transferrinsättigung"), 28.36, 1e-9);
+    }
+
+    #[test]
+    fn missing_inputs_derive_nothing() {
+        assert!(derived(vec![], Some("female"), Some(40.0)).is_empty());
+        assert!(derived(vec![value("Cholesterin", 200.0, "mg/dL")], None, None).is_empty());
+        assert!(derived(vec![value("Nüchternblutzucker", 90.0, "mg/dL")], None, None).is_empty());
+        assert!(derived(vec![value("Insulin", 10.0, "µU/mL")], None, None).is_empty());
+        assert!(derived(vec![value("Eisen", 100.0, "µg/dL")], None, None).is_empty());
+        // Unknown unit: the input can't be converted
+        assert!(derived(vec![value("Kreatinin", 1.0, "furlongs")], Some("male"), Some(50.0)).is_empty());
+    }
+}
@@ -1,6 +1,13 @@
 mod app;
+mod cache;
 mod config;
+mod derived;
+mod secrets;
 mod state;
+mod units;
+mod validation;
+#[cfg(test)]
+mod test_support;
 mod api;
 mod ui;
 
@@ -0,0 +1,832 @@
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use secret_service::{EncryptionType, SecretService};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::config_path;
+
+const APP_ATTRIBUTE: &str = "blutwerte-gtk";
+
+/// Transport encryption of secrets on the bus.
+const ENCRYPTION: EncryptionType = EncryptionType::Dh;
+
+/// Where an API token is kept. Only this reference is written to config.toml.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenRef {
+    pub store: TokenStore,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStore {
+    /// freedesktop Secret Service (GNOME Keyring, KWallet, ...)
+    Keyring,
+    /// Passphrase-encrypted file next to config.toml
+    File,
+}
+
+/// Why the encrypted token file could not be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenFileError {
+    /// No passphrase was entered yet, see [`set_passphrase`]
+    PassphraseRequired,
+    /// The file does not decrypt: wrong passphrase or a damaged file
+    WrongPassphrase,
+}
+
+impl fmt::Display for TokenFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenFileError::PassphraseRequired => write!(f, "Passphrase for token file not set"),
+            TokenFileError::WrongPassphrase => write!(f, "Wrong passphrase or corrupted token file"),
+        }
+    }
+}
+
+impl std::error::Error for TokenFileError {}
+
+// ─── Public API ──────────────────────────────────────────────────────────────
+//
+// Keyring calls go over D-Bus and may wait for an unlock prompt, so these
+// run on the Tokio runtime, never on the GTK main thread.
+
+/// Stores `token`, preferring the keyring. An existing reference keeps its id,
+/// so the entry is replaced instead of duplicated.
+pub async fn store_token(existing: Option<&TokenRef>, label: &str, token: &str) -> Result<TokenRef> {
+    if let Some(current) = existing {
+        if matches!(load_token(current).await, Ok(Some(stored)) if stored == token) {
+            return Ok(current.clone());
+        }
+    }
+    let id = existing.map(|r| r.id.clone()).unwrap_or_else(new_token_id);
+
+    if keyring_available().await {
+        keyring_store(&id, label, token).await?;
+        if let Some(old) = existing.filter(|r| r.store == TokenStore::File) {
+            if let Err(e) = delete_token(old).await {
+                eprintln!("Failed to remove token from encrypted file: {e}");
+            }
+        }
+        return Ok(TokenRef { store: TokenStore::Keyring, id });
+    }
+
+    let mut tokens = read_file_tokens()?;
+    tokens.insert(id.clone(), token.to_string());
+    write_file_tokens(&tokens)?;
+    Ok(TokenRef { store: TokenStore::File, id })
+}
+
+pub async fn load_token(token_ref: &TokenRef) -> Result<Option<String>> {
+    match token_ref.store {
+        TokenStore::Keyring => keyring_load(&token_ref.id).await,
+        TokenStore::File => Ok(read_file_tokens()?.remove(&token_ref.id)),
+    }
+}
+
+pub async fn delete_token(token_ref: &TokenRef) -> Result<()> {
+    match token_ref.store {
+        TokenStore::Keyring => keyring_delete(&token_ref.id).await,
+        TokenStore::File => {
+            let mut tokens = read_file_tokens()?;
+            if tokens.remove(&token_ref.id).is_some() {
+                write_file_tokens(&tokens)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Whether a Secret Service is reachable on the session bus.
+/// Checked once per run.
+pub async fn keyring_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    if let Some(available) = AVAILABLE.get() {
+        return *available;
+    }
+    let available = async {
+        let ss = SecretService::connect(ENCRYPTION).await?;
+        ss.get_default_collection().await.map(|_| ())
+    }
+    .await
+    .is_ok();
+    *AVAILABLE.get_or_init(|| available)
+}
+
+// ─── Secret Service ──────────────────────────────────────────────────────────
+
+fn attributes(id: &str) -> HashMap<&str, &str> {
+    HashMap::from([("application", APP_ATTRIBUTE), ("token-id", id)])
+}
+
+async fn keyring_store(id: &str, label: &str, token: &str) -> Result<()> {
+    let ss = SecretService::connect(ENCRYPTION).await?;
+    let collection = ss.get_default_collection().await?;
+    collection.ensure_unlocked().await?;
+    collection
+        .create_item(
+            &format!("Blutwerte API-Token ({label})"),
+            attributes(id),
+            token.as_bytes(),
+            true,
+            "text/plain",
+        )
+        .await
+        .context("Failed to store token in keyring")?;
+    Ok(())
+}
+
+async fn keyring_load(id: &str) -> Result<Option<String>> {
+    let ss = SecretService::connect(ENCRYPTION).await?;
+    let found = ss.search_items(attributes(id)).await?;
+    let item = match (found.unlocked.first(), found.locked.first()) {
+        (Some(item), _) => item,
+        (None, Some(item)) => {
+            item.unlock().await.context("Failed to unlock keyring item")?;
+            item
+        }
+        (None, None) => return Ok(None),
+    };
+    let secret = item.get_secret().await.context("Failed to read token from keyring")?;
+    Ok(Some(String::from_utf8(secret).context("Token in keyring is not valid UTF-8")?))
+}
+
+async fn keyring_delete(id: &str) -> Result<()> {
+    let ss = SecretService::connect(ENCRYPTION).await?;
+    let found = ss.search_items(attributes(id)).await?;
+    for item in found.unlocked.iter().chain(found.locked.iter()) {
+        item.delete().await.context("Failed to delete token from keyring")?;
+    }
+    Ok(())
+}
+
+// ─── Encrypted file fallback ─────────────────────────────────────────────────
+
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn token_file_path() -> PathBuf {
+    config_path().with_file_name("tokens.enc")
+}
+
+pub fn has_passphrase() -> bool {
+    PASSPHRASE.lock().map(|p| p.is_some()).unwrap_or(false)
+}
+
+/// Sets the passphrase for the encrypted token file. If the file already
+/// exists, the passphrase is checked against it first.
+pub fn set_passphrase(passphrase: &str) -> Result<()> {
+    let path = token_file_path();
+    if path.exists() {
+        decrypt_file(&path, passphrase)?;
+    }
+    *PASSPHRASE.lock().map_err(|_| anyhow!("Passphrase lock poisoned"))? = Some(passphrase.to_string());
+    Ok(())
+}
+
+/// Forgets the passphrase, so the next access asks for it again.
+pub fn forget_passphrase() {
+    if let Ok(mut passphrase) = PASSPHRASE.lock() {
+        *passphrase = None;
+    }
+}
+
+fn current_passphrase() -> Result<String> {
+    let passphrase = PASSPHRASE.lock().map_err(|_| anyhow!("Passphrase lock poisoned"))?.clone();
+    Ok(passphrase.ok_or(TokenFileError::PassphraseRequired)?)
+}
+
+fn read_file_tokens() -> Result<HashMap<String, String>> {
+    let path = token_file_path();
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    decrypt_file(&path, &current_passphrase()?)
+}
+
+fn write_file_tokens(tokens: &HashMap<String, String>) -> Result<()> {
+    let passphrase = current_passphrase()?;
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = XChaCha20Poly1305::new(&derive_key(&passphrase, &salt)?.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(tokens).context("Failed to serialize tokens")?;
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| anyhow!("Failed to encrypt tokens"))?;
+
+    let content = serde_json::to_vec(&EncryptedFile {
+        salt: to_hex(&salt),
+        nonce: to_hex(&nonce),
+        ciphertext: to_hex(&ciphertext),
+    })?;
+
+    let path = token_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    let tmp = path.with_extension("enc.tmp");
+    std::fs::write(&tmp, content)
+        .with_context(|| format!("Failed to write token file {:?}", tmp))?;
+    restrict_permissions(&tmp)?;
+    std::fs::rename(&tmp, &path)
+        .with_context(|| format!("Failed to move token file to {:?}", path))?;
+    Ok(())
+}
+
+fn decrypt_file(path: &std::path::Path, passphrase: &str) -> Result<HashMap<String, String>> {
+    let content = std::fs::read(path)
+        .with_context(|| format!("Failed to read token file {:?}", path))?;
+    let file: EncryptedFile =
+        serde_json::from_slice(&content).context("Failed to parse token file")?;
+
+    let salt = from_hex(&file.salt)?;
+    let nonce = from_hex(&file.nonce)?;
+    if nonce.len() != 24 {
+        bail!("Token file is corrupted");
+    }
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), from_hex(&file.ciphertext)?.as_slice())
+        .map_err(|_| TokenFileError::WrongPassphrase)?;
+    serde_json::from_slice(&plaintext).context("Failed to parse decrypted tokens")
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key: {e}"))?;
+    Ok(key)
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to set permissions on {:?}", path))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
+
+fn new_token_id() -> String {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    to_hex(&id)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        bail!("Invalid hex string");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("Invalid hex string"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_config, load_tokens, save_config, Config, Profile};
+    use crate::state::{block_on, init_tokio};
+    use crate::test_support::{temp_home, TempHome};
+    use aes::cipher::block_padding::Pkcs7;
+    use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+    use num_bigint::BigUint;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+    use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type, Value};
+
+    // ─── Mock Secret Service ─────────────────────────────────────────────────
+
+    const COLLECTION: &str = "/org/freedesktop/secrets/collection/default";
+
+    /// The only session algorithm the mock accepts, as the app always
+    /// encrypts secrets on the bus
+    const ALGORITHM_DH: &str = "dh-ietf1024-sha256-aes128-cbc-pkcs7";
+
+    /// 1024-bit MODP group of RFC 2409, generator 2
+    const DH_PRIME: &[u8] = b"FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74\
+        020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437\
+        4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+        EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE65381FFFFFFFFFFFFFFFF";
+
+    /// Server half of the key exchange: our public key and the AES key
+    /// shared with the client.
+    fn dh_exchange(client_public: &[u8]) -> (Vec<u8>, [u8; 16]) {
+        let prime = BigUint::parse_bytes(DH_PRIME, 16).expect("valid prime");
+        let mut private = [0u8; 128];
+        OsRng.fill_bytes(&mut private);
+        let private = BigUint::from_bytes_be(&private);
+
+        let public = BigUint::from(2u8).modpow(&private, &prime);
+        let shared = BigUint::from_bytes_be(client_public).modpow(&private, &prime).to_bytes_be();
+        let mut padded = vec![0u8; 128 - shared.len()];
+        padded.extend(shared);
+
+        let mut key = [0u8; 16];
+        hkdf::Hkdf::<sha2::Sha256>::new(None, &padded)
+            .expand(&[], &mut key)
+            .expect("valid key length");
+        (public.to_bytes_be(), key)
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Type)]
+    struct Secret {
+        session: OwnedObjectPath,
+        parameters: Vec<u8>,
+        value: Vec<u8>,
+        content_type: String,
+    }
+
+    /// Items with their secrets in plain text, and the AES key of each session.
+    #[derive(Default)]
+    struct Items {
+        next_id: u32,
+        by_id: HashMap<u32, (HashMap<String, String>, Vec<u8>)>,
+        sessions: HashMap<OwnedObjectPath, [u8; 16]>,
+    }
+
+    impl Items {
+        fn session_key(&self, session: &ObjectPath<'_>) -> zbus::fdo::Result<[u8; 16]> {
+            self.sessions
+                .iter()
+                .find(|(path, _)| path.as_str() == session.as_str())
+                .map(|(_, key)| *key)
+                .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("unknown session {session}")))
+        }
+    }
+
+    type SharedItems = Arc<Mutex<Items>>;
+
+    fn item_path(id: u32) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("{COLLECTION}/{id}")).expect("valid object path")
+    }
+
+    fn no_prompt() -> OwnedObjectPath {
+        OwnedObjectPath::try_from("/").expect("valid object path")
+    }
+
+    struct MockService(SharedItems);
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Service")]
+    impl MockService {
+        fn open_session(&self, algorithm: &str, input: Value<'_>) -> zbus::fdo::Result<(OwnedValue, OwnedObjectPath)> {
+            if algorithm != ALGORITHM_DH {
+                return Err(zbus::fdo::Error::NotSupported(algorithm.to_string()));
+            }
+            let client_public = Vec::<u8>::try_from(input)
+                .map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?;
+            let (public, key) = dh_exchange(&client_public);
+
+            let mut items = self.0.lock().expect("items lock");
+            let path = OwnedObjectPath::try_from(format!("/org/freedesktop/secrets/session/{}", items.sessions.len() + 1))
+                .expect("valid object path");
+            items.sessions.insert(path.clone(), key);
+            let output = OwnedValue::try_from(Value::from(public)).expect("byte array value");
+            Ok((output, path))
+        }
+
+        fn search_items(&self, attributes: HashMap<String, String>) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+            let items = self.0.lock().expect("items lock");
+            let mut found: Vec<u32> = items
+                .by_id
+                .iter()
+                .filter(|(_, (attrs, _))| attributes.iter().all(|(k, v)| attrs.get(k) == Some(v)))
+                .map(|(id, _)| *id)
+                .collect();
+            found.sort();
+            (found.into_iter().map(item_path).collect(), Vec::new())
+        }
+
+        fn read_alias(&self, name: &str) -> OwnedObjectPath {
+            if name == "default" {
+                OwnedObjectPath::try_from(COLLECTION).expect("valid object path")
+            } else {
+                no_prompt()
+            }
+        }
+
+        fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+            (objects, no_prompt())
+        }
+    }
+
+    struct MockCollection(SharedItems);
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Collection")]
+    impl MockCollection {
+        async fn create_item(
+            &self,
+            #[zbus(object_server)] server: &zbus::ObjectServer,
+            properties: HashMap<String, OwnedValue>,
+            secret: Secret,
+            replace: bool,
+        ) -> zbus::fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+            let attributes: HashMap<String, String> = properties
+                .get("org.freedesktop.Secret.Item.Attributes")
+                .and_then(|v| v.try_clone().ok())
+                .and_then(|v| HashMap::try_from(v).ok())
+                .unwrap_or_default();
+            let (id, created) = {
+                let mut items = self.0.lock().expect("items lock");
+                let key = items.session_key(&secret.session)?;
+                let value = cbc::Decryptor::<aes::Aes128>::new_from_slices(&key, &secret.parameters)
+                    .map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?
+                    .decrypt_padded_vec_mut::<Pkcs7>(&secret.value)
+                    .map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?;
+                let existing = items.by_id.iter().find(|(_, (a, _))| *a == attributes).map(|(id, _)| *id);
+                match existing.filter(|_| replace) {
+                    Some(id) => {
+                        items.by_id.insert(id, (attributes, value));
+                        (id, false)
+                    }
+                    None => {
+                        items.next_id += 1;
+                        let id = items.next_id;
+                        items.by_id.insert(id, (attributes, value));
+                        (id, true)
+                    }
+                }
+            };
+            if created {
+                server.at(item_path(id), MockItem { id, items: self.0.clone() }).await?;
+            }
+            Ok((item_path(id), no_prompt()))
+        }
+
+        #[zbus(property)]
+        fn locked(&self) -> bool {
+            false
+        }
+    }
+
+    struct MockItem {
+        id: u32,
+        items: SharedItems,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Item")]
+    impl MockItem {
+        fn get_secret(&self, session: ObjectPath<'_>) -> zbus::fdo::Result<Secret> {
+            let items = self.items.lock().expect("items lock");
+            let key = items.session_key(&session)?;
+            let (_, value) = items
+                .by_id
+                .get(&self.id)
+                .ok_or_else(|| zbus::fdo::Error::UnknownObject(format!("item {}", self.id)))?;
+            let mut iv = [0u8; 16];
+            OsRng.fill_bytes(&mut iv);
+            let value = cbc::Encryptor::<aes::Aes128>::new(&key.into(), &iv.into())
+                .encrypt_padded_vec_mut::<Pkcs7>(value);
+            Ok(Secret {
+                session: session.into(),
+                parameters: iv.to_vec(),
+                value,
+                content_type: "text/plain".to_string(),
+            })
+        }
+
+        async fn delete(&self, #[zbus(object_server)] server: &zbus::ObjectServer) -> zbus::fdo::Result<OwnedObjectPath> {
+            self.items.lock().expect("items lock").by_id.remove(&self.id);
+            server.remove::<MockItem, _>(item_path(self.id)).await?;
+            Ok(no_prompt())
+        }
+
+        #[zbus(property)]
+        fn locked(&self) -> bool {
+            false
+        }
+    }
+
+    /// Private session bus with the mock service, shared by all tests.
+    /// The shell wrapper stops the bus once the test process closes its stdin.
+    /// It closes its own stdout, so the address read ends if the daemon fails.
+    struct MockBus {
+        items: SharedItems,
+        _daemon: Child,
+        _dir: tempfile::TempDir,
+    }
+
+    fn start_mock_bus() -> Option<MockBus> {
+        let dir = tempfile::tempdir().ok()?;
+        let config = dir.path().join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                "<busconfig><type>session</type><listen>unix:path={}</listen>\
+                 <policy context=\"default\"><allow send_destination=\"*\"/><allow receive_sender=\"*\"/><allow own=\"*\"/></policy></busconfig>",
+                dir.path().join("bus").display()
+            ),
+        )
+        .ok()?;
+        let mut daemon = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "dbus-daemon --config-file={} --nofork --print-address=1 & pid=$!; exec >&-; read _; kill $pid",
+                config.display()
+            ))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            return None;
+        }
+        std::env::set_var("DBUS_SESSION_BUS_ADDRESS", &address);
+
+        // Served from its own runtime for the rest of the test run
+        let items = SharedItems::default();
+        let served = items.clone();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("runtime");
+            rt.block_on(async move {
+                let connection = zbus::connection::Builder::address(address.as_str())
+                    .and_then(|b| b.name("org.freedesktop.secrets"))
+                    .and_then(|b| b.serve_at("/org/freedesktop/secrets", MockService(served.clone())))
+                    .and_then(|b| b.serve_at(COLLECTION, MockCollection(served)));
+                let connection = match connection {
+                    Ok(builder) => builder.build().await,
+                    Err(e) => Err(e),
+                };
+                ready_tx.send(connection.is_ok()).ok();
+                if connection.is_ok() {
+                    std::future::pending::<()>().await;
+                }
+            });
+        });
+        ready_rx.recv().ok()?.then_some(MockBus { items, _daemon: daemon, _dir: dir })
+    }
+
+    fn mock_bus() -> Option<&'static MockBus> {
+        static BUS: OnceLock<Option<MockBus>> = OnceLock::new();
+        BUS.get_or_init(start_mock_bus).as_ref()
+    }
+
+    /// Serializes the tests, which share the bus and environment, and gives
+    /// each its own config directory. Without `dbus-daemon` the keyring
+    /// tests fail, unless BLUTWERTE_SKIP_DBUS_TESTS is set to skip them.
+    fn test_env() -> Option<TempHome> {
+        let home = temp_home();
+        if mock_bus().is_none() {
+            assert!(
+                std::env::var_os("BLUTWERTE_SKIP_DBUS_TESTS").is_some(),
+                "the keyring tests need dbus-daemon; set BLUTWERTE_SKIP_DBUS_TESTS=1 to skip them"
+            );
+            return None;
+        }
+        init_tokio();
+        Some(home)
+    }
+
+    macro_rules! require_bus {
+        () => {
+            match test_env() {
+                Some(env) => env,
+                None => {
+                    eprintln!("BLUTWERTE_SKIP_DBUS_TESTS set, skipping");
+                    return;
+                }
+            }
+        };
+    }
+
+    /// Like [`test_env`] for tests of the encrypted file, which need no bus.
+    fn file_env() -> TempHome {
+        let home = temp_home();
+        forget_passphrase();
+        init_tokio();
+        home
+    }
+
+    fn file_ref(id: &str) -> TokenRef {
+        TokenRef { store: TokenStore::File, id: id.to_string() }
+    }
+
+    fn token_file_error(e: &anyhow::Error) -> Option<TokenFileError> {
+        e.downcast_ref::<TokenFileError>().copied()
+    }
+
+    fn profile(name: &str, token: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            server_url: format!("https://{name}.example"),
+            api_token: token.to_string(),
+            token: None,
+        }
+    }
+
+    // ─── Tests ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn stores_and_loads_token_in_keyring() {
+        let _env = require_bus!();
+        assert!(block_on(keyring_available()));
+
+        let token_ref = block_on(store_token(None, "Test", "secret-1")).unwrap();
+        assert_eq!(token_ref.store, TokenStore::Keyring);
+        assert_eq!(block_on(load_token(&token_ref)).unwrap().as_deref(), Some("secret-1"));
+
+        // The mock only speaks encrypted sessions and decrypts on arrival
+        let items = mock_bus().unwrap().items.lock().unwrap();
+        let stored = items.by_id.values().find(|(attrs, _)| attrs.get("token-id") == Some(&token_ref.id));
+        assert_eq!(stored.map(|(_, value)| value.as_slice()), Some(&b"secret-1"[..]));
+    }
+
+    #[test]
+    fn unknown_token_loads_as_none() {
+        let _env = require_bus!();
+        let missing = TokenRef { store: TokenStore::Keyring, id: new_token_id() };
+        assert_eq!(block_on(load_token(&missing)).unwrap(), None);
+    }
+
+    #[test]
+    fn replacing_token_keeps_id() {
+        let _env = require_bus!();
+        let first = block_on(store_token(None, "Test", "old")).unwrap();
+        let second = block_on(store_token(Some(&first), "Test", "new")).unwrap();
+        assert_eq!(second, first);
+        assert_eq!(block_on(load_token(&second)).unwrap().as_deref(), Some("new"));
+
+        let found = block_on(async {
+            let ss = SecretService::connect(ENCRYPTION).await?;
+            ss.search_items(attributes(&first.id)).await.map(|r| r.unlocked.len())
+        })
+        .unwrap();
+        assert_eq!(found, 1, "replacing must not duplicate the item");
+
+        // Same token again: nothing to write
+        assert_eq!(block_on(store_token(Some(&second), "Test", "new")).unwrap(), first);
+    }
+
+    #[test]
+    fn deletes_token() {
+        let _env = require_bus!();
+        let token_ref = block_on(store_token(None, "Test", "gone")).unwrap();
+        block_on(delete_token(&token_ref)).unwrap();
+        assert_eq!(block_on(load_token(&token_ref)).unwrap(), None);
+    }
+
+    #[test]
+    fn migrates_plaintext_config() {
+        let _env = require_bus!();
+        let path = crate::config::config_path();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "server_url = \"https://blut.example\"\napi_token = \"plain-token\"\n").unwrap();
+
+        let mut config = load_config().unwrap();
+        assert!(config.has_unstored_tokens());
+        block_on(save_config(&mut config)).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("plain-token"), "token left in config.toml: {content}");
+        assert!(content.contains("keyring"));
+
+        let mut reloaded = load_config().unwrap();
+        assert!(!reloaded.has_unstored_tokens());
+        block_on(load_tokens(&mut reloaded)).unwrap();
+        let active = reloaded.active().unwrap();
+        assert_eq!(active.server_url, "https://blut.example");
+        assert_eq!(active.api_token, "plain-token");
+    }
+
+    #[test]
+    fn setup_on_unloaded_tokens_keeps_other_profiles() {
+        let _env = require_bus!();
+        let mut config = Config {
+            active_profile: "Privat".to_string(),
+            profiles: vec![profile("Praxis", "praxis-token"), profile("Privat", "privat-token")],
+            ..Config::default()
+        };
+        block_on(save_config(&mut config)).unwrap();
+
+        // Tokens not loaded, e.g. because the keyring was locked
+        let mut config = load_config().unwrap();
+        config.set_active_server("https://neu.example".to_string(), "new-token".to_string());
+        block_on(save_config(&mut config)).unwrap();
+
+        let mut reloaded = load_config().unwrap();
+        block_on(load_tokens(&mut reloaded)).unwrap();
+        assert_eq!(reloaded.profile_names(), ["Praxis", "Privat"]);
+        assert_eq!(reloaded.profile("Praxis").unwrap().api_token, "praxis-token");
+        let active = reloaded.active().unwrap();
+        assert_eq!(active.name, "Privat");
+        assert_eq!(active.server_url, "https://neu.example");
+        assert_eq!(active.api_token, "new-token");
+    }
+
+    #[test]
+    fn removed_profile_token_is_deleted() {
+        let _env = require_bus!();
+        let mut config = Config {
+            profiles: vec![profile("Praxis", "praxis-token"), profile("Privat", "privat-token")],
+            ..Config::default()
+        };
+        block_on(save_config(&mut config)).unwrap();
+        let removed_ref = config.profiles[0].token.clone().unwrap();
+        let kept_ref = config.profiles[1].token.clone().unwrap();
+
+        config.remove_profile(0).unwrap();
+        block_on(save_config(&mut config)).unwrap();
+
+        assert_eq!(block_on(load_token(&removed_ref)).unwrap(), None);
+        assert_eq!(block_on(load_token(&kept_ref)).unwrap().as_deref(), Some("privat-token"));
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let _env = file_env();
+        set_passphrase("geheim").unwrap();
+        write_file_tokens(&HashMap::from([("a".to_string(), "token-a".to_string())])).unwrap();
+
+        assert_eq!(block_on(load_token(&file_ref("a"))).unwrap().as_deref(), Some("token-a"));
+        assert_eq!(block_on(load_token(&file_ref("b"))).unwrap(), None);
+
+        let content = std::fs::read_to_string(token_file_path()).unwrap();
+        assert!(!content.contains("token-a"), "token in plain text: {content}");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(token_file_path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Readable again after a restart with the same passphrase
+        forget_passphrase();
+        set_passphrase("geheim").unwrap();
+        assert_eq!(block_on(load_token(&file_ref("a"))).unwrap().as_deref(), Some("token-a"));
+
+        block_on(delete_token(&file_ref("a"))).unwrap();
+        assert_eq!(block_on(load_token(&file_ref("a"))).unwrap(), None);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let _env = file_env();
+        set_passphrase("richtig").unwrap();
+        write_file_tokens(&HashMap::from([("a".to_string(), "token-a".to_string())])).unwrap();
+        forget_passphrase();
+
+        let err = set_passphrase("falsch").unwrap_err();
+        assert_eq!(token_file_error(&err), Some(TokenFileError::WrongPassphrase));
+        assert!(!has_passphrase());
+
+        let err = block_on(load_token(&file_ref("a"))).unwrap_err();
+        assert_eq!(token_file_error(&err), Some(TokenFileError::PassphraseRequired));
+    }
+
+    #[test]
+    fn tampered_file_is_rejected() {
+        let _env = file_env();
+        set_passphrase("geheim").unwrap();
+        write_file_tokens(&HashMap::from([("a".to_string(), "token-a".to_string())])).unwrap();
+
+        let path = token_file_path();
+        let mut file: EncryptedFile = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let flipped = if file.ciphertext.starts_with('0') { "1" } else { "0" };
+        file.ciphertext.replace_range(..1, flipped);
+        std::fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        let err = block_on(load_token(&file_ref("a"))).unwrap_err();
+        assert_eq!(token_file_error(&err), Some(TokenFileError::WrongPassphrase));
+
+        std::fs::write(&path, "kein JSON").unwrap();
+        let err = block_on(load_token(&file_ref("a"))).unwrap_err();
+        assert_eq!(token_file_error(&err), None);
+    }
+
+    #[test]
+    fn passphrase_for_new_file_is_accepted() {
+        let _env = file_env();
+        assert!(!has_passphrase());
+        set_passphrase("neu").unwrap();
+        assert!(has_passphrase());
+        assert!(!token_file_path().exists());
+    }
+}
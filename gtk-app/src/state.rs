@@ -1,7 +1,13 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use glib::prelude::*;
+use glib::subclass::prelude::*;
 use std::cell::RefCell;
+use std::rc::Rc;
 use tokio::runtime::Runtime;
 
-use crate::api::types::*;
+use crate::api::{types::*, ApiClient};
+use crate::units::{self, DisplayData, UnitSystem};
 
 // ─── Data bundle returned by initial load ─────────────────────────────────────
 
@@ -12,6 +18,47 @@ pub struct DataBundle {
     pub reference_db: ReferenceDatabase,
 }
 
+/// Where the currently shown data came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataSource {
+    Live,
+    /// Offline snapshot, read-only
+    Snapshot { saved_at: DateTime<Utc> },
+    /// Values another user shared with me, read-only
+    Shared,
+}
+
+/// Loads the data of a received share. The owner's gender goes into `user`,
+/// so reference ranges are evaluated for them.
+pub async fn fetch_shared_bundle(client: &ApiClient, share_id: &str) -> Result<DataBundle> {
+    let user_data = client.get_shared_data(share_id).await?;
+    let reference_db = client.get_shared_reference(share_id).await?;
+    let user = AuthUser {
+        authenticated: true,
+        user_id: Some(user_data.user_id.clone()),
+        display_name: Some(user_data.display_name.clone()),
+        email: None,
+        gender: user_data.gender.clone(),
+        is_admin: Some(false),
+    };
+    Ok(DataBundle { user, user_data, reference_db })
+}
+
+pub async fn fetch_bundle(client: &ApiClient) -> Result<DataBundle> {
+    let user_data = client.get_blood_values().await?;
+    let reference_db = client.get_reference().await?;
+    // Try /api/auth/me (works after backend fix); fall back to UserData fields
+    let user = client.get_me().await.unwrap_or_else(|_| AuthUser {
+        authenticated: true,
+        user_id: Some(user_data.user_id.clone()),
+        display_name: Some(user_data.display_name.clone()),
+        email: Some(user_data.email.clone()),
+        gender: user_data.gender.clone(),
+        is_admin: None,
+    });
+    Ok(DataBundle { user, user_data, reference_db })
+}
+
 // ─── Tokio runtime (thread-local) ────────────────────────────────────────────
 
 thread_local! {
@@ -39,3 +86,251 @@ where
         }
     });
 }
+
+/// Like [`spawn_task`], but the task can be cancelled through the returned handle.
+pub fn spawn_abortable<F>(f: F) -> Option<tokio::task::AbortHandle>
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    TOKIO_RT.with(|rt| {
+        rt.borrow().as_ref().map(|rt| rt.spawn(f).abort_handle())
+    })
+}
+
+/// Runs `f` on the Tokio runtime and blocks until it finishes, for tests
+/// of async code.
+#[cfg(test)]
+pub fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    TOKIO_RT.with(|rt| {
+        rt.borrow()
+            .as_ref()
+            .expect("Tokio runtime not initialized")
+            .block_on(f)
+    })
+}
+
+// ─── Shared application state ────────────────────────────────────────────────
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use std::cell::Cell;
+    use std::sync::OnceLock;
+
+    #[derive(Default)]
+    pub struct AppStore {
+        pub client: RefCell<Option<ApiClient>>,
+        pub user: RefCell<Option<AuthUser>>,
+        pub user_data: RefCell<Rc<UserData>>,
+        pub reference_db: RefCell<Rc<Vec<ReferenceValue>>>,
+        pub source: Cell<Option<DataSource>>,
+        pub generation: Cell<u64>,
+        pub unit_system: Cell<UnitSystem>,
+        pub evaluation_mode: Cell<EvaluationMode>,
+        /// Computed on first use after each change
+        pub display_data: RefCell<Option<Rc<DisplayData>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AppStore {
+        const NAME: &'static str = "BlutwerteAppStore";
+        type Type = super::AppStore;
+    }
+
+    impl ObjectImpl for AppStore {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| vec![Signal::builder("changed").build()])
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Data of the active profile, shared by all pages of a window.
+    /// Every modification emits `changed`, so views never hold stale copies.
+    pub struct AppStore(ObjectSubclass<imp::AppStore>);
+}
+
+impl Default for AppStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppStore {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Replaces everything after a full load. Starts a new generation.
+    pub fn set_bundle(&self, client: ApiClient, bundle: DataBundle, source: DataSource) {
+        let imp = self.imp();
+        *imp.client.borrow_mut() = Some(client);
+        *imp.user.borrow_mut() = Some(bundle.user);
+        *imp.user_data.borrow_mut() = Rc::new(bundle.user_data);
+        *imp.reference_db.borrow_mut() = Rc::new(bundle.reference_db.values);
+        imp.source.set(Some(source));
+        imp.generation.set(imp.generation.get() + 1);
+        self.emit_changed();
+    }
+
+    /// Replaces the user data, e.g. after an entry was saved.
+    pub fn set_user_data(&self, user_data: UserData) {
+        *self.imp().user_data.borrow_mut() = Rc::new(user_data);
+        self.emit_changed();
+    }
+
+    /// Applies a saved health profile. The gender changes how every value
+    /// is evaluated, so all pages re-render.
+    pub fn set_health_profile(&self, profile: HealthProfile) {
+        let imp = self.imp();
+        if let Some(user) = imp.user.borrow_mut().as_mut() {
+            user.gender = profile.gender.clone();
+        }
+        let mut user_data = (*self.user_data()).clone();
+        user_data.apply_health_profile(profile);
+        *imp.user_data.borrow_mut() = Rc::new(user_data);
+        self.emit_changed();
+    }
+
+    /// Replaces the reference database after an admin edit.
+    pub fn set_reference_db(&self, values: Vec<ReferenceValue>) {
+        *self.imp().reference_db.borrow_mut() = Rc::new(values);
+        self.emit_changed();
+    }
+
+    /// Changes the display units; all pages re-render.
+    pub fn set_unit_system(&self, system: UnitSystem) {
+        if self.imp().unit_system.replace(system) != system {
+            self.emit_changed();
+        }
+    }
+
+    pub fn unit_system(&self) -> UnitSystem {
+        self.imp().unit_system.get()
+    }
+
+    /// Changes how values are graded; all pages re-render.
+    pub fn set_evaluation_mode(&self, mode: EvaluationMode) {
+        if self.imp().evaluation_mode.replace(mode) != mode {
+            self.emit_changed();
+        }
+    }
+
+    pub fn evaluation_mode(&self) -> EvaluationMode {
+        self.imp().evaluation_mode.get()
+    }
+
+    /// User data and reference database converted to the display units and
+    /// completed with derived values, for status evaluation and charts.
+    /// Editors keep using the raw data.
+    pub fn display_data(&self) -> Rc<DisplayData> {
+        if let Some(data) = self.imp().display_data.borrow().as_ref() {
+            return data.clone();
+        }
+        let data = Rc::new(units::display_data(
+            &self.user_data(),
+            &self.reference_db(),
+            self.unit_system(),
+            &self.evaluation(),
+        ));
+        *self.imp().display_data.borrow_mut() = Some(data.clone());
+        data
+    }
+
+    /// Gender, date of birth and evaluation mode that values are judged by.
+    pub fn evaluation(&self) -> Evaluation {
+        let user_data = self.user_data();
+        Evaluation::new(self.gender(), user_data.birth_date.as_deref(), self.evaluation_mode())
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.imp().user.borrow().as_ref().and_then(|u| u.is_admin).unwrap_or(false)
+    }
+
+    /// Forgets all data, e.g. when switching profiles.
+    pub fn clear(&self) {
+        let imp = self.imp();
+        imp.client.borrow_mut().take();
+        imp.user.borrow_mut().take();
+        *imp.user_data.borrow_mut() = Default::default();
+        *imp.reference_db.borrow_mut() = Default::default();
+        imp.source.set(None);
+        imp.generation.set(imp.generation.get() + 1);
+        self.emit_changed();
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.imp().source.get().is_some()
+    }
+
+    pub fn source(&self) -> Option<DataSource> {
+        self.imp().source.get()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self.source(), Some(DataSource::Snapshot { .. }))
+    }
+
+    /// Offline snapshots and shared data cannot be edited.
+    pub fn is_read_only(&self) -> bool {
+        self.source() != Some(DataSource::Live)
+    }
+
+    /// Client for server requests; `None` while offline or not loaded.
+    pub fn client(&self) -> Option<ApiClient> {
+        if self.source() != Some(DataSource::Live) {
+            return None;
+        }
+        self.imp().client.borrow().clone()
+    }
+
+    pub fn user(&self) -> Option<AuthUser> {
+        self.imp().user.borrow().clone()
+    }
+
+    pub fn gender(&self) -> Option<String> {
+        self.imp().user.borrow().as_ref().and_then(|u| u.gender.clone())
+    }
+
+    pub fn user_data(&self) -> Rc<UserData> {
+        self.imp().user_data.borrow().clone()
+    }
+
+    pub fn reference_db(&self) -> Rc<Vec<ReferenceValue>> {
+        self.imp().reference_db.borrow().clone()
+    }
+
+    /// Increases with every full load or profile switch, so views can tell
+    /// a new session from an update of the current one.
+    pub fn generation(&self) -> u64 {
+        self.imp().generation.get()
+    }
+
+    pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("changed", false, move |values| {
+            if let Ok(store) = values[0].get::<Self>() {
+                f(&store);
+            }
+            None
+        })
+    }
+
+    /// Like [`Self::connect_changed`], but disconnects when `owner` is
+    /// finalized. Used by pages that are rebuilt or closed; `f` must only
+    /// hold weak references to `owner`.
+    pub fn connect_changed_for<F: Fn(&Self) + 'static>(&self, owner: &impl IsA<glib::Object>, f: F) {
+        let id = self.connect_changed(f);
+        let store = self.downgrade();
+        owner.add_weak_ref_notify_local(move || {
+            if let Some(store) = store.upgrade() {
+                store.disconnect(id);
+            }
+        });
+    }
+
+    fn emit_changed(&self) {
+        self.imp().display_data.borrow_mut().take();
+        self.emit_by_name::<()>("changed", &[]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Helpers shared by the unit tests.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+/// Fresh XDG config and cache directories for tests that touch config.toml,
+/// snapshots or other files. The directories come from the environment, so
+/// only one such test runs at a time.
+pub struct TempHome {
+    pub dir: tempfile::TempDir,
+    _guard: MutexGuard<'static, ()>,
+}
+
+pub fn temp_home() -> TempHome {
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = tempfile::tempdir().expect("temp dir");
+    std::env::set_var("XDG_CONFIG_HOME", dir.path().join("config"));
+    std::env::set_var("XDG_CACHE_HOME", dir.path().join("cache"));
+    TempHome { dir, _guard: guard }
+}
+
+/// HTTP server answering every request with a canned status and JSON body
+/// by path, 404 otherwise. Dropping it and starting a new one on the same
+/// port simulates a server going offline and coming back.
+pub struct StubServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StubServer {
+    /// Listens on `port` of localhost, or on a free one for 0.
+    pub fn start(port: u16, routes: Vec<(&'static str, u16, String)>) -> StubServer {
+        let listener = TcpListener::bind(("127.0.0.1", port)).expect("bind stub server");
+        let addr = listener.local_addr().expect("stub server address");
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        answer(stream, &routes);
+                    }
+                }
+            }
+        });
+
+        StubServer { addr, stop, thread: Some(thread) }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes up the accept loop
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn answer(stream: TcpStream, routes: &[(&'static str, u16, String)]) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut header = String::new();
+    while reader.read_line(&mut header).is_ok_and(|n| n > 0) && header != "\r\n" {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = routes
+        .iter()
+        .find(|(p, _, _)| *p == path)
+        .map_or((404, r#"{"error":"Not found"}"#), |(_, status, body)| (*status, body.as_str()));
+    let mut out = &stream;
+    let _ = write!(
+        out,
+        "HTTP/1.1 {status} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
@@ -1,7 +1,7 @@
 use gtk4::prelude::*;
 
-/// Returns (container_widget, text_view, send_button)
-pub fn build_input_bar() -> (gtk4::Box, gtk4::TextView, gtk4::Button) {
+/// Returns (container_widget, text_view, send_button, stop_button)
+pub fn build_input_bar() -> (gtk4::Box, gtk4::TextView, gtk4::Button, gtk4::Button) {
     let container = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
     container.set_margin_start(16);
     container.set_margin_end(16);
@@ -60,10 +60,20 @@ pub fn build_input_bar() -> (gtk4::Box, gtk4::TextView, gtk4::Button) {
     send_btn.set_valign(gtk4::Align::End);
     send_btn.set_tooltip_text(Some("Senden (Enter)"));
 
+    // Stop button, replaces the send button while an answer is generated
+    let stop_btn = gtk4::Button::new();
+    stop_btn.set_icon_name("media-playback-stop-symbolic");
+    stop_btn.add_css_class("destructive-action");
+    stop_btn.add_css_class("circular");
+    stop_btn.set_valign(gtk4::Align::End);
+    stop_btn.set_tooltip_text(Some("Antwort abbrechen"));
+    stop_btn.set_visible(false);
+
     input_row.append(&scrolled);
     input_row.append(&send_btn);
+    input_row.append(&stop_btn);
 
     container.append(&input_row);
 
-    (container, text_view, send_btn)
+    (container, text_view, send_btn, stop_btn)
 }
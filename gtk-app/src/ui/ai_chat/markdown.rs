@@ -0,0 +1,770 @@
+use gtk4::prelude::*;
+
+/// A block-level element of a chat answer. Text fields already hold
+/// escaped Pango markup.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading { level: u8, markup: String },
+    Paragraph(String),
+    Quote(String),
+    List(Vec<ListItem>),
+    Table { header: Vec<String>, rows: Vec<Vec<String>> },
+    Code(String),
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    pub depth: usize,
+    pub marker: String,
+    pub markup: String,
+}
+
+// ── Block parser ─────────────────────────────────────────────────────────────
+
+/// Splits Markdown as produced by the LLMs into blocks. Unknown or
+/// unfinished syntax (e.g. while an answer is still streaming) falls
+/// back to plain text.
+pub fn parse_blocks(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            i += 1;
+            continue;
+        }
+
+        if let Some(fence) = code_fence(trimmed) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1; // closing fence
+            blocks.push(Block::Code(code.join("\n")));
+            continue;
+        }
+
+        if let Some((level, title)) = heading(trimmed) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            blocks.push(Block::Heading { level, markup: inline_markup(title) });
+            i += 1;
+            continue;
+        }
+
+        if is_rule(trimmed) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            blocks.push(Block::Rule);
+            i += 1;
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut quote = Vec::new();
+            while i < lines.len() {
+                let Some(rest) = lines[i].trim().strip_prefix('>') else {
+                    break;
+                };
+                quote.push(rest.strip_prefix(' ').unwrap_or(rest));
+                i += 1;
+            }
+            blocks.push(Block::Quote(inline_markup(&quote.join("\n"))));
+            continue;
+        }
+
+        if trimmed.contains('|') && lines.get(i + 1).is_some_and(|l| is_table_separator(l)) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let header = split_row(trimmed);
+            let columns = header.len();
+            i += 2;
+            let mut rows = Vec::new();
+            while i < lines.len() && lines[i].contains('|') && !lines[i].trim().is_empty() {
+                let mut row = split_row(lines[i].trim());
+                row.resize(columns, String::new());
+                rows.push(row.iter().map(|c| inline_markup(c)).collect());
+                i += 1;
+            }
+            blocks.push(Block::Table {
+                header: header.iter().map(|c| inline_markup(c)).collect(),
+                rows,
+            });
+            continue;
+        }
+
+        if list_marker(line).is_some() {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut items: Vec<(usize, String, String)> = Vec::new();
+            while i < lines.len() {
+                let l = lines[i];
+                if let Some((depth, marker, rest)) = list_marker(l) {
+                    items.push((depth, marker, rest.to_string()));
+                } else if l.trim().is_empty() {
+                    // Blank lines between items keep the list going
+                    if lines.get(i + 1).is_none_or(|n| list_marker(n).is_none()) {
+                        break;
+                    }
+                } else if l.starts_with([' ', '\t']) {
+                    // Continuation of the previous item
+                    if let Some(last) = items.last_mut() {
+                        last.2.push('\n');
+                        last.2.push_str(l.trim());
+                    }
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            blocks.push(Block::List(
+                items
+                    .into_iter()
+                    .map(|(depth, marker, text)| ListItem {
+                        depth,
+                        marker,
+                        markup: inline_markup(&text),
+                    })
+                    .collect(),
+            ));
+            continue;
+        }
+
+        paragraph.push(trimmed);
+        i += 1;
+    }
+
+    flush_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+fn flush_paragraph(blocks: &mut Vec<Block>, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::Paragraph(inline_markup(&paragraph.join("\n"))));
+        paragraph.clear();
+    }
+}
+
+fn code_fence(line: &str) -> Option<&'static str> {
+    ["```", "~~~"].into_iter().find(|f| line.starts_with(f))
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level as u8, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3
+        && matches!(chars[0], '-' | '*' | '_')
+        && chars.iter().all(|&c| c == chars[0])
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('|')
+        && line.contains('-')
+        && line.chars().all(|c| matches!(c, '|' | ':' | '-' | ' ' | '\t'))
+}
+
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Returns indentation depth, display marker and item text.
+fn list_marker(line: &str) -> Option<(usize, String, &str)> {
+    let indent: usize = line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    let depth = indent / 2;
+    let rest = line.trim_start();
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = rest.strip_prefix(bullet) {
+            let text = text.trim();
+            if let Some(task) = text.strip_prefix("[ ] ") {
+                return Some((depth, "☐".to_string(), task));
+            }
+            if let Some(task) = text.strip_prefix("[x] ").or_else(|| text.strip_prefix("[X] ")) {
+                return Some((depth, "☑".to_string(), task));
+            }
+            let marker = match depth {
+                0 => "•",
+                1 => "◦",
+                _ => "▪",
+            };
+            return Some((depth, marker.to_string(), text));
+        }
+    }
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if (1..=9).contains(&digits) {
+        let after = &rest[digits..];
+        if let Some(text) = after.strip_prefix(". ").or_else(|| after.strip_prefix(") ")) {
+            return Some((depth, format!("{}.", &rest[..digits]), text.trim()));
+        }
+    }
+    None
+}
+
+// ── Inline markup ────────────────────────────────────────────────────────────
+
+/// Converts inline Markdown (bold, italic, strikethrough, code spans,
+/// links and bare URLs) to Pango markup. Everything else is escaped,
+/// so the result is always valid markup.
+pub fn inline_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\\' => {
+                if let Some(next) = rest[1..].chars().next().filter(|n| n.is_ascii_punctuation()) {
+                    push_escaped(&mut out, next);
+                    rest = &rest[1 + next.len_utf8()..];
+                    prev = Some(next);
+                    continue;
+                }
+            }
+            '`' => {
+                if let Some(end) = rest[1..].find('`').filter(|&end| end > 0) {
+                    out.push_str("<tt>");
+                    out.push_str(&escape(&rest[1..1 + end]));
+                    out.push_str("</tt>");
+                    rest = &rest[end + 2..];
+                    prev = Some('`');
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, len)) = parse_link(rest) {
+                    if is_safe_url(url) {
+                        out.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), inline_markup(label)));
+                    } else {
+                        out.push_str(&inline_markup(label));
+                    }
+                    rest = &rest[len..];
+                    prev = Some(')');
+                    continue;
+                }
+            }
+            '*' | '_' | '~' => {
+                if let Some((tag, inner, len)) = parse_emphasis(rest, prev) {
+                    out.push_str(&format!("<{tag}>{}</{tag}>", inline_markup(inner)));
+                    rest = &rest[len..];
+                    prev = Some(c);
+                    continue;
+                }
+            }
+            'h' if !prev.is_some_and(|p| p.is_alphanumeric()) => {
+                if let Some(len) = bare_url_len(rest) {
+                    let url = &rest[..len];
+                    out.push_str(&format!("<a href=\"{0}\">{0}</a>", escape(url)));
+                    rest = &rest[len..];
+                    prev = url.chars().last();
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        push_escaped(&mut out, c);
+        rest = &rest[c.len_utf8()..];
+        prev = Some(c);
+    }
+
+    out
+}
+
+/// `[label](url)` at the start of `text` → (label, url, consumed bytes).
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find(']')?;
+    let after = &text[close + 1..];
+    if !after.starts_with('(') {
+        return None;
+    }
+    let end = after.find(')')?;
+    let target = after[1..end].trim();
+    // Drop an optional title: [label](url "title")
+    let url = target.split_whitespace().next().unwrap_or("");
+    Some((&text[1..close], url, close + 1 + end + 1))
+}
+
+fn is_safe_url(url: &str) -> bool {
+    ["https://", "http://", "mailto:"].iter().any(|s| url.starts_with(s))
+}
+
+fn bare_url_len(text: &str) -> Option<usize> {
+    if !text.starts_with("https://") && !text.starts_with("http://") {
+        return None;
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+        .unwrap_or(text.len());
+    // Sentence punctuation after a URL is not part of it
+    let url = text[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '*', '_']);
+    (url.len() > "https://".len()).then_some(url.len())
+}
+
+/// Emphasis at the start of `text` → (Pango tag, inner text, consumed bytes).
+fn parse_emphasis(text: &str, prev: Option<char>) -> Option<(&'static str, &str, usize)> {
+    for (delim, tag) in [("**", "b"), ("__", "b"), ("~~", "s"), ("*", "i"), ("_", "i")] {
+        let Some(after) = text.strip_prefix(delim) else {
+            continue;
+        };
+        let underscore = delim.starts_with('_');
+        // snake_case_words are not emphasis
+        if underscore && prev.is_some_and(|p| p.is_alphanumeric()) {
+            continue;
+        }
+        if after.is_empty() || after.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some(end) = find_closing(after, delim) else {
+            continue;
+        };
+        if underscore
+            && after[end + delim.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric())
+        {
+            continue;
+        }
+        return Some((tag, &after[..end], delim.len() * 2 + end));
+    }
+    None
+}
+
+fn find_closing(text: &str, delim: &str) -> Option<usize> {
+    let delim_char = delim.as_bytes()[0] as char;
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(delim) {
+        let start = from + pos;
+        let mut idx = start;
+        // "***both***": close on the last delimiter of the run
+        while text[idx + delim.len()..].starts_with(delim_char) {
+            idx += 1;
+        }
+        // A run after whitespace opens nested emphasis: "*a **b***"
+        let before = &text[..start];
+        if !before.is_empty() && !before.ends_with(char::is_whitespace) {
+            return Some(idx);
+        }
+        from = idx + delim.len();
+    }
+    None
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&apos;"),
+        _ => out.push(c),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_escaped(&mut out, c);
+    }
+    out
+}
+
+// ── Widgets ──────────────────────────────────────────────────────────────────
+
+/// Replaces the children of `container` with the rendered Markdown.
+pub fn render_markdown(container: &gtk4::Box, text: &str) {
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+    for block in parse_blocks(text) {
+        container.append(&build_block(&block));
+    }
+}
+
+fn build_block(block: &Block) -> gtk4::Widget {
+    match block {
+        Block::Heading { level, markup } => {
+            let label = markup_label(markup);
+            label.add_css_class(match level {
+                1 => "title-3",
+                2 => "title-4",
+                _ => "heading",
+            });
+            label.set_margin_top(4);
+            label.upcast()
+        }
+        Block::Paragraph(markup) => markup_label(markup).upcast(),
+        Block::Quote(markup) => {
+            let label = markup_label(markup);
+            label.add_css_class("dim-label");
+            label.set_margin_start(12);
+            label.upcast()
+        }
+        Block::List(items) => {
+            let list = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+            for item in items {
+                let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+                row.set_margin_start(item.depth as i32 * 16);
+
+                let marker = gtk4::Label::new(Some(&item.marker));
+                marker.set_valign(gtk4::Align::Start);
+                marker.set_xalign(1.0);
+                marker.set_width_chars(2);
+
+                let text = markup_label(&item.markup);
+                text.set_hexpand(true);
+
+                row.append(&marker);
+                row.append(&text);
+                list.append(&row);
+            }
+            list.upcast()
+        }
+        Block::Table { header, rows } => {
+            let grid = gtk4::Grid::new();
+            grid.set_column_spacing(16);
+            grid.set_row_spacing(4);
+
+            for (col, cell) in header.iter().enumerate() {
+                let label = markup_label(cell);
+                label.add_css_class("heading");
+                grid.attach(&label, col as i32, 0, 1, 1);
+            }
+            let separator = gtk4::Separator::new(gtk4::Orientation::Horizontal);
+            grid.attach(&separator, 0, 1, header.len().max(1) as i32, 1);
+
+            for (r, row) in rows.iter().enumerate() {
+                for (col, cell) in row.iter().enumerate() {
+                    grid.attach(&markup_label(cell), col as i32, r as i32 + 2, 1, 1);
+                }
+            }
+
+            // Wide tables scroll instead of stretching the bubble
+            let scrolled = gtk4::ScrolledWindow::new();
+            scrolled.set_policy(gtk4::PolicyType::Automatic, gtk4::PolicyType::Never);
+            scrolled.set_propagate_natural_height(true);
+            scrolled.set_propagate_natural_width(true);
+            scrolled.set_child(Some(&grid));
+            scrolled.upcast()
+        }
+        Block::Code(code) => {
+            let label = gtk4::Label::new(Some(code));
+            label.add_css_class("monospace");
+            label.set_xalign(0.0);
+            label.set_selectable(true);
+            label.set_wrap(true);
+            label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+
+            let frame = gtk4::Frame::new(None);
+            label.set_margin_top(6);
+            label.set_margin_bottom(6);
+            label.set_margin_start(8);
+            label.set_margin_end(8);
+            frame.set_child(Some(&label));
+            frame.upcast()
+        }
+        Block::Rule => gtk4::Separator::new(gtk4::Orientation::Horizontal).upcast(),
+    }
+}
+
+/// Wrapping, selectable label; `<a>` links in the markup open in the browser.
+fn markup_label(markup: &str) -> gtk4::Label {
+    let label = gtk4::Label::new(None);
+    label.set_markup(markup);
+    label.set_wrap(true);
+    label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    label.set_xalign(0.0);
+    label.set_valign(gtk4::Align::Start);
+    label.set_selectable(true);
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that `markup` only uses the tags `inline_markup` emits,
+    /// properly nested, and that `<`, `>` and `&` are otherwise escaped.
+    fn assert_valid_markup(markup: &str) {
+        let mut open: Vec<&str> = Vec::new();
+        let mut rest = markup;
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '<' => {
+                    let end = rest.find('>').unwrap_or_else(|| panic!("unclosed tag in {markup:?}"));
+                    let tag = &rest[1..end];
+                    if let Some(name) = tag.strip_prefix('/') {
+                        assert_eq!(open.pop(), Some(name), "misnested </{name}> in {markup:?}");
+                    } else if let Some(href) = tag.strip_prefix("a href=\"") {
+                        let href = href.strip_suffix('"').unwrap_or_else(|| panic!("bad link in {markup:?}"));
+                        assert!(!href.contains(['"', '<', '>']), "unescaped href in {markup:?}");
+                        open.push("a");
+                    } else {
+                        assert!(["b", "i", "s", "tt"].contains(&tag), "unexpected <{tag}> in {markup:?}");
+                        open.push(tag);
+                    }
+                    rest = &rest[end + 1..];
+                }
+                '&' => {
+                    let entity = ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"]
+                        .into_iter()
+                        .find(|e| rest.starts_with(e))
+                        .unwrap_or_else(|| panic!("unescaped & in {markup:?}"));
+                    rest = &rest[entity.len()..];
+                }
+                '>' => panic!("unescaped > in {markup:?}"),
+                _ => rest = &rest[c.len_utf8()..],
+            }
+        }
+        assert!(open.is_empty(), "unclosed {open:?} in {markup:?}");
+    }
+
+    fn assert_blocks_valid(blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Heading { markup, .. } | Block::Paragraph(markup) | Block::Quote(markup) => {
+                    assert_valid_markup(markup)
+                }
+                Block::List(items) => items.iter().for_each(|item| assert_valid_markup(&item.markup)),
+                Block::Table { header, rows } => {
+                    header.iter().chain(rows.iter().flatten()).for_each(|cell| assert_valid_markup(cell))
+                }
+                Block::Code(_) | Block::Rule => {}
+            }
+        }
+    }
+
+    fn item(depth: usize, marker: &str, markup: &str) -> ListItem {
+        ListItem { depth, marker: marker.to_string(), markup: markup.to_string() }
+    }
+
+    const ANSWER: &str = "\
+## Zusammenfassung
+
+Ihr **LDL-Cholesterin** liegt mit *162 mg/dL* über dem Zielwert (< 116 mg/dL).
+Das Verhältnis LDL/HDL ist > 3.
+
+- **Ferritin**: 18 ng/mL – *leicht erniedrigt*
+  - häufige Ursache: Eisenmangel
+- Vitamin D: im Normbereich
+
+| Wert | Ergebnis | Referenz |
+|------|---------:|----------|
+| HbA1c | 5,9 % | < 5,7 % |
+| TSH | **2,1** mU/l | 0,4 – 4,0 |
+
+---
+
+> **Hinweis:** Dies ersetzt keine ärztliche Beratung.
+> Quelle: https://www.dge.de/.
+";
+
+    #[test]
+    fn parses_captured_answer() {
+        let blocks = parse_blocks(ANSWER);
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Heading { level: 2, markup: "Zusammenfassung".into() },
+                Block::Paragraph(
+                    "Ihr <b>LDL-Cholesterin</b> liegt mit <i>162 mg/dL</i> über dem Zielwert (&lt; 116 mg/dL).\n\
+                     Das Verhältnis LDL/HDL ist &gt; 3."
+                        .into()
+                ),
+                Block::List(vec![
+                    item(0, "•", "<b>Ferritin</b>: 18 ng/mL – <i>leicht erniedrigt</i>"),
+                    item(1, "◦", "häufige Ursache: Eisenmangel"),
+                    item(0, "•", "Vitamin D: im Normbereich"),
+                ]),
+                Block::Table {
+                    header: vec!["Wert".into(), "Ergebnis".into(), "Referenz".into()],
+                    rows: vec![
+                        vec!["HbA1c".into(), "5,9 %".into(), "&lt; 5,7 %".into()],
+                        vec!["TSH".into(), "<b>2,1</b> mU/l".into(), "0,4 – 4,0".into()],
+                    ],
+                },
+                Block::Rule,
+                Block::Quote(
+                    "<b>Hinweis:</b> Dies ersetzt keine ärztliche Beratung.\n\
+                     Quelle: <a href=\"https://www.dge.de/\">https://www.dge.de/</a>."
+                        .into()
+                ),
+            ]
+        );
+        assert_blocks_valid(&blocks);
+    }
+
+    #[test]
+    fn every_prefix_of_a_streamed_answer_is_valid_markup() {
+        for (i, _) in ANSWER.char_indices() {
+            assert_blocks_valid(&parse_blocks(&ANSWER[..i]));
+        }
+    }
+
+    #[test]
+    fn nested_emphasis() {
+        assert_eq!(inline_markup("**fett mit *kursiv* darin**"), "<b>fett mit <i>kursiv</i> darin</b>");
+        assert_eq!(inline_markup("*kursiv mit **fett***"), "<i>kursiv mit <b>fett</b></i>");
+        assert_eq!(inline_markup("***beides***"), "<b><i>beides</i></b>");
+        assert_eq!(inline_markup("~~**nicht mehr**~~"), "<s><b>nicht mehr</b></s>");
+        assert_eq!(inline_markup("__fett__ und _kursiv_"), "<b>fett</b> und <i>kursiv</i>");
+        assert_eq!(inline_markup("**[Quelle](https://example.org)**"), "<b><a href=\"https://example.org\">Quelle</a></b>");
+    }
+
+    #[test]
+    fn no_emphasis_inside_words_or_around_spaces() {
+        assert_eq!(inline_markup("ldl_hdl_ratio"), "ldl_hdl_ratio");
+        assert_eq!(inline_markup("2 * 3 * 4"), "2 * 3 * 4");
+        assert_eq!(inline_markup("\\*kein\\* Stern"), "*kein* Stern");
+    }
+
+    #[test]
+    fn unclosed_emphasis_stays_text() {
+        assert_eq!(inline_markup("Das ist **wichtig"), "Das ist **wichtig");
+        assert_eq!(inline_markup("**Ferritin**: 18 **ng"), "<b>Ferritin</b>: 18 **ng");
+        assert_eq!(inline_markup("*offen und `code"), "*offen und `code");
+        assert_eq!(inline_markup("[Link](https://example.org"), "[Link](<a href=\"https://example.org\">https://example.org</a>");
+        for text in ["**", "***", "** **", "__x", "~~", "`", "[", "[a]("] {
+            assert_valid_markup(&inline_markup(text));
+        }
+    }
+
+    #[test]
+    fn code_fences() {
+        let blocks = parse_blocks("Beispiel:\n\n```python\nif a < b and c > 0:\n    print(\"**nicht fett**\" & x)\n```\nDanach.");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Paragraph("Beispiel:".into()),
+                Block::Code("if a < b and c > 0:\n    print(\"**nicht fett**\" & x)".into()),
+                Block::Paragraph("Danach.".into()),
+            ]
+        );
+
+        // Still streaming: the fence is not closed yet
+        assert_eq!(parse_blocks("~~~\n# kein Titel\n- kein Punkt"), vec![Block::Code("# kein Titel\n- kein Punkt".into())]);
+        assert_eq!(inline_markup("`a <b> & c`"), "<tt>a &lt;b&gt; &amp; c</tt>");
+        assert_eq!(inline_markup("`**roh**`"), "<tt>**roh**</tt>");
+    }
+
+    #[test]
+    fn lists() {
+        let blocks = parse_blocks(
+            "1. Erstens\n2) Zweitens\n   weiter im Text\n\n3. Drittens\n- [ ] offen\n- [x] erledigt\n      - tief\n\nEnde",
+        );
+        assert_eq!(
+            blocks,
+            vec![
+                Block::List(vec![
+                    item(0, "1.", "Erstens"),
+                    item(0, "2.", "Zweitens\nweiter im Text"),
+                    item(0, "3.", "Drittens"),
+                    item(0, "☐", "offen"),
+                    item(0, "☑", "erledigt"),
+                    item(3, "▪", "tief"),
+                ]),
+                Block::Paragraph("Ende".into()),
+            ]
+        );
+        // Not a list
+        assert_eq!(parse_blocks("-5 °C"), vec![Block::Paragraph("-5 °C".into())]);
+    }
+
+    #[test]
+    fn tables() {
+        let blocks = parse_blocks("a | b\n--|--\n1 \\| 2 | <x>\nnur eins\n\ndanach");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Table {
+                    header: vec!["a".into(), "b".into()],
+                    rows: vec![vec!["1 | 2".into(), "&lt;x&gt;".into()]],
+                },
+                Block::Paragraph("nur eins".into()),
+                Block::Paragraph("danach".into()),
+            ]
+        );
+
+        // Short rows are padded to the header
+        let Block::Table { rows, .. } = &parse_blocks("|A|B|C|\n|-|-|-|\n|1|\n")[0] else {
+            panic!("expected a table");
+        };
+        assert_eq!(rows, &vec![vec!["1".to_string(), String::new(), String::new()]]);
+
+        // A pipe without separator line is text
+        assert_eq!(parse_blocks("a | b"), vec![Block::Paragraph("a | b".into())]);
+    }
+
+    #[test]
+    fn markup_in_answers_is_escaped() {
+        assert_eq!(
+            inline_markup("<span foreground='red'>rot</span> & <b>fett</b>"),
+            "&lt;span foreground=&apos;red&apos;&gt;rot&lt;/span&gt; &amp; &lt;b&gt;fett&lt;/b&gt;"
+        );
+        assert_eq!(inline_markup("**<i>**"), "<b>&lt;i&gt;</b>");
+        assert_eq!(inline_markup("&amp; &#60;"), "&amp;amp; &amp;#60;");
+        assert_eq!(
+            parse_blocks("# Werte <script>\n\n> \"Zitat\" & mehr"),
+            vec![
+                Block::Heading { level: 1, markup: "Werte &lt;script&gt;".into() },
+                Block::Quote("&quot;Zitat&quot; &amp; mehr".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn links_are_escaped_and_restricted() {
+        assert_eq!(
+            inline_markup("[x](https://a.de/\"onclick=\"alert)"),
+            "<a href=\"https://a.de/&quot;onclick=&quot;alert\">x</a>"
+        );
+        assert_eq!(inline_markup("[<b>x</b>](https://a.de)"), "<a href=\"https://a.de\">&lt;b&gt;x&lt;/b&gt;</a>");
+        assert_eq!(inline_markup("[klick](javascript:alert)"), "klick");
+        assert_eq!(inline_markup("[Datei](file:///etc/passwd)"), "Datei");
+        assert_eq!(
+            inline_markup("https://a.de/?a=1&b=<2>"),
+            "<a href=\"https://a.de/?a=1&amp;b=\">https://a.de/?a=1&amp;b=</a>&lt;2&gt;"
+        );
+    }
+}
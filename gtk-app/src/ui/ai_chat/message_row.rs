@@ -1,8 +1,15 @@
 use gtk4::prelude::*;
 
 use crate::api::types::ChatMessage;
+use super::markdown::render_markdown;
 
 pub fn build_message_row(msg: &ChatMessage) -> gtk4::Box {
+    build_message_row_with_content(msg).0
+}
+
+/// Like [`build_message_row`], but also returns the content box so the
+/// content can be updated while an answer is streamed in.
+pub fn build_message_row_with_content(msg: &ChatMessage) -> (gtk4::Box, gtk4::Box) {
     let is_user = msg.role == "user";
 
     let row_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
@@ -22,25 +29,19 @@ pub fn build_message_row(msg: &ChatMessage) -> gtk4::Box {
     let bubble_box = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
     bubble_box.set_hexpand(true);
 
-    let text_label = gtk4::Label::new(Some(&msg.content));
-    text_label.set_wrap(true);
-    text_label.set_xalign(0.0);
-    text_label.set_selectable(true);
-
-    // Apply basic markdown-like formatting
-    let markup = simple_markdown(&msg.content);
-    text_label.set_markup(&markup);
+    let content_box = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    set_message_content(&content_box, &msg.content);
 
     let frame = gtk4::Frame::new(None);
     frame.add_css_class("card");
     if is_user {
         frame.add_css_class("accent");
     }
-    frame.set_child(Some(&text_label));
-    text_label.set_margin_top(10);
-    text_label.set_margin_bottom(10);
-    text_label.set_margin_start(12);
-    text_label.set_margin_end(12);
+    frame.set_child(Some(&content_box));
+    content_box.set_margin_top(10);
+    content_box.set_margin_bottom(10);
+    content_box.set_margin_start(12);
+    content_box.set_margin_end(12);
 
     // Timestamp
     let time_label = gtk4::Label::new(Some(&format_time(&msg.timestamp)));
@@ -67,7 +68,11 @@ pub fn build_message_row(msg: &ChatMessage) -> gtk4::Box {
         bubble_box.set_halign(gtk4::Align::Start);
     }
 
-    row_box
+    (row_box, content_box)
+}
+
+pub fn set_message_content(content_box: &gtk4::Box, content: &str) {
+    render_markdown(content_box, content);
 }
 
 fn format_time(timestamp: &str) -> String {
@@ -79,50 +84,3 @@ fn format_time(timestamp: &str) -> String {
         timestamp.to_string()
     }
 }
-
-fn simple_markdown(text: &str) -> String {
-    // Escape XML special chars first
-    let escaped = glib::markup_escape_text(text);
-
-    // Apply very basic markdown
-    let mut result = escaped.to_string();
-
-    // Bold: **text**
-    result = regex_replace_bold(&result);
-
-    // We keep it simple to avoid regex dependency;
-    // just escape and wrap
-    result
-}
-
-fn regex_replace_bold(text: &str) -> String {
-    // Simple state-machine based **bold** replacement
-    let mut output = String::with_capacity(text.len());
-    let mut chars = text.chars().peekable();
-    let mut in_bold = false;
-
-    while let Some(c) = chars.next() {
-        if c == '*' {
-            if chars.peek() == Some(&'*') {
-                chars.next(); // consume second *
-                if in_bold {
-                    output.push_str("</b>");
-                    in_bold = false;
-                } else {
-                    output.push_str("<b>");
-                    in_bold = true;
-                }
-            } else {
-                output.push(c);
-            }
-        } else {
-            output.push(c);
-        }
-    }
-
-    if in_bold {
-        output.push_str("</b>"); // close unclosed bold
-    }
-
-    output
-}
@@ -1,16 +1,20 @@
+pub mod markdown;
 pub mod message_row;
 pub mod input_bar;
 
 use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use glib::clone;
 
-use crate::api::{ApiClient, types::*};
-use crate::state::spawn_task;
-use message_row::build_message_row;
+use crate::api::{types::*, ApiError};
+use crate::state::{spawn_abortable, spawn_task, AppStore};
+use crate::ui::error_actions::SETTINGS_ACTION;
+use message_row::{build_message_row, build_message_row_with_content, set_message_content};
+
+const OFFLINE_MESSAGE: &str = "Der KI-Doktor ist offline nicht verfügbar.";
 
 const SUGGESTED_PROMPTS: &[&str] = &[
     "Was bedeutet mein erhöhter LDL-Wert?",
@@ -21,7 +25,7 @@ const SUGGESTED_PROMPTS: &[&str] = &[
     "Was kann ich bei erhöhten Leberwerten tun?",
 ];
 
-pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
+pub fn build_ai_chat_page(store: &AppStore) -> adw::NavigationPage {
     let page = adw::NavigationPage::new(&gtk4::Label::new(None), "KI-Doktor");
     page.set_title("KI-Doktor");
 
@@ -82,13 +86,21 @@ pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
     error_label.set_visible(false);
     error_label.add_css_class("error");
 
+    // Offered when the server rejects the token
+    let token_btn = gtk4::Button::with_label("Token ändern");
+    token_btn.set_action_name(Some(SETTINGS_ACTION));
+    token_btn.set_halign(gtk4::Align::Center);
+    token_btn.set_margin_top(4);
+    token_btn.set_visible(false);
+
     // Input bar
-    let (input_widget, text_view, send_btn) = input_bar::build_input_bar();
+    let (input_widget, text_view, send_btn, stop_btn) = input_bar::build_input_bar();
 
     main_box.append(&header_box);
     main_box.append(&disclaimer);
     main_box.append(&scrolled);
     main_box.append(&error_label);
+    main_box.append(&token_btn);
     main_box.append(&input_widget);
 
     page.set_child(Some(&main_box));
@@ -96,6 +108,8 @@ pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
     // State (RC because single-threaded GTK)
     let messages: Rc<RefCell<Vec<ChatMessage>>> = Rc::new(RefCell::new(Vec::new()));
     let loading = Rc::new(RefCell::new(false));
+    // Running stream request, aborted by the stop button
+    let current_task: Rc<RefCell<Option<tokio::task::AbortHandle>>> = Rc::new(RefCell::new(None));
 
     // Helper: scroll to bottom
     let scroll_to_bottom = {
@@ -136,38 +150,77 @@ pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
     // Initial empty state
     rebuild_messages();
 
-    // Load history
-    {
-        let client = client.clone();
+    // Load history; the chat needs a server connection
+    let load_history = {
         let messages = messages.clone();
         let rebuild = rebuild_messages.clone();
         let scroll_to_bottom = scroll_to_bottom.clone();
+        let input_widget = input_widget.clone();
+        let error_label = error_label.clone();
+        let token_btn = token_btn.clone();
 
-        let (tx, rx) = async_channel::bounded::<Result<ChatHistory, String>>(1);
-        spawn_task(async move {
-            let r = client.get_chat_history().await.map_err(|e| e.to_string());
-            tx.send(r).await.ok();
-        });
+        Rc::new(move |store: &AppStore| {
+            messages.borrow_mut().clear();
+            rebuild();
+
+            let Some(client) = store.client() else {
+                input_widget.set_sensitive(false);
+                error_label.set_text(OFFLINE_MESSAGE);
+                error_label.set_visible(true);
+                return;
+            };
+            input_widget.set_sensitive(true);
+            error_label.set_visible(false);
+            token_btn.set_visible(false);
 
-        glib::MainContext::default().spawn_local(async move {
-            if let Ok(result) = rx.recv().await {
-                if let Ok(history) = result {
-                    *messages.borrow_mut() = history.messages;
+            let (tx, rx) = async_channel::bounded::<Result<ChatHistory, String>>(1);
+            spawn_task(async move {
+                let r = client.get_chat_history().await.map_err(|e| e.to_string());
+                tx.send(r).await.ok();
+            });
+
+            let messages = messages.clone();
+            let rebuild = rebuild.clone();
+            let scroll_to_bottom = scroll_to_bottom.clone();
+            glib::MainContext::default().spawn_local(async move {
+                if let Ok(result) = rx.recv().await {
+                    if let Ok(history) = result {
+                        *messages.borrow_mut() = history.messages;
+                    }
+                    rebuild();
+                    scroll_to_bottom();
                 }
-                rebuild();
-                scroll_to_bottom();
+            });
+        })
+    };
+    load_history(store);
+
+    // A new session (reconnect, refresh, other profile) reloads the history
+    {
+        let seen_generation = Rc::new(Cell::new(store.generation()));
+        let load_history = load_history.clone();
+        let loading = loading.clone();
+        store.connect_changed_for(&page, move |store| {
+            if store.generation() == seen_generation.get() || *loading.borrow() {
+                return;
             }
+            seen_generation.set(store.generation());
+            load_history(store);
         });
     }
 
     // Send message
     let send_message = {
-        let client = client.clone();
+        let store = store.clone();
         let messages = messages.clone();
         let loading = loading.clone();
+        let current_task = current_task.clone();
         let send_btn = send_btn.clone();
+        let stop_btn = stop_btn.clone();
         let error_label = error_label.clone();
+        let token_btn = token_btn.clone();
         let text_view = text_view.clone();
+        let messages_box = messages_box.clone();
         let rebuild = rebuild_messages.clone();
         let scroll_to_bottom = scroll_to_bottom.clone();
 
@@ -176,67 +229,129 @@ pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
             if text.is_empty() || *loading.borrow() {
                 return;
             }
+            let Some(client) = store.client() else {
+                error_label.set_text(OFFLINE_MESSAGE);
+                error_label.set_visible(true);
+                return;
+            };
 
             *loading.borrow_mut() = true;
-            send_btn.set_sensitive(false);
+            send_btn.set_visible(false);
+            stop_btn.set_visible(true);
             error_label.set_visible(false);
+            token_btn.set_visible(false);
             text_view.buffer().set_text("");
 
-            // Optimistic user message
+            // Optimistic user message, replaced by the stored one when the
+            // answer is complete. Its own id keeps messages of earlier,
+            // stopped answers in place.
+            let temp_id = glib::uuid_string_random();
+            let user_id = format!("temp-user-{temp_id}");
+            let assistant_id = format!("temp-assistant-{temp_id}");
             messages.borrow_mut().push(ChatMessage {
-                id: "temp-user".to_string(),
+                id: user_id.clone(),
                 role: "user".to_string(),
                 content: text.clone(),
                 timestamp: chrono::Utc::now().to_rfc3339(),
             });
             rebuild();
+
+            // Assistant bubble that is filled while tokens arrive
+            let (stream_row, stream_content) = build_message_row_with_content(&ChatMessage {
+                id: assistant_id.clone(),
+                role: "assistant".to_string(),
+                content: "…".to_string(),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            });
+            messages_box.append(&stream_row);
             scroll_to_bottom();
 
-            let client = client.clone();
+            let (tx, rx) = async_channel::unbounded::<Result<ChatStreamEvent, ApiError>>();
+            let handle = spawn_abortable(async move {
+                let events = tx.clone();
+                let r = client
+                    .send_chat_stream(&text, move |event| {
+                        events.try_send(Ok(event)).ok();
+                    })
+                    .await;
+                if let Err(e) = r {
+                    tx.send(Err(e)).await.ok();
+                }
+            });
+            *current_task.borrow_mut() = handle;
+
             let messages = messages.clone();
             let loading = loading.clone();
+            let current_task = current_task.clone();
             let send_btn = send_btn.clone();
+            let stop_btn = stop_btn.clone();
             let error_label = error_label.clone();
+            let token_btn = token_btn.clone();
             let rebuild = rebuild.clone();
             let scroll_to_bottom = scroll_to_bottom.clone();
 
-            let (tx, rx) = async_channel::bounded::<Result<ChatResponse, String>>(1);
-            spawn_task(async move {
-                let r = client.send_chat(&text).await.map_err(|e| e.to_string());
-                tx.send(r).await.ok();
-            });
-
             glib::MainContext::default().spawn_local(async move {
-                if let Ok(result) = rx.recv().await {
+                let mut streamed = String::new();
+
+                // Ends when the task finished or was aborted (sender dropped)
+                while let Ok(result) = rx.recv().await {
                     match result {
-                        Ok(resp) => {
+                        Ok(ChatStreamEvent::Token(token)) => {
+                            streamed.push_str(&token);
+                            set_message_content(&stream_content, &streamed);
+                            scroll_to_bottom();
+                        }
+                        Ok(ChatStreamEvent::Done(resp)) => {
                             let mut msgs = messages.borrow_mut();
-                            msgs.retain(|m| m.id != "temp-user");
+                            msgs.retain(|m| m.id != user_id);
                             msgs.push(resp.user_message);
                             msgs.push(resp.message);
-                            drop(msgs);
-                            rebuild();
-                            scroll_to_bottom();
+                            streamed.clear();
                         }
                         Err(e) => {
-                            messages.borrow_mut().retain(|m| m.id != "temp-user");
-                            let display = if e.starts_with("RATE_LIMIT:") {
-                                e.trim_start_matches("RATE_LIMIT:").trim().to_string()
-                            } else {
-                                format!("Fehler: {e}")
+                            messages.borrow_mut().retain(|m| m.id != user_id);
+                            let display = match e {
+                                ApiError::RateLimited { .. } => e.to_string(),
+                                _ => format!("Fehler: {e}"),
                             };
                             error_label.set_text(&display);
                             error_label.set_visible(true);
-                            rebuild();
+                            token_btn.set_visible(e == ApiError::Unauthorized);
+                            streamed.clear();
                         }
                     }
-                    *loading.borrow_mut() = false;
-                    send_btn.set_sensitive(true);
                 }
+
+                // Stopped early: keep the partial answer, the server stored it too
+                if !streamed.is_empty() {
+                    messages.borrow_mut().push(ChatMessage {
+                        id: assistant_id,
+                        role: "assistant".to_string(),
+                        content: streamed,
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                    });
+                }
+
+                current_task.borrow_mut().take();
+                rebuild();
+                scroll_to_bottom();
+                *loading.borrow_mut() = false;
+                stop_btn.set_visible(false);
+                send_btn.set_visible(true);
             });
         })
     };
 
+    // Stop generation
+    {
+        let current_task = current_task.clone();
+        stop_btn.connect_clicked(move |_| {
+            if let Some(handle) = current_task.borrow_mut().take() {
+                handle.abort();
+            }
+        });
+    }
+
     // Connect send button
     {
         let text_view = text_view.clone();
@@ -269,12 +384,12 @@ pub fn build_ai_chat_page(client: ApiClient) -> adw::NavigationPage {
 
     // Clear history
     {
-        let client = client.clone();
+        let store = store.clone();
         let messages = messages.clone();
         let rebuild = rebuild_messages.clone();
 
         clear_btn.connect_clicked(move |_| {
-            let client = client.clone();
+            let Some(client) = store.client() else { return };
             let messages = messages.clone();
             let rebuild = rebuild.clone();
 
@@ -0,0 +1,229 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+
+use crate::api::{types::*, ApiClient, ApiError};
+use crate::state::spawn_task;
+use crate::ui::received_shares::format_share_date;
+
+/// "API-Tokens" page of the settings: list, create and revoke the tokens of
+/// the account `client` is logged in to.
+pub fn build_tokens_page(dialog: &adw::PreferencesDialog, client: ApiClient) -> adw::PreferencesPage {
+    let page = adw::PreferencesPage::new();
+    page.set_title("API-Tokens");
+    page.set_icon_name(Some("dialog-password-symbolic"));
+
+    let create_group = adw::PreferencesGroup::new();
+    create_group.set_title("Neuer Token");
+    create_group.set_description(Some(
+        "Mit einem API-Token melden sich Apps wie diese bei deinem Konto an.",
+    ));
+
+    let name_row = adw::EntryRow::new();
+    name_row.set_title("Name, z.B. Laptop");
+    create_group.add(&name_row);
+
+    let create_btn = adw::ButtonRow::new();
+    create_btn.set_title("Token erstellen");
+    create_btn.set_start_icon_name(Some("list-add-symbolic"));
+    create_btn.add_css_class("suggested-action");
+    create_group.add(&create_btn);
+    page.add(&create_group);
+
+    let list_group = adw::PreferencesGroup::new();
+    list_group.set_title("Vorhandene Tokens");
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    let placeholder = gtk4::Label::new(Some("Keine Tokens vorhanden."));
+    placeholder.add_css_class("dim-label");
+    placeholder.set_margin_top(12);
+    placeholder.set_margin_bottom(12);
+    list.set_placeholder(Some(&placeholder));
+    list_group.add(&list);
+    page.add(&list_group);
+
+    let view = TokensView {
+        client,
+        list,
+        dialog: dialog.clone(),
+    };
+    view.reload();
+
+    create_btn.connect_activated(clone!(#[strong] view, #[weak] name_row, move |btn| {
+        let name = name_row.text().trim().to_string();
+        if name.is_empty() {
+            view.toast("Bitte einen Namen für den Token eingeben.");
+            return;
+        }
+        btn.set_sensitive(false);
+
+        let client = view.client.clone();
+        let (tx, rx) = async_channel::bounded::<Result<CreatedApiToken, ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.create_token(&name).await).await.ok();
+        });
+
+        let view = view.clone();
+        glib::MainContext::default().spawn_local(clone!(#[weak] btn, #[weak] name_row, async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(created) => {
+                        name_row.set_text("");
+                        show_created_token(&view.dialog, &created);
+                        view.reload();
+                    }
+                    Err(e) => view.toast(&format!("Token konnte nicht erstellt werden: {e}")),
+                }
+                btn.set_sensitive(true);
+            }
+        }));
+    }));
+
+    page
+}
+
+#[derive(Clone)]
+struct TokensView {
+    client: ApiClient,
+    list: gtk4::ListBox,
+    dialog: adw::PreferencesDialog,
+}
+
+impl TokensView {
+    fn reload(&self) {
+        let client = self.client.clone();
+        let (tx, rx) = async_channel::bounded::<Result<Vec<ApiToken>, ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.list_tokens().await).await.ok();
+        });
+
+        let view = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(tokens) => view.fill(&tokens),
+                    Err(e) => view.toast(&format!("Tokens konnten nicht geladen werden: {e}")),
+                }
+            }
+        });
+    }
+
+    fn fill(&self, tokens: &[ApiToken]) {
+        self.list.remove_all();
+        for token in tokens {
+            let row = adw::ActionRow::new();
+            row.set_title(&glib::markup_escape_text(&token.name));
+            let created = format_share_date(&token.created_at).unwrap_or_default();
+            if token.current {
+                row.set_subtitle(&format!("Erstellt am {created} · von dieser App verwendet"));
+            } else {
+                row.set_subtitle(&format!("Erstellt am {created}"));
+            }
+
+            let revoke_btn = gtk4::Button::from_icon_name("user-trash-symbolic");
+            revoke_btn.add_css_class("flat");
+            revoke_btn.set_valign(gtk4::Align::Center);
+            revoke_btn.set_tooltip_text(Some("Token widerrufen"));
+            let view = self.clone();
+            let token = token.clone();
+            revoke_btn.connect_clicked(move |_| view.confirm_revoke(&token));
+            row.add_suffix(&revoke_btn);
+
+            self.list.append(&row);
+        }
+    }
+
+    /// Asks before revoking, with a stronger warning for the token this
+    /// app is logged in with.
+    fn confirm_revoke(&self, token: &ApiToken) {
+        let (heading, body) = if token.current {
+            (
+                "Verwendeten Token widerrufen?".to_string(),
+                "Diese App meldet sich mit diesem Token an. Danach kann sie keine Daten mehr \
+                 laden, bis du unter „Verbindung“ einen neuen Token einträgst."
+                    .to_string(),
+            )
+        } else {
+            (
+                "Token widerrufen?".to_string(),
+                format!("Apps, die „{}“ verwenden, verlieren sofort den Zugriff.", token.name),
+            )
+        };
+        let alert = adw::AlertDialog::new(Some(&heading), Some(&body));
+        alert.add_response("cancel", "Abbrechen");
+        alert.add_response("revoke", "Widerrufen");
+        alert.set_response_appearance("revoke", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+
+        let view = self.clone();
+        let id = token.id.clone();
+        alert.connect_response(None, move |_, response| {
+            if response != "revoke" {
+                return;
+            }
+            let client = view.client.clone();
+            let id = id.clone();
+            let (tx, rx) = async_channel::bounded::<Result<(), ApiError>>(1);
+            spawn_task(async move {
+                tx.send(client.delete_token(&id).await).await.ok();
+            });
+
+            let view = view.clone();
+            glib::MainContext::default().spawn_local(async move {
+                if let Ok(result) = rx.recv().await {
+                    match result {
+                        Ok(()) => view.toast("Token widerrufen"),
+                        Err(e) => view.toast(&format!("Widerrufen fehlgeschlagen: {e}")),
+                    }
+                    view.reload();
+                }
+            });
+        });
+
+        alert.present(Some(&self.dialog));
+    }
+
+    fn toast(&self, message: &str) {
+        self.dialog.add_toast(adw::Toast::new(message));
+    }
+}
+
+/// Shows the value of a new token, which the server returns only once.
+fn show_created_token(parent: &adw::PreferencesDialog, created: &CreatedApiToken) {
+    let alert = adw::AlertDialog::new(
+        Some("Token erstellt"),
+        Some("Kopiere den Token jetzt. Er wird nicht noch einmal angezeigt."),
+    );
+
+    let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    let token_label = gtk4::Label::new(Some(&created.token));
+    token_label.set_selectable(true);
+    token_label.set_wrap(true);
+    token_label.set_wrap_mode(gtk4::pango::WrapMode::Char);
+    token_label.set_hexpand(true);
+    token_label.set_xalign(0.0);
+    token_label.add_css_class("monospace");
+
+    let copy_btn = gtk4::Button::from_icon_name("edit-copy-symbolic");
+    copy_btn.set_valign(gtk4::Align::Center);
+    copy_btn.set_tooltip_text(Some("In die Zwischenablage kopieren"));
+    let token = created.token.clone();
+    copy_btn.connect_clicked(move |btn| {
+        btn.clipboard().set_text(&token);
+        btn.set_icon_name("object-select-symbolic");
+        btn.set_tooltip_text(Some("Kopiert"));
+    });
+
+    hbox.append(&token_label);
+    hbox.append(&copy_btn);
+    alert.set_extra_child(Some(&hbox));
+
+    alert.add_response("close", "Fertig");
+    alert.set_default_response(Some("close"));
+    alert.set_close_response("close");
+    alert.present(Some(parent));
+}
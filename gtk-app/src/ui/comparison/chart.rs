@@ -0,0 +1,424 @@
+use cairo::Context;
+use chrono::NaiveDate;
+
+use crate::api::types::*;
+
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+/// Right margin when the second Y axis is labelled there
+const MARGIN_RIGHT_AXIS: f64 = 60.0;
+const MARGIN_TOP: f64 = 16.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const LEGEND_LINE_HEIGHT: f64 = 18.0;
+
+/// Series colors, in the order values are picked.
+pub const PALETTE: [(f64, f64, f64); 6] = [
+    (0.231, 0.510, 0.965), // blue-500
+    (0.976, 0.451, 0.086), // orange-500
+    (0.545, 0.361, 0.965), // violet-500
+    (0.925, 0.282, 0.600), // pink-500
+    (0.024, 0.714, 0.831), // cyan-500
+    (0.396, 0.639, 0.051), // lime-600
+];
+
+/// How series with different units share the Y axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompareScale {
+    /// Own units, the first on the left axis, the second on the right
+    #[default]
+    DualAxis,
+    /// 0 % is the lower, 100 % the upper bound of the reference range
+    PercentOfRange,
+    /// Standard deviations from the middle of the reference range, taken
+    /// as mean ± 2 SD
+    ZScore,
+}
+
+impl CompareScale {
+    pub const ALL: [CompareScale; 3] = [
+        CompareScale::DualAxis,
+        CompareScale::PercentOfRange,
+        CompareScale::ZScore,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CompareScale::DualAxis => "Zwei Y-Achsen",
+            CompareScale::PercentOfRange => "% des Referenzbereichs",
+            CompareScale::ZScore => "z-Wert",
+        }
+    }
+
+    /// The normal band on a normalized axis.
+    fn band(&self) -> Option<(f64, f64)> {
+        match self {
+            CompareScale::DualAxis => None,
+            CompareScale::PercentOfRange => Some((0.0, 100.0)),
+            CompareScale::ZScore => Some((-2.0, 2.0)),
+        }
+    }
+}
+
+/// `value` as percent of `range`: 0 at the lower, 100 at the upper bound.
+/// Without a lower bound it is taken as 0; `None` without an upper bound.
+pub fn percent_of_range(value: f64, range: (Option<f64>, Option<f64>)) -> Option<f64> {
+    let (min, max) = (range.0.unwrap_or(0.0), range.1?);
+    (max > min).then(|| (value - min) / (max - min) * 100.0)
+}
+
+/// z-score of `value` if the reference range covers mean ± 2 SD. `None`
+/// without both bounds.
+pub fn z_score(value: f64, range: (Option<f64>, Option<f64>)) -> Option<f64> {
+    let (min, max) = (range.0?, range.1?);
+    let sd = (max - min) / 4.0;
+    (sd > 0.0).then(|| (value - (min + max) / 2.0) / sd)
+}
+
+/// One value over time, with the normal range on each date.
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    pub unit: String,
+    pub color: (f64, f64, f64),
+    pub points: Vec<SeriesPoint>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeriesPoint {
+    pub date: NaiveDate,
+    pub value: f64,
+    pub range: (Option<f64>, Option<f64>),
+    pub derived: bool,
+}
+
+impl Series {
+    /// Points with a date that can be read, oldest first.
+    pub fn new(
+        name: &str,
+        history: &[ValueHistoryPoint],
+        ref_val: Option<&ReferenceValue>,
+        evaluation: &Evaluation,
+        color: (f64, f64, f64),
+    ) -> Self {
+        let mut points: Vec<SeriesPoint> = history
+            .iter()
+            .filter_map(|p| {
+                Some(SeriesPoint {
+                    date: NaiveDate::parse_from_str(p.date.get(..10)?, "%Y-%m-%d").ok()?,
+                    value: p.value,
+                    range: ref_val.map(|r| evaluation.range(r, &p.date)).unwrap_or((None, None)),
+                    derived: p.derived,
+                })
+            })
+            .collect();
+        points.sort_by_key(|p| p.date);
+        let unit = ref_val
+            .map(|r| r.unit.clone())
+            .or_else(|| history.last().map(|p| p.unit.clone()))
+            .unwrap_or_default();
+        Self { name: name.to_string(), unit, color, points }
+    }
+
+    /// Value and range of each point on `scale`; points that cannot be
+    /// normalized are left out.
+    fn scaled(&self, scale: CompareScale) -> Vec<SeriesPoint> {
+        self.points
+            .iter()
+            .filter_map(|p| {
+                let value = match scale {
+                    CompareScale::DualAxis => p.value,
+                    CompareScale::PercentOfRange => percent_of_range(p.value, p.range)?,
+                    CompareScale::ZScore => z_score(p.value, p.range)?,
+                };
+                Some(SeriesPoint { value, ..p.clone() })
+            })
+            .collect()
+    }
+}
+
+/// The Y axis of each series in dual-axis mode: units in the order they
+/// appear, the first on the left (0), the second on the right (1). A third
+/// unit has no axis.
+pub fn assign_axes(series: &[Series]) -> Vec<Option<usize>> {
+    let mut units: Vec<&str> = Vec::new();
+    series
+        .iter()
+        .map(|s| {
+            let pos = match units.iter().position(|u| *u == s.unit) {
+                Some(pos) => pos,
+                None => {
+                    units.push(&s.unit);
+                    units.len() - 1
+                }
+            };
+            (pos < 2).then_some(pos)
+        })
+        .collect()
+}
+
+/// Padded value domain of `values`.
+fn domain(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(mn, mx), v| (mn.min(v), mx.max(v)));
+    if !min.is_finite() || !max.is_finite() {
+        return None;
+    }
+    let pad = (max - min) * 0.15;
+    let pad = if pad < 0.001 { 1.0 } else { pad };
+    Some((min - pad, max + pad))
+}
+
+/// Legend text of a series, noting why it is not (fully) shown.
+pub fn legend_label(series: &Series, scale: CompareScale, axis: Option<usize>) -> String {
+    let base = if series.unit.is_empty() {
+        series.name.clone()
+    } else {
+        format!("{} ({})", series.name, series.unit)
+    };
+    let shown = series.scaled(scale).len();
+    if scale == CompareScale::DualAxis && axis.is_none() {
+        format!("{base} – dritte Einheit, bitte normalisieren")
+    } else if shown == 0 {
+        format!("{base} – kein Referenzbereich")
+    } else if shown < series.points.len() {
+        format!("{base} – teils ohne Referenzbereich")
+    } else if scale == CompareScale::DualAxis && axis == Some(1) {
+        format!("{base} – rechte Achse")
+    } else {
+        base
+    }
+}
+
+pub fn draw_comparison(cr: &Context, width: i32, height: i32, series: &[Series], scale: CompareScale) {
+    let w = width as f64;
+    let h = height as f64;
+
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    let _ = cr.paint();
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    let axes = match scale {
+        CompareScale::DualAxis => assign_axes(series),
+        _ => vec![Some(0); series.len()],
+    };
+    let scaled: Vec<_> = series.iter().map(|s| s.scaled(scale)).collect();
+    let plotted = |i: usize| axes[i].is_some() && !scaled[i].is_empty();
+
+    let has_right_axis = axes.contains(&Some(1));
+    let margin_right = if has_right_axis { MARGIN_RIGHT_AXIS } else { MARGIN_RIGHT };
+
+    // Legend above the plot, wrapped to the width
+    cr.set_font_size(11.0);
+    let mut legend = Vec::new();
+    let (mut lx, mut ly) = (MARGIN_LEFT, MARGIN_TOP);
+    for (i, s) in series.iter().enumerate() {
+        let text = legend_label(s, scale, axes[i]);
+        let text_w = cr.text_extents(&text).map(|e| e.width()).unwrap_or(0.0);
+        let item_w = 16.0 + text_w + 16.0;
+        if lx > MARGIN_LEFT && lx + item_w > w - margin_right {
+            lx = MARGIN_LEFT;
+            ly += LEGEND_LINE_HEIGHT;
+        }
+        legend.push((lx, ly, s.color, text));
+        lx += item_w;
+    }
+    let margin_top = if series.is_empty() { MARGIN_TOP } else { ly + LEGEND_LINE_HEIGHT + 8.0 };
+
+    let plot_w = w - MARGIN_LEFT - margin_right;
+    let plot_h = h - margin_top - MARGIN_BOTTOM;
+
+    for (x, y, (r, g, b), text) in &legend {
+        cr.set_source_rgb(*r, *g, *b);
+        cr.rectangle(*x, y + 2.0, 10.0, 10.0);
+        let _ = cr.fill();
+        cr.set_source_rgb(0.25, 0.25, 0.25);
+        let _ = cr.move_to(x + 16.0, y + 11.0);
+        let _ = cr.show_text(text);
+    }
+
+    let dates = (0..series.len()).filter(|&i| plotted(i)).flat_map(|i| scaled[i].iter().map(|p| p.date));
+    let (first, last) = match dates.fold(None, |acc: Option<(NaiveDate, NaiveDate)>, d| {
+        Some(acc.map_or((d, d), |(a, b)| (a.min(d), b.max(d))))
+    }) {
+        Some(span) if plot_w > 0.0 && plot_h > 0.0 => span,
+        _ => {
+            cr.set_source_rgb(0.5, 0.5, 0.5);
+            cr.set_font_size(13.0);
+            let text = if series.is_empty() {
+                "Wähle Werte zum Vergleichen aus"
+            } else {
+                "Keine darstellbaren Daten"
+            };
+            let text_w = cr.text_extents(text).map(|e| e.width()).unwrap_or(0.0);
+            let _ = cr.move_to(w / 2.0 - text_w / 2.0, h / 2.0);
+            let _ = cr.show_text(text);
+            return;
+        }
+    };
+
+    // Y domain per axis, with the bands that are drawn
+    let axis_domain = |axis: usize| {
+        let values = (0..series.len())
+            .filter(|&i| plotted(i) && axes[i] == Some(axis))
+            .flat_map(|i| {
+                scaled[i].iter().flat_map(|p| {
+                    let bounds = match scale {
+                        CompareScale::DualAxis => [p.range.0, p.range.1],
+                        _ => [None, None],
+                    };
+                    std::iter::once(p.value).chain(bounds.into_iter().flatten())
+                })
+            })
+            .chain(scale.band().into_iter().flat_map(|(a, b)| [a, b]));
+        domain(values)
+    };
+    let domains = [axis_domain(0), axis_domain(1)];
+
+    let total_days = (last - first).num_days() as f64;
+    let to_x = |date: NaiveDate| -> f64 {
+        if total_days <= 0.0 {
+            MARGIN_LEFT + plot_w / 2.0
+        } else {
+            MARGIN_LEFT + (date - first).num_days() as f64 / total_days * plot_w
+        }
+    };
+    let to_y = |axis: usize, val: f64| -> f64 {
+        let (y_min, y_max) = domains[axis].unwrap_or((0.0, 1.0));
+        let frac = (val - y_min) / (y_max - y_min);
+        margin_top + (1.0 - frac) * plot_h
+    };
+
+    // Grid lines & Y axis labels
+    cr.set_line_width(1.0);
+    cr.set_font_size(10.0);
+    let grid_steps = 5;
+    for i in 0..=grid_steps {
+        let frac = i as f64 / grid_steps as f64;
+        let y = margin_top + (1.0 - frac) * plot_h;
+
+        cr.set_source_rgba(0.0, 0.0, 0.0, 0.08);
+        let _ = cr.move_to(MARGIN_LEFT, y);
+        let _ = cr.line_to(MARGIN_LEFT + plot_w, y);
+        let _ = cr.stroke();
+
+        cr.set_source_rgb(0.4, 0.4, 0.4);
+        if let Some((y_min, y_max)) = domains[0] {
+            let label = format_axis_val(y_min + (y_max - y_min) * frac);
+            let (lbl_w, lbl_h) = cr.text_extents(&label).map(|e| (e.width(), e.height())).unwrap_or((0.0, 0.0));
+            let _ = cr.move_to(MARGIN_LEFT - lbl_w - 6.0, y + lbl_h / 2.0);
+            let _ = cr.show_text(&label);
+        }
+        if let (true, Some((y_min, y_max))) = (has_right_axis, domains[1]) {
+            let label = format_axis_val(y_min + (y_max - y_min) * frac);
+            let lbl_h = cr.text_extents(&label).map(|e| e.height()).unwrap_or(0.0);
+            let _ = cr.move_to(MARGIN_LEFT + plot_w + 6.0, y + lbl_h / 2.0);
+            let _ = cr.show_text(&label);
+        }
+    }
+
+    // Normal band: one for all on a normalized axis, else one per series
+    cr.save().ok();
+    cr.rectangle(MARGIN_LEFT, margin_top, plot_w, plot_h);
+    cr.clip();
+    if let Some((low, high)) = scale.band() {
+        let (y1, y2) = (to_y(0, high), to_y(0, low));
+        cr.set_source_rgba(0.133, 0.773, 0.369, 0.12); // green
+        cr.rectangle(MARGIN_LEFT, y1, plot_w, y2 - y1);
+        let _ = cr.fill();
+        cr.set_source_rgba(0.133, 0.773, 0.369, 0.6);
+        cr.set_dash(&[4.0, 4.0], 0.0);
+        for y in [y1, y2] {
+            let _ = cr.move_to(MARGIN_LEFT, y);
+            let _ = cr.line_to(MARGIN_LEFT + plot_w, y);
+            let _ = cr.stroke();
+        }
+        cr.set_dash(&[], 0.0);
+    } else {
+        for i in (0..series.len()).filter(|&i| plotted(i)) {
+            let axis = axes[i].unwrap_or(0);
+            let (r, g, b) = series[i].color;
+            let points = &scaled[i];
+            for (j, point) in points.iter().enumerate() {
+                let (Some(mn), Some(mx)) = point.range else { continue };
+                // Halfway to the neighbouring measurements of this series
+                let x = to_x(point.date);
+                let x1 = if j == 0 { x } else { (to_x(points[j - 1].date) + x) / 2.0 };
+                let x2 = match points.get(j + 1) {
+                    Some(next) => (x + to_x(next.date)) / 2.0,
+                    None => x,
+                };
+                let (x1, x2) = if points.len() == 1 { (MARGIN_LEFT, MARGIN_LEFT + plot_w) } else { (x1, x2) };
+                let (y1, y2) = (to_y(axis, mx), to_y(axis, mn));
+                cr.set_source_rgba(r, g, b, 0.08);
+                cr.rectangle(x1, y1, x2 - x1, y2 - y1);
+                let _ = cr.fill();
+                cr.set_source_rgba(r, g, b, 0.5);
+                cr.set_dash(&[4.0, 4.0], 0.0);
+                for y in [y1, y2] {
+                    let _ = cr.move_to(x1, y);
+                    let _ = cr.line_to(x2, y);
+                    let _ = cr.stroke();
+                }
+                cr.set_dash(&[], 0.0);
+            }
+        }
+    }
+    cr.restore().ok();
+
+    // Lines and points
+    for i in (0..series.len()).filter(|&i| plotted(i)) {
+        let axis = axes[i].unwrap_or(0);
+        let (r, g, b) = series[i].color;
+
+        cr.set_source_rgb(r, g, b);
+        cr.set_line_width(2.0);
+        for (j, point) in scaled[i].iter().enumerate() {
+            let (x, y) = (to_x(point.date), to_y(axis, point.value));
+            if j == 0 {
+                let _ = cr.move_to(x, y);
+            } else {
+                let _ = cr.line_to(x, y);
+            }
+        }
+        let _ = cr.stroke();
+
+        for point in &scaled[i] {
+            let (x, y) = (to_x(point.date), to_y(axis, point.value));
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            cr.arc(x, y, 5.0, 0.0, 2.0 * std::f64::consts::PI);
+            let _ = cr.fill();
+            cr.set_source_rgb(r, g, b);
+            if point.derived {
+                cr.set_line_width(2.0);
+                cr.arc(x, y, 3.5, 0.0, 2.0 * std::f64::consts::PI);
+                let _ = cr.stroke();
+            } else {
+                cr.arc(x, y, 4.0, 0.0, 2.0 * std::f64::consts::PI);
+                let _ = cr.fill();
+            }
+        }
+    }
+
+    // X axis date labels (rotated), evenly spread over the time span
+    cr.set_source_rgb(0.4, 0.4, 0.4);
+    cr.set_font_size(9.0);
+    let ticks = if total_days <= 0.0 { 0 } else { 6 };
+    for t in 0..=ticks {
+        let date = first + chrono::Duration::days((total_days * t as f64 / ticks.max(1) as f64).round() as i64);
+        cr.save().ok();
+        let _ = cr.translate(to_x(date), margin_top + plot_h + 6.0);
+        let _ = cr.rotate(-std::f64::consts::PI / 4.0);
+        let _ = cr.move_to(0.0, 0.0);
+        let _ = cr.show_text(&date.format("%d.%m.%y").to_string());
+        cr.restore().ok();
+    }
+}
+
+fn format_axis_val(v: f64) -> String {
+    if v.abs() >= 100.0 {
+        format!("{:.0}", v)
+    } else if v.abs() >= 10.0 {
+        format!("{:.1}", v)
+    } else {
+        format!("{:.2}", v)
+    }
+}
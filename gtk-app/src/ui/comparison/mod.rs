@@ -0,0 +1,149 @@
+pub mod chart;
+
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use glib::clone;
+
+use crate::state::AppStore;
+use crate::ui::dashboard::{category_group::collect_history_for, collect_latest_values, find_reference};
+use chart::{draw_comparison, CompareScale, Series, PALETTE};
+
+/// Page that plots several values on a shared time axis, e.g. TSH and fT4.
+/// `selected` are the value names picked at first.
+pub fn build_comparison_page(store: &AppStore, selected: &[String]) -> adw::NavigationPage {
+    let page = adw::NavigationPage::new(&gtk4::Label::new(None), "Werte vergleichen");
+    page.set_tag(Some("comparison"));
+
+    let scrolled = gtk4::ScrolledWindow::new();
+    scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+    scrolled.set_vexpand(true);
+
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 16);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let chart_area = gtk4::DrawingArea::new();
+    chart_area.set_size_request(-1, 320);
+    chart_area.set_hexpand(true);
+    let chart_frame = gtk4::Frame::new(None);
+    chart_frame.add_css_class("card");
+    chart_frame.set_child(Some(&chart_area));
+    vbox.append(&chart_frame);
+
+    let options_group = adw::PreferencesGroup::new();
+    let scale_row = adw::ComboRow::new();
+    scale_row.set_title("Darstellung");
+    scale_row.set_subtitle("Unterschiedliche Einheiten auf zwei Achsen oder normiert");
+    let labels: Vec<&str> = CompareScale::ALL.iter().map(|s| s.label()).collect();
+    scale_row.set_model(Some(&gtk4::StringList::new(&labels)));
+    options_group.add(&scale_row);
+    vbox.append(&options_group);
+
+    // Values to pick, in dashboard order
+    let values_group = adw::PreferencesGroup::new();
+    values_group.set_title("Werte");
+    values_group.set_description(Some(&format!("Bis zu {} Werte gleichzeitig", PALETTE.len())));
+    vbox.append(&values_group);
+
+    scrolled.set_child(Some(&vbox));
+    page.set_child(Some(&scrolled));
+
+    let picked: Rc<RefCell<Vec<String>>> =
+        Rc::new(RefCell::new(selected.iter().take(PALETTE.len()).cloned().collect()));
+    let scale = Rc::new(Cell::new(CompareScale::default()));
+    let series: Rc<RefCell<Vec<Series>>> = Rc::new(RefCell::new(Vec::new()));
+
+    // Recomputes the series from the store, e.g. after an edit
+    let update_series = {
+        let picked = picked.clone();
+        let series = series.clone();
+        Rc::new(move |store: &AppStore| {
+            let data = store.display_data();
+            let evaluation = store.evaluation();
+            *series.borrow_mut() = picked
+                .borrow()
+                .iter()
+                .zip(PALETTE)
+                .map(|(name, color)| {
+                    let history = collect_history_for(&data.user_data, name);
+                    let ref_val = find_reference(&data.reference_db, name);
+                    Series::new(name, &history, ref_val, &evaluation, color)
+                })
+                .collect();
+        })
+    };
+    update_series(store);
+
+    chart_area.set_draw_func(clone!(#[strong] series, #[strong] scale, move |_, cr, width, height| {
+        draw_comparison(cr, width, height, &series.borrow(), scale.get());
+    }));
+
+    scale_row.connect_selected_notify(clone!(#[strong] scale, #[weak] chart_area, move |row| {
+        scale.set(CompareScale::ALL.get(row.selected() as usize).copied().unwrap_or_default());
+        chart_area.queue_draw();
+    }));
+
+    let store = store.clone();
+    let checks: Rc<RefCell<Vec<glib::WeakRef<gtk4::CheckButton>>>> = Rc::new(RefCell::new(Vec::new()));
+    // Derived values can be compared too
+    let names: Vec<String> = collect_latest_values(&store.display_data().user_data)
+        .into_iter()
+        .map(|(_, bv)| bv.name)
+        .collect();
+    for name in names {
+        let row = adw::ActionRow::new();
+        row.set_title(&glib::markup_escape_text(&name));
+        let check = gtk4::CheckButton::new();
+        check.set_active(picked.borrow().contains(&name));
+        row.add_prefix(&check);
+        row.set_activatable_widget(Some(&check));
+        values_group.add(&row);
+        checks.borrow_mut().push(check.downgrade());
+
+        check.connect_toggled(clone!(
+            #[strong] picked,
+            #[strong] checks,
+            #[strong] update_series,
+            #[strong] store,
+            #[weak] chart_area,
+            move |check| {
+                {
+                    let mut picked = picked.borrow_mut();
+                    if check.is_active() {
+                        if !picked.contains(&name) {
+                            picked.push(name.clone());
+                        }
+                    } else {
+                        picked.retain(|n| *n != name);
+                    }
+                }
+                update_limit(&checks.borrow(), picked.borrow().len());
+                update_series(&store);
+                chart_area.queue_draw();
+            }
+        ));
+    }
+    update_limit(&checks.borrow(), picked.borrow().len());
+
+    store.connect_changed_for(&page, clone!(#[strong] update_series, #[weak] chart_area, move |store| {
+        if store.is_loaded() {
+            update_series(store);
+            chart_area.queue_draw();
+        }
+    }));
+
+    page
+}
+
+/// Once the palette is used up, only picked values can be toggled.
+fn update_limit(checks: &[glib::WeakRef<gtk4::CheckButton>], picked: usize) {
+    let full = picked >= PALETTE.len();
+    for check in checks.iter().filter_map(|c| c.upgrade()) {
+        check.set_sensitive(!full || check.is_active());
+    }
+}
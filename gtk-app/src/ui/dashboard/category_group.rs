@@ -4,44 +4,40 @@ use libadwaita as adw;
 use glib::clone;
 
 use crate::api::types::*;
+use crate::state::AppStore;
+use crate::units::DisplayData;
+use crate::ui::entry_editor::EntryEditorContext;
 use crate::ui::value_detail::build_value_detail_page;
 use super::{find_reference, value_card::build_value_card};
 
 pub fn build_category_group(
     category: &str,
     values: &[&BloodValue],
-    reference_db: &[ReferenceValue],
-    gender: Option<&str>,
+    data: &DisplayData,
+    evaluation: &Evaluation,
     nav_view: &adw::NavigationView,
-    user_data: &UserData,
+    store: &AppStore,
+    editor: &EntryEditorContext,
 ) -> adw::PreferencesGroup {
     let group = adw::PreferencesGroup::new();
     group.set_title(category);
 
     for &bv in values {
-        let ref_val = find_reference(reference_db, &bv.name);
+        let ref_val = find_reference(&data.reference_db, &bv.name);
 
         // Build history for trend
-        let history = collect_history_for(user_data, &bv.name);
-        let row = build_value_card(bv, ref_val, gender, &history);
+        let history = collect_history_for(&data.user_data, &bv.name);
+        let warnings = history
+            .last()
+            .map_or(&[][..], |latest| data.warnings(&latest.entry_id, &bv.name));
+        let row = build_value_card(bv, ref_val, evaluation, &history, warnings);
 
-        // Navigate to detail on click
+        // Navigate to detail on click; the page reads its data from the store
         let bv_name = bv.name.clone();
-        let ref_val_owned: Option<ReferenceValue> = ref_val.cloned();
-        let user_data_clone = user_data.clone();
-        let gender_owned = gender.map(|s| s.to_string());
-        let nav_view_clone = nav_view.clone();
-        let history_clone = history.clone();
-
-        row.connect_activated(move |_| {
-            let detail_page = build_value_detail_page(
-                &bv_name,
-                &history_clone,
-                ref_val_owned.as_ref(),
-                gender_owned.as_deref(),
-            );
-            nav_view_clone.push(&detail_page);
-        });
+        row.connect_activated(clone!(#[weak] nav_view, #[strong] store, #[strong] editor, move |_| {
+            let detail_page = build_value_detail_page(&bv_name, &nav_view, &store, &editor);
+            nav_view.push(&detail_page);
+        }));
 
         group.add(&row);
     }
@@ -49,7 +45,7 @@ pub fn build_category_group(
     group
 }
 
-fn collect_history_for(user_data: &UserData, name: &str) -> Vec<ValueHistoryPoint> {
+pub fn collect_history_for(user_data: &UserData, name: &str) -> Vec<ValueHistoryPoint> {
     user_data
         .entries
         .iter()
@@ -59,6 +55,7 @@ fn collect_history_for(user_data: &UserData, name: &str) -> Vec<ValueHistoryPoin
                 value: v.value,
                 unit: v.unit.clone(),
                 entry_id: entry.id.clone(),
+                derived: v.derived,
             })
         })
         .collect()
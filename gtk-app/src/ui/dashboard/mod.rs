@@ -5,15 +5,23 @@ pub mod value_card;
 use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
+use glib::clone;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::api::types::*;
-use super::value_detail::build_value_detail_page;
+use crate::state::{AppStore, DataSource};
+use crate::validation::ValueWarning;
+use super::entry_editor::{show_entry_editor, EntryEditorContext};
+use super::scan_import::choose_and_scan;
+use super::comparison::build_comparison_page;
+use super::report::show_report_dialog;
+use super::value_detail::{build_value_detail_page, format_date};
 
 pub fn build_dashboard_page(
     nav_view: &adw::NavigationView,
-    user_data: &UserData,
-    reference_db: &[ReferenceValue],
-    gender: Option<&str>,
+    store: &AppStore,
+    editor: &EntryEditorContext,
 ) -> adw::NavigationPage {
     let page = adw::NavigationPage::new(
         &gtk4::Label::new(None), // placeholder child, replaced below
@@ -30,18 +38,117 @@ pub fn build_dashboard_page(
     vbox.set_margin_start(16);
     vbox.set_margin_end(16);
 
+    let sections: Rc<RefCell<Sections>> = Rc::default();
+    fill_dashboard(&vbox, &sections, nav_view, store, editor);
+
+    scrolled.set_child(Some(&vbox));
+    page.set_child(Some(&scrolled));
+    page.set_tag(Some("dashboard"));
+    if store.source() == Some(DataSource::Shared) {
+        page.set_title(&store.user_data().display_name);
+    }
+
+    // Edits, refreshes and reconnects update the page in place, keeping
+    // the scroll position
+    store.connect_changed_for(&page, clone!(#[weak] scrolled, #[weak] vbox, #[weak] nav_view, #[strong] sections, #[strong] editor, move |store| {
+        if store.is_loaded() {
+            let adjustment = scrolled.vadjustment();
+            let position = adjustment.value();
+            fill_dashboard(&vbox, &sections, &nav_view, store, &editor);
+            // The new content is only measured on the next layout
+            glib::idle_add_local_once(move || adjustment.set_value(position));
+        }
+    }));
+
+    page
+}
+
+/// What a section of the dashboard shows. Updates keep the widgets whose
+/// key is unchanged.
+#[derive(PartialEq)]
+enum SectionKey {
+    Actions { has_entries: bool, editable: bool },
+    Summary(StatusCounts, EvaluationMode),
+    Critical(String),
+    Category { name: String, cards: Vec<CardKey>, evaluation: Evaluation },
+    Entries(EntriesKey),
+    Empty { editable: bool },
+}
+
+/// Everything a value card shows: the value, its trend and warnings and
+/// the reference it is graded by.
+#[derive(PartialEq)]
+struct CardKey {
+    value: BloodValue,
+    history: Vec<ValueHistoryPoint>,
+    warnings: Vec<ValueWarning>,
+    reference: Option<ReferenceValue>,
+}
+
+/// The stored entries, compared by content.
+struct EntriesKey(Rc<UserData>);
+
+impl PartialEq for EntriesKey {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0.entries == other.0.entries
+    }
+}
+
+/// Widgets of the dashboard in display order.
+type Sections = Vec<(SectionKey, gtk4::Widget)>;
+
+/// The widget from `old` that shows `key`, or a new one.
+fn reuse_or_build<W: IsA<gtk4::Widget>>(
+    old: &mut Sections,
+    key: SectionKey,
+    build: impl FnOnce() -> W,
+) -> (SectionKey, gtk4::Widget) {
+    match old.iter().position(|(k, _)| *k == key) {
+        Some(i) => old.remove(i),
+        None => (key, build().upcast()),
+    }
+}
+
+fn fill_dashboard(
+    vbox: &gtk4::Box,
+    sections: &RefCell<Sections>,
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    ctx: &EntryEditorContext,
+) {
+    // Measurements converted to the units of their reference values
+    let data = store.display_data();
+    let user_data = &data.user_data;
+    let reference_db = &data.reference_db;
+    let evaluation = store.evaluation();
+    // Offline snapshots and shared data are read-only
+    let editor = (!store.is_read_only()).then_some(ctx);
+
+    let mut old = sections.take();
+    let mut new = Sections::new();
+
+    let has_entries = !user_data.entries.is_empty();
+    if has_entries || editor.is_some() {
+        let key = SectionKey::Actions { has_entries, editable: editor.is_some() };
+        new.push(reuse_or_build(&mut old, key, || {
+            build_actions(nav_view, store, has_entries, editor)
+        }));
+    }
+
     // Collect latest values across all entries
     let latest_values = collect_latest_values(user_data);
 
     // Summary bar
-    let summary_counts = compute_summary_counts(&latest_values, reference_db, gender);
-    let summary_bar = summary_bar::build_summary_bar(summary_counts);
-    vbox.append(&summary_bar);
+    let summary_counts = compute_summary_counts(&latest_values, reference_db, &evaluation);
+    let key = SectionKey::Summary(summary_counts, evaluation.mode);
+    new.push(reuse_or_build(&mut old, key, || {
+        summary_bar::build_summary_bar(summary_counts, evaluation.mode)
+    }));
 
     // Alert banner for critical values
-    let critical: Vec<_> = latest_values.iter().filter(|bv| {
+    let critical: Vec<_> = latest_values.iter().filter(|(date, bv)| {
         if let Some(ref_val) = find_reference(reference_db, &bv.name) {
-            let status = get_value_status(bv.value, ref_val, gender);
+            let status = evaluation.status(bv.value, ref_val, date);
             matches!(status, ValueStatus::CriticalHigh | ValueStatus::CriticalLow)
         } else {
             false
@@ -49,14 +156,14 @@ pub fn build_dashboard_page(
     }).collect();
 
     if !critical.is_empty() {
-        let names: Vec<_> = critical.iter().map(|v| v.name.as_str()).collect();
-        let banner = adw::Banner::new(&format!(
-            "Kritische Werte: {}",
-            names.join(", ")
-        ));
-        banner.set_revealed(true);
-        banner.add_css_class("error");
-        vbox.append(&banner);
+        let names: Vec<_> = critical.iter().map(|(_, v)| v.name.as_str()).collect();
+        let text = format!("Kritische Werte: {}", names.join(", "));
+        new.push(reuse_or_build(&mut old, SectionKey::Critical(text.clone()), || {
+            let banner = adw::Banner::new(&text);
+            banner.set_revealed(true);
+            banner.add_css_class("error");
+            banner
+        }));
     }
 
     // Group by category
@@ -64,7 +171,7 @@ pub fn build_dashboard_page(
     let mut by_category: std::collections::HashMap<String, Vec<&BloodValue>> =
         std::collections::HashMap::new();
 
-    for bv in &latest_values {
+    for (_, bv) in &latest_values {
         by_category.entry(bv.category.clone()).or_default().push(bv);
         if !categories.contains(&bv.category) {
             categories.push(bv.category.clone());
@@ -73,35 +180,128 @@ pub fn build_dashboard_page(
 
     for cat in &categories {
         if let Some(values) = by_category.get(cat) {
-            let group = category_group::build_category_group(
-                cat,
-                values,
-                reference_db,
-                gender,
-                nav_view,
-                user_data,
-            );
-            vbox.append(&group);
+            let cards = values.iter().map(|&bv| {
+                let history = category_group::collect_history_for(user_data, &bv.name);
+                let warnings = history
+                    .last()
+                    .map_or(&[][..], |latest| data.warnings(&latest.entry_id, &bv.name))
+                    .to_vec();
+                CardKey {
+                    value: bv.clone(),
+                    history,
+                    warnings,
+                    reference: find_reference(reference_db, &bv.name).cloned(),
+                }
+            }).collect();
+            let key = SectionKey::Category { name: cat.clone(), cards, evaluation: evaluation.clone() };
+            new.push(reuse_or_build(&mut old, key, || {
+                category_group::build_category_group(
+                    cat,
+                    values,
+                    &data,
+                    &evaluation,
+                    nav_view,
+                    store,
+                    ctx,
+                )
+            }));
+        }
+    }
+
+    if let Some(ctx) = editor {
+        if has_entries {
+            // Without derived values, these are the entries as stored
+            let stored = store.user_data();
+            let key = SectionKey::Entries(EntriesKey(stored.clone()));
+            new.push(reuse_or_build(&mut old, key, || build_entries_group(&stored, ctx)));
         }
     }
 
     if latest_values.is_empty() {
-        let empty_label = gtk4::Label::new(Some("Keine Blutwerte vorhanden.\nGib Werte im Web-UI ein."));
-        empty_label.add_css_class("dim-label");
-        empty_label.set_justify(gtk4::Justification::Center);
-        empty_label.set_vexpand(true);
-        empty_label.set_valign(gtk4::Align::Center);
-        vbox.append(&empty_label);
+        new.push(reuse_or_build(&mut old, SectionKey::Empty { editable: editor.is_some() }, || {
+            let hint = if editor.is_some() {
+                "Keine Blutwerte vorhanden.\nLege oben einen neuen Eintrag an."
+            } else {
+                "Keine Blutwerte vorhanden.\nGib Werte im Web-UI ein."
+            };
+            let empty_label = gtk4::Label::new(Some(hint));
+            empty_label.add_css_class("dim-label");
+            empty_label.set_justify(gtk4::Justification::Center);
+            empty_label.set_vexpand(true);
+            empty_label.set_valign(gtk4::Align::Center);
+            empty_label
+        }));
     }
 
-    scrolled.set_child(Some(&vbox));
-    page.set_child(Some(&scrolled));
-    page.set_tag(Some("dashboard"));
-    page
+    // Replace what changed, keep the rest in place
+    for (_, widget) in old {
+        vbox.remove(&widget);
+    }
+    let mut previous: Option<gtk4::Widget> = None;
+    for (_, widget) in &new {
+        if widget.parent().is_some() {
+            vbox.reorder_child_after(widget, previous.as_ref());
+        } else {
+            vbox.insert_child_after(widget, previous.as_ref());
+        }
+        previous = Some(widget.clone());
+    }
+    sections.replace(new);
+}
+
+fn build_actions(
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    has_entries: bool,
+    editor: Option<&EntryEditorContext>,
+) -> gtk4::Box {
+    let actions_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    actions_box.set_halign(gtk4::Align::End);
+
+    if has_entries {
+        let compare_btn = gtk4::Button::with_label("Vergleichen");
+        compare_btn.add_css_class("pill");
+        compare_btn.set_tooltip_text(Some("Mehrere Werte in einem Diagramm vergleichen"));
+        compare_btn.connect_clicked(clone!(#[weak] nav_view, #[strong] store, move |_| {
+            nav_view.push(&build_comparison_page(&store, &[]));
+        }));
+        actions_box.append(&compare_btn);
+
+        let report_btn = gtk4::Button::with_label("Bericht erstellen");
+        report_btn.add_css_class("pill");
+        report_btn.set_tooltip_text(Some("Alle Werte als PDF speichern oder drucken, z. B. für einen Arzttermin"));
+        report_btn.connect_clicked(clone!(#[strong] store, move |btn| {
+            show_report_dialog(btn, &store);
+        }));
+        actions_box.append(&report_btn);
+    }
+
+    if let Some(ctx) = editor {
+        let scan_btn = gtk4::Button::with_label("Befund scannen");
+        scan_btn.add_css_class("pill");
+        scan_btn.set_tooltip_text(Some("Foto oder PDF eines Laborbefunds importieren"));
+        let scan_ctx = ctx.clone();
+        scan_btn.connect_clicked(move |btn| {
+            choose_and_scan(btn, &scan_ctx);
+        });
+
+        let new_btn = gtk4::Button::with_label("Neuer Eintrag");
+        new_btn.add_css_class("suggested-action");
+        new_btn.add_css_class("pill");
+        let ctx = ctx.clone();
+        new_btn.connect_clicked(move |btn| {
+            show_entry_editor(btn, &ctx, None);
+        });
+
+        actions_box.append(&scan_btn);
+        actions_box.append(&new_btn);
+    }
+    actions_box
 }
 
-pub fn collect_latest_values(user_data: &UserData) -> Vec<BloodValue> {
-    let mut map: std::collections::HashMap<String, BloodValue> = std::collections::HashMap::new();
+/// Latest value per name, with the date of its entry.
+pub fn collect_latest_values(user_data: &UserData) -> Vec<(String, BloodValue)> {
+    let mut map: std::collections::HashMap<String, (String, BloodValue)> = std::collections::HashMap::new();
     let mut order: Vec<String> = Vec::new();
 
     // entries are assumed sorted oldest→newest; last one wins per name
@@ -110,7 +310,7 @@ pub fn collect_latest_values(user_data: &UserData) -> Vec<BloodValue> {
             if !order.contains(&bv.name) {
                 order.push(bv.name.clone());
             }
-            map.insert(bv.name.clone(), bv.clone());
+            map.insert(bv.name.clone(), (entry.date.clone(), bv.clone()));
         }
     }
 
@@ -124,27 +324,97 @@ pub fn find_reference<'a>(db: &'a [ReferenceValue], name: &str) -> Option<&'a Re
     })
 }
 
-#[derive(Debug, Default)]
+/// Reference values whose name, short name or alias contains `query`,
+/// prefix matches first.
+pub fn search_references<'a>(
+    db: &'a [ReferenceValue],
+    query: &str,
+    limit: usize,
+) -> Vec<&'a ReferenceValue> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let names = |r: &'a ReferenceValue| {
+        std::iter::once(r.name.as_str())
+            .chain(r.short_name.as_deref())
+            .chain(r.aliases.iter().map(|a| a.as_str()))
+            .map(|n| n.to_lowercase())
+    };
+
+    let mut prefix = Vec::new();
+    let mut contains = Vec::new();
+    for r in db {
+        if names(r).any(|n| n.starts_with(&query)) {
+            prefix.push(r);
+        } else if names(r).any(|n| n.contains(&query)) {
+            contains.push(r);
+        }
+    }
+
+    prefix.into_iter().chain(contains).take(limit).collect()
+}
+
+fn build_entries_group(user_data: &UserData, ctx: &EntryEditorContext) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::new();
+    group.set_title("Laboreinträge");
+
+    let mut entries: Vec<&BloodEntry> = user_data.entries.iter().collect();
+    entries.sort_by(|a, b| b.date.cmp(&a.date));
+
+    for entry in entries {
+        let row = adw::ActionRow::new();
+        row.set_title(&format_date(&entry.date));
+        let count = match entry.values.len() {
+            1 => "1 Wert".to_string(),
+            n => format!("{n} Werte"),
+        };
+        let subtitle = match entry.lab_name.as_deref().filter(|l| !l.is_empty()) {
+            Some(lab) => format!("{lab} · {count}"),
+            None => count,
+        };
+        row.set_subtitle(&glib::markup_escape_text(&subtitle));
+        row.set_activatable(true);
+        row.add_suffix(&gtk4::Image::from_icon_name("document-edit-symbolic"));
+
+        let ctx = ctx.clone();
+        let entry_id = entry.id.clone();
+        row.connect_activated(move |row| {
+            if let Some(entry) = ctx.find_entry(&entry_id) {
+                show_entry_editor(row, &ctx, Some(&entry));
+            }
+        });
+        group.add(&row);
+    }
+
+    group
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct StatusCounts {
+    /// Normal or optimal
     pub normal: usize,
     pub warning: usize,
+    pub suboptimal: usize,
     pub abnormal: usize,
     pub critical: usize,
     pub total: usize,
 }
 
 fn compute_summary_counts(
-    values: &[BloodValue],
+    values: &[(String, BloodValue)],
     reference_db: &[ReferenceValue],
-    gender: Option<&str>,
+    evaluation: &Evaluation,
 ) -> StatusCounts {
     let mut counts = StatusCounts::default();
-    for bv in values {
+    for (date, bv) in values {
         counts.total += 1;
         if let Some(ref_val) = find_reference(reference_db, &bv.name) {
-            match get_value_status(bv.value, ref_val, gender) {
-                ValueStatus::Normal => counts.normal += 1,
+            match evaluation.status(bv.value, ref_val, date) {
+                ValueStatus::Normal | ValueStatus::Optimal => counts.normal += 1,
                 ValueStatus::Warning => counts.warning += 1,
+                ValueStatus::Suboptimal => counts.suboptimal += 1,
                 ValueStatus::CriticalHigh | ValueStatus::CriticalLow => counts.critical += 1,
                 ValueStatus::High | ValueStatus::Low => counts.abnormal += 1,
                 ValueStatus::Unknown => counts.normal += 1,
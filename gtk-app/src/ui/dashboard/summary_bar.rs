@@ -1,20 +1,28 @@
 use gtk4::prelude::*;
 
+use crate::api::types::EvaluationMode;
 use super::StatusCounts;
 
-pub fn build_summary_bar(counts: StatusCounts) -> gtk4::Box {
+pub fn build_summary_bar(counts: StatusCounts, mode: EvaluationMode) -> gtk4::Box {
     let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
     hbox.set_homogeneous(true);
 
+    // With optimal ranges, values that have none are still graded
+    // "Grenzwertig"; they count as suboptimal here
+    let (normal_label, warning_label) = match mode {
+        EvaluationMode::Reference => ("Normal", "Grenzwertig"),
+        EvaluationMode::Optimal => ("Optimal", "Suboptimal"),
+    };
+
     hbox.append(&make_card(
-        "Normal",
+        normal_label,
         counts.normal,
         "success",
         "emblem-ok-symbolic",
     ));
     hbox.append(&make_card(
-        "Grenzwertig",
-        counts.warning,
+        warning_label,
+        counts.warning + counts.suboptimal,
         "warning",
         "dialog-warning-symbolic",
     ));
@@ -3,15 +3,19 @@ use libadwaita::prelude::*;
 use libadwaita as adw;
 
 use crate::api::types::*;
+use crate::validation::{warnings_text, ValueWarning};
 
 pub fn build_value_card(
     bv: &BloodValue,
     ref_val: Option<&ReferenceValue>,
-    gender: Option<&str>,
+    evaluation: &Evaluation,
     history: &[ValueHistoryPoint],
+    warnings: &[ValueWarning],
 ) -> adw::ActionRow {
+    // Judged by the age on the date of the latest measurement
+    let date = history.last().map_or("", |p| p.date.as_str());
     let status = ref_val
-        .map(|r| get_value_status(bv.value, r, gender))
+        .map(|r| evaluation.status(bv.value, r, date))
         .unwrap_or(ValueStatus::Unknown);
 
     let trend = get_trend(history);
@@ -32,6 +36,18 @@ pub fn build_value_card(
     });
     row.add_prefix(&dot);
 
+    if bv.derived {
+        row.add_suffix(&derived_badge());
+    }
+
+    // Unit or plausibility problems of the latest measurement
+    if !warnings.is_empty() {
+        let icon = gtk4::Image::from_icon_name("dialog-warning-symbolic");
+        icon.add_css_class("warning");
+        icon.set_tooltip_text(Some(&warnings_text(warnings)));
+        row.add_suffix(&icon);
+    }
+
     // Value + unit label
     let value_str = format_value(bv.value);
     let suffix_label = gtk4::Label::new(Some(&format!("{} {}", value_str, bv.unit)));
@@ -59,6 +75,16 @@ pub fn build_value_card(
     row
 }
 
+/// Marks a value the app calculated from other values.
+pub fn derived_badge() -> gtk4::Label {
+    let badge = gtk4::Label::new(Some("berechnet"));
+    badge.add_css_class("caption");
+    badge.add_css_class("dim-label");
+    badge.set_valign(gtk4::Align::Center);
+    badge.set_tooltip_text(Some("Aus anderen Werten dieses Eintrags berechnet"));
+    badge
+}
+
 fn format_value(v: f64) -> String {
     if v.fract().abs() < f64::EPSILON {
         format!("{}", v as i64)
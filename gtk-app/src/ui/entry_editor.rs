@@ -0,0 +1,725 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use chrono::Datelike;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::api::{ApiClient, types::*};
+use crate::state::{spawn_task, AppStore};
+use crate::ui::dashboard::{find_reference, search_references};
+use crate::validation::{self, warnings_text, ValueWarning};
+
+const MAX_SUGGESTIONS: usize = 8;
+const DEFAULT_CATEGORY: &str = "Sonstige";
+const OFFLINE_MESSAGE: &str = "Offline – Änderungen sind erst wieder mit Serververbindung möglich.";
+
+/// Everything a page needs to open the entry editor and trigger a reload afterwards.
+#[derive(Clone)]
+pub struct EntryEditorContext {
+    pub store: AppStore,
+    pub on_saved: Rc<dyn Fn()>,
+}
+
+impl EntryEditorContext {
+    pub fn find_entry(&self, id: &str) -> Option<BloodEntry> {
+        self.store.user_data().entries.iter().find(|e| e.id == id).cloned()
+    }
+
+    pub fn reference_db(&self) -> Rc<Vec<ReferenceValue>> {
+        self.store.reference_db()
+    }
+
+    /// `None` while offline: the snapshot is read-only.
+    pub fn client(&self) -> Option<ApiClient> {
+        self.store.client()
+    }
+}
+
+/// Raw text of one value row, before validation.
+#[derive(Debug, Clone, Default)]
+pub struct RawValue {
+    pub name: String,
+    pub value: String,
+    pub unit: String,
+    pub category: String,
+    /// Name the row was loaded with; its short and long name are kept as
+    /// long as the name stays the same
+    pub loaded_name: String,
+    pub short_name: Option<String>,
+    pub long_name: Option<String>,
+}
+
+struct ValueRow {
+    container: gtk4::Box,
+    name: gtk4::Entry,
+    value: gtk4::Entry,
+    unit: gtk4::Entry,
+    category: gtk4::Entry,
+    loaded_name: String,
+    short_name: Option<String>,
+    long_name: Option<String>,
+}
+
+impl ValueRow {
+    fn raw(&self) -> RawValue {
+        RawValue {
+            name: self.name.text().to_string(),
+            value: self.value.text().to_string(),
+            unit: self.unit.text().to_string(),
+            category: self.category.text().to_string(),
+            loaded_name: self.loaded_name.clone(),
+            short_name: self.short_name.clone(),
+            long_name: self.long_name.clone(),
+        }
+    }
+}
+
+type ValueRows = Rc<RefCell<Vec<ValueRow>>>;
+
+pub fn show_entry_editor(
+    parent: &impl IsA<gtk4::Widget>,
+    ctx: &EntryEditorContext,
+    entry: Option<&BloodEntry>,
+) {
+    match entry {
+        Some(e) => present_editor(
+            parent,
+            ctx,
+            "Eintrag bearbeiten",
+            Some(e.id.clone()),
+            &BloodEntryInput {
+                date: e.date.clone(),
+                lab_name: e.lab_name.clone(),
+                notes: e.notes.clone(),
+                values: e.values.clone(),
+            },
+        ),
+        None => present_editor(
+            parent,
+            ctx,
+            "Neuer Eintrag",
+            None,
+            &BloodEntryInput {
+                date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+                lab_name: None,
+                notes: None,
+                values: Vec::new(),
+            },
+        ),
+    }
+}
+
+/// Opens the editor prefilled with a not yet saved entry, e.g. from a scanned report.
+pub fn show_entry_draft(
+    parent: &impl IsA<gtk4::Widget>,
+    ctx: &EntryEditorContext,
+    title: &str,
+    draft: &BloodEntryInput,
+) {
+    present_editor(parent, ctx, title, None, draft);
+}
+
+fn present_editor(
+    parent: &impl IsA<gtk4::Widget>,
+    ctx: &EntryEditorContext,
+    title: &str,
+    editing_id: Option<String>,
+    entry: &BloodEntryInput,
+) {
+    let dialog = adw::Dialog::new();
+    dialog.set_title(title);
+    dialog.set_content_width(680);
+    dialog.set_content_height(640);
+
+    let toolbar_view = adw::ToolbarView::new();
+    let header = adw::HeaderBar::new();
+    header.set_show_start_title_buttons(false);
+    header.set_show_end_title_buttons(false);
+
+    let cancel_btn = gtk4::Button::with_label("Abbrechen");
+    let save_btn = gtk4::Button::with_label("Speichern");
+    save_btn.add_css_class("suggested-action");
+    header.pack_start(&cancel_btn);
+    header.pack_end(&save_btn);
+    toolbar_view.add_top_bar(&header);
+
+    let scrolled = gtk4::ScrolledWindow::new();
+    scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+    scrolled.set_vexpand(true);
+
+    let content_box = gtk4::Box::new(gtk4::Orientation::Vertical, 24);
+    content_box.set_margin_top(24);
+    content_box.set_margin_bottom(24);
+    content_box.set_margin_start(24);
+    content_box.set_margin_end(24);
+
+    // ── General ──────────────────────────────────────────────────────────────
+    let general_group = adw::PreferencesGroup::new();
+    general_group.set_title("Allgemein");
+
+    let date_row = adw::EntryRow::new();
+    date_row.set_title("Datum (JJJJ-MM-TT)");
+    date_row.set_text(&entry.date);
+    date_row.set_input_hints(gtk4::InputHints::NO_SPELLCHECK);
+
+    let calendar = gtk4::Calendar::new();
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(&date_row.text(), "%Y-%m-%d") {
+        if let Ok(dt) = glib::DateTime::from_local(d.year(), d.month() as i32, d.day() as i32, 0, 0, 0.0) {
+            calendar.select_day(&dt);
+        }
+    }
+    let calendar_popover = gtk4::Popover::new();
+    calendar_popover.set_child(Some(&calendar));
+
+    let calendar_btn = gtk4::MenuButton::new();
+    calendar_btn.set_icon_name("x-office-calendar-symbolic");
+    calendar_btn.set_tooltip_text(Some("Datum wählen"));
+    calendar_btn.set_valign(gtk4::Align::Center);
+    calendar_btn.add_css_class("flat");
+    calendar_btn.set_popover(Some(&calendar_popover));
+    date_row.add_suffix(&calendar_btn);
+
+    calendar.connect_day_selected(clone!(#[weak] date_row, #[weak] calendar_popover, move |cal| {
+        if let Ok(s) = cal.date().format("%Y-%m-%d") {
+            date_row.set_text(&s);
+        }
+        calendar_popover.popdown();
+    }));
+    general_group.add(&date_row);
+
+    let lab_row = adw::EntryRow::new();
+    lab_row.set_title("Labor");
+    lab_row.set_text(entry.lab_name.as_deref().unwrap_or(""));
+    general_group.add(&lab_row);
+
+    let notes_row = adw::EntryRow::new();
+    notes_row.set_title("Notizen");
+    notes_row.set_text(entry.notes.as_deref().unwrap_or(""));
+    general_group.add(&notes_row);
+
+    content_box.append(&general_group);
+
+    // ── Values ───────────────────────────────────────────────────────────────
+    let values_group = adw::PreferencesGroup::new();
+    values_group.set_title("Werte");
+    values_group.set_description(Some("Name, Wert, Einheit und Kategorie"));
+
+    let add_btn = gtk4::Button::from_icon_name("list-add-symbolic");
+    add_btn.set_tooltip_text(Some("Wert hinzufügen"));
+    add_btn.add_css_class("flat");
+    values_group.set_header_suffix(Some(&add_btn));
+
+    let values_box = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    values_group.add(&values_box);
+
+    let rows: ValueRows = Rc::new(RefCell::new(Vec::new()));
+    for bv in &entry.values {
+        add_value_row(&values_box, &rows, &ctx.reference_db(), Some(bv));
+    }
+    if entry.values.is_empty() {
+        add_value_row(&values_box, &rows, &ctx.reference_db(), None);
+    }
+
+    let reference_db = ctx.reference_db();
+    add_btn.connect_clicked(clone!(#[weak] values_box, #[strong] rows, move |_| {
+        add_value_row(&values_box, &rows, &reference_db, None);
+        if let Some(row) = rows.borrow().last() {
+            row.name.grab_focus();
+        }
+    }));
+
+    content_box.append(&values_group);
+
+    let status_label = gtk4::Label::new(None);
+    status_label.set_wrap(true);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.add_css_class("error");
+    status_label.set_visible(false);
+    content_box.append(&status_label);
+
+    // ── Delete (existing entries only) ───────────────────────────────────────
+    if let Some(id) = editing_id.clone() {
+        let delete_btn = gtk4::Button::with_label("Eintrag löschen");
+        delete_btn.add_css_class("destructive-action");
+        delete_btn.set_halign(gtk4::Align::Start);
+        content_box.append(&delete_btn);
+
+        let ctx = ctx.clone();
+        delete_btn.connect_clicked(clone!(#[weak] dialog, #[weak] status_label, move |btn| {
+            confirm_delete(&dialog, btn, &status_label, &ctx, id.clone());
+        }));
+    }
+
+    scrolled.set_child(Some(&content_box));
+    toolbar_view.set_content(Some(&scrolled));
+    dialog.set_child(Some(&toolbar_view));
+
+    cancel_btn.connect_clicked(clone!(#[weak] dialog, move |_| {
+        dialog.close();
+    }));
+
+    // Save
+    {
+        let ctx = ctx.clone();
+        save_btn.connect_clicked(clone!(#[weak] dialog, #[weak] date_row, #[weak] lab_row, #[weak] notes_row, #[weak] status_label, #[strong] rows, move |btn| {
+            let raw: Vec<RawValue> = rows.borrow().iter().map(ValueRow::raw).collect();
+            let input = match build_entry_input(
+                &date_row.text(),
+                &lab_row.text(),
+                &notes_row.text(),
+                &raw,
+            ) {
+                Ok(input) => input,
+                Err(msg) => {
+                    status_label.set_text(&msg);
+                    status_label.set_visible(true);
+                    return;
+                }
+            };
+
+            let Some(client) = ctx.client() else {
+                status_label.set_text(OFFLINE_MESSAGE);
+                status_label.set_visible(true);
+                return;
+            };
+
+            btn.set_sensitive(false);
+            status_label.set_visible(false);
+
+            let id = editing_id.clone();
+            let (tx, rx) = async_channel::bounded::<Result<(), String>>(1);
+            spawn_task(async move {
+                let r = match id {
+                    Some(id) => client.update_entry(&id, &input).await.map(|_| ()),
+                    None => client.create_entry(&input).await.map(|_| ()),
+                };
+                tx.send(r.map_err(|e| e.to_string())).await.ok();
+            });
+
+            let on_saved = ctx.on_saved.clone();
+            let btn = btn.clone();
+            glib::MainContext::default().spawn_local(async move {
+                if let Ok(result) = rx.recv().await {
+                    match result {
+                        Ok(()) => {
+                            on_saved();
+                            dialog.close();
+                        }
+                        Err(e) => {
+                            status_label.set_text(&format!("Fehler beim Speichern: {e}"));
+                            status_label.set_visible(true);
+                            btn.set_sensitive(true);
+                        }
+                    }
+                }
+            });
+        }));
+    }
+
+    dialog.present(Some(parent));
+}
+
+fn confirm_delete(
+    dialog: &adw::Dialog,
+    delete_btn: &gtk4::Button,
+    status_label: &gtk4::Label,
+    ctx: &EntryEditorContext,
+    id: String,
+) {
+    let alert = adw::AlertDialog::new(
+        Some("Eintrag löschen?"),
+        Some("Der Eintrag und alle zugehörigen Werte werden endgültig entfernt."),
+    );
+    alert.add_response("cancel", "Abbrechen");
+    alert.add_response("delete", "Löschen");
+    alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+    alert.set_default_response(Some("cancel"));
+    alert.set_close_response("cancel");
+
+    let ctx = ctx.clone();
+    let on_saved = ctx.on_saved.clone();
+    alert.connect_response(None, clone!(#[weak] dialog, #[weak] delete_btn, #[weak] status_label, move |_, response| {
+        if response != "delete" {
+            return;
+        }
+        let Some(client) = ctx.client() else {
+            status_label.set_text(OFFLINE_MESSAGE);
+            status_label.set_visible(true);
+            return;
+        };
+        delete_btn.set_sensitive(false);
+
+        let id = id.clone();
+        let (tx, rx) = async_channel::bounded::<Result<(), String>>(1);
+        spawn_task(async move {
+            let r = client.delete_entry(&id).await.map_err(|e| e.to_string());
+            tx.send(r).await.ok();
+        });
+
+        let on_saved = on_saved.clone();
+        glib::MainContext::default().spawn_local(async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(()) => {
+                        on_saved();
+                        dialog.close();
+                    }
+                    Err(e) => {
+                        status_label.set_text(&format!("Fehler beim Löschen: {e}"));
+                        status_label.set_visible(true);
+                        delete_btn.set_sensitive(true);
+                    }
+                }
+            }
+        });
+    }));
+
+    alert.present(Some(dialog));
+}
+
+fn add_value_row(
+    values_box: &gtk4::Box,
+    rows: &ValueRows,
+    reference_db: &Rc<Vec<ReferenceValue>>,
+    initial: Option<&BloodValue>,
+) {
+    let container = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+
+    let name = gtk4::Entry::new();
+    name.set_placeholder_text(Some("Name"));
+    name.set_hexpand(true);
+
+    let value = gtk4::Entry::new();
+    value.set_placeholder_text(Some("Wert"));
+    value.set_width_chars(8);
+    value.set_input_purpose(gtk4::InputPurpose::Number);
+
+    let unit = gtk4::Entry::new();
+    unit.set_placeholder_text(Some("Einheit"));
+    unit.set_width_chars(8);
+
+    let category = gtk4::Entry::new();
+    category.set_placeholder_text(Some("Kategorie"));
+    category.set_width_chars(12);
+
+    if let Some(bv) = initial {
+        name.set_text(&bv.name);
+        value.set_text(&format_number(bv.value));
+        unit.set_text(&bv.unit);
+        category.set_text(&bv.category);
+    }
+
+    attach_autocomplete(&name, &unit, &category, reference_db);
+
+    // Shows whether the name resolves to a known reference value and
+    // whether value and unit fit it
+    let match_icon = gtk4::Image::new();
+    match_icon.set_valign(gtk4::Align::Center);
+    let update_match = Rc::new(clone!(#[weak] match_icon, #[weak] name, #[weak] value, #[weak] unit, #[strong] reference_db, move || {
+        let reference = find_reference(&reference_db, &name.text());
+        let warnings = reference
+            .zip(parse_number(&value.text()))
+            .map(|(r, number)| validation::check(number, &unit.text(), r))
+            .unwrap_or_default();
+        update_match_icon(&match_icon, reference, &warnings);
+    }));
+    update_match();
+    for entry in [&name, &value, &unit] {
+        entry.connect_changed(clone!(#[strong] update_match, move |_| update_match()));
+    }
+
+    let remove_btn = gtk4::Button::from_icon_name("list-remove-symbolic");
+    remove_btn.set_tooltip_text(Some("Wert entfernen"));
+    remove_btn.set_valign(gtk4::Align::Center);
+    remove_btn.add_css_class("flat");
+
+    container.append(&match_icon);
+    container.append(&name);
+    container.append(&value);
+    container.append(&unit);
+    container.append(&category);
+    container.append(&remove_btn);
+    values_box.append(&container);
+
+    let rows_weak = Rc::downgrade(rows);
+    remove_btn.connect_clicked(clone!(#[weak] values_box, #[weak] container, move |_| {
+        if let Some(rows) = rows_weak.upgrade() {
+            rows.borrow_mut().retain(|r| r.container != container);
+        }
+        values_box.remove(&container);
+    }));
+
+    rows.borrow_mut().push(ValueRow {
+        container,
+        name,
+        value,
+        unit,
+        category,
+        loaded_name: initial.map(|bv| bv.name.clone()).unwrap_or_default(),
+        short_name: initial.and_then(|bv| bv.short_name.clone()),
+        long_name: initial.and_then(|bv| bv.long_name.clone()),
+    });
+}
+
+/// Shows reference suggestions below the name entry and fills unit and
+/// category from the chosen reference value.
+fn attach_autocomplete(
+    name: &gtk4::Entry,
+    unit: &gtk4::Entry,
+    category: &gtk4::Entry,
+    reference_db: &Rc<Vec<ReferenceValue>>,
+) {
+    let suggestions = gtk4::ListBox::new();
+    suggestions.set_selection_mode(gtk4::SelectionMode::None);
+
+    let popover = gtk4::Popover::new();
+    popover.set_autohide(false);
+    popover.set_has_arrow(false);
+    popover.set_position(gtk4::PositionType::Bottom);
+    popover.set_child(Some(&suggestions));
+    popover.set_parent(name);
+
+    name.connect_destroy(clone!(#[weak] popover, move |_| {
+        popover.unparent();
+    }));
+
+    let current: Rc<RefCell<Vec<ReferenceValue>>> = Rc::new(RefCell::new(Vec::new()));
+
+    name.connect_changed(clone!(#[weak] popover, #[weak] suggestions, #[weak] unit, #[weak] category, #[strong] current, #[strong] reference_db, move |entry| {
+        let query = entry.text().to_string();
+
+        // Exact hit: fill in the blanks and stop suggesting
+        if let Some(r) = find_reference(&reference_db, &query) {
+            fill_from_reference(r, &unit, &category, false);
+            popover.popdown();
+            return;
+        }
+
+        while let Some(child) = suggestions.first_child() {
+            suggestions.remove(&child);
+        }
+
+        let matches: Vec<ReferenceValue> = search_references(&reference_db, &query, MAX_SUGGESTIONS)
+            .into_iter()
+            .cloned()
+            .collect();
+        if matches.is_empty() {
+            popover.popdown();
+            current.borrow_mut().clear();
+            return;
+        }
+
+        for r in &matches {
+            let row = adw::ActionRow::new();
+            row.set_title(&glib::markup_escape_text(&r.name));
+            row.set_subtitle(&glib::markup_escape_text(&format!("{} · {}", r.unit, r.category)));
+            row.set_activatable(true);
+            suggestions.append(&row);
+        }
+        *current.borrow_mut() = matches;
+        popover.popup();
+    }));
+
+    suggestions.connect_row_activated(clone!(#[weak] name, #[weak] unit, #[weak] category, #[weak] popover, #[strong] current, move |_, row| {
+        let chosen = current.borrow().get(row.index() as usize).cloned();
+        if let Some(r) = chosen {
+            fill_from_reference(&r, &unit, &category, true);
+            name.set_text(&r.name);
+            name.set_position(-1);
+        }
+        popover.popdown();
+    }));
+
+    // Enter picks the first suggestion while the list is open
+    name.connect_activate(clone!(#[weak] popover, #[weak] suggestions, move |_| {
+        if popover.is_visible() {
+            if let Some(row) = suggestions.row_at_index(0) {
+                let _ = row.activate();
+            }
+        }
+    }));
+
+    let focus_ctrl = gtk4::EventControllerFocus::new();
+    focus_ctrl.connect_leave(clone!(#[weak] popover, move |_| {
+        // Delay so a click on a suggestion is still delivered
+        glib::timeout_add_local_once(std::time::Duration::from_millis(200), clone!(#[weak] popover, move || {
+            popover.popdown();
+        }));
+    }));
+    name.add_controller(focus_ctrl);
+}
+
+fn update_match_icon(icon: &gtk4::Image, reference: Option<&ReferenceValue>, warnings: &[ValueWarning]) {
+    match reference {
+        Some(_) if !warnings.is_empty() => {
+            icon.set_icon_name(Some("dialog-warning-symbolic"));
+            icon.set_tooltip_text(Some(&warnings_text(warnings)));
+            icon.remove_css_class("success");
+            icon.add_css_class("warning");
+        }
+        Some(r) => {
+            icon.set_icon_name(Some("emblem-ok-symbolic"));
+            icon.set_tooltip_text(Some(&format!("Referenzwert: {} ({})", r.name, r.unit)));
+            icon.remove_css_class("warning");
+            icon.add_css_class("success");
+        }
+        None => {
+            icon.set_icon_name(Some("dialog-warning-symbolic"));
+            icon.set_tooltip_text(Some("Kein passender Referenzwert gefunden"));
+            icon.remove_css_class("success");
+            icon.add_css_class("warning");
+        }
+    }
+}
+
+fn fill_from_reference(r: &ReferenceValue, unit: &gtk4::Entry, category: &gtk4::Entry, overwrite: bool) {
+    if overwrite || unit.text().is_empty() {
+        unit.set_text(&r.unit);
+    }
+    if overwrite || category.text().is_empty() {
+        category.set_text(&r.category);
+    }
+}
+
+/// Validates the form contents and turns them into a request body.
+pub fn build_entry_input(
+    date: &str,
+    lab_name: &str,
+    notes: &str,
+    values: &[RawValue],
+) -> Result<BloodEntryInput, String> {
+    let date = date.trim();
+    if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        return Err("Bitte ein gültiges Datum im Format JJJJ-MM-TT eingeben.".to_string());
+    }
+
+    let mut parsed = Vec::new();
+    for (i, raw) in values.iter().enumerate() {
+        let name = raw.name.trim();
+        let value = raw.value.trim();
+        if name.is_empty() && value.is_empty() {
+            continue; // untouched row
+        }
+        if name.is_empty() {
+            return Err(format!("Wert {}: Name fehlt.", i + 1));
+        }
+        let number = parse_number(value)
+            .ok_or_else(|| format!("{name}: \"{value}\" ist keine Zahl."))?;
+        let unit = raw.unit.trim();
+        if unit.is_empty() {
+            return Err(format!("{name}: Einheit fehlt."));
+        }
+        let category = match raw.category.trim() {
+            "" => DEFAULT_CATEGORY,
+            c => c,
+        };
+        // The entry is replaced as a whole, so names set on import are
+        // sent back unless the value was renamed
+        let renamed = name != raw.loaded_name.trim();
+        parsed.push(BloodValue {
+            name: name.to_string(),
+            value: number,
+            unit: unit.to_string(),
+            category: category.to_string(),
+            short_name: raw.short_name.clone().filter(|_| !renamed),
+            long_name: raw.long_name.clone().filter(|_| !renamed),
+            derived: false,
+        });
+    }
+
+    if parsed.is_empty() {
+        return Err("Bitte mindestens einen Wert eingeben.".to_string());
+    }
+
+    let optional = |s: &str| {
+        let s = s.trim();
+        (!s.is_empty()).then(|| s.to_string())
+    };
+
+    Ok(BloodEntryInput {
+        date: date.to_string(),
+        lab_name: optional(lab_name),
+        notes: optional(notes),
+        values: parsed,
+    })
+}
+
+/// Parses a number, accepting a German decimal comma.
+pub fn parse_number(s: &str) -> Option<f64> {
+    s.trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+}
+
+pub fn format_number(v: f64) -> String {
+    if v.fract().abs() < f64::EPSILON {
+        format!("{}", v as i64)
+    } else {
+        v.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(name: &str, value: &str) -> RawValue {
+        RawValue {
+            name: name.to_string(),
+            value: value.to_string(),
+            unit: "mg/dl".to_string(),
+            category: "Fettstoffwechsel".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn imported(name: &str) -> RawValue {
+        RawValue {
+            loaded_name: name.to_string(),
+            short_name: Some("LDL".to_string()),
+            long_name: Some("LDL-Cholesterin".to_string()),
+            ..raw(name, "120")
+        }
+    }
+
+    #[test]
+    fn editing_keeps_short_and_long_name() {
+        let mut edited = imported("LDL-Cholesterin");
+        edited.value = "118,5".to_string();
+        let input = build_entry_input("2024-03-01", "", "", &[edited]).unwrap();
+
+        let bv = &input.values[0];
+        assert_eq!(bv.value, 118.5);
+        assert_eq!(bv.short_name.as_deref(), Some("LDL"));
+        assert_eq!(bv.long_name.as_deref(), Some("LDL-Cholesterin"));
+    }
+
+    #[test]
+    fn renaming_drops_short_and_long_name() {
+        let mut renamed = imported("LDL-Cholesterin");
+        renamed.name = "HDL-Cholesterin".to_string();
+        let input = build_entry_input("2024-03-01", "", "", &[renamed]).unwrap();
+
+        assert_eq!(input.values[0].short_name, None);
+        assert_eq!(input.values[0].long_name, None);
+    }
+
+    #[test]
+    fn new_values_have_no_short_or_long_name() {
+        let input = build_entry_input("2024-03-01", " Labor ", "", &[raw("Ferritin", "80")]).unwrap();
+        assert_eq!(input.lab_name.as_deref(), Some("Labor"));
+        assert_eq!(input.values[0].short_name, None);
+        assert_eq!(input.values[0].long_name, None);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(build_entry_input("01.03.2024", "", "", &[raw("Ferritin", "80")]).is_err());
+        assert!(build_entry_input("2024-03-01", "", "", &[raw("Ferritin", "viel")]).is_err());
+        assert!(build_entry_input("2024-03-01", "", "", &[raw("", "")]).is_err());
+    }
+}
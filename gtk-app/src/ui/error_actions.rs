@@ -0,0 +1,28 @@
+use libadwaita as adw;
+
+use crate::api::error::{api_error, ApiError};
+
+/// Window action that opens the settings, where the token can be re-entered.
+pub const SETTINGS_ACTION: &str = "win.settings";
+/// Window action that reloads all data.
+pub const REFRESH_ACTION: &str = "win.refresh";
+
+/// What the user can do about `error`, as (button label, action name).
+pub fn recovery_action(error: &ApiError) -> Option<(&'static str, &'static str)> {
+    match error {
+        ApiError::Unauthorized => Some(("Token ändern", SETTINGS_ACTION)),
+        e if e.is_transient() => Some(("Erneut versuchen", REFRESH_ACTION)),
+        _ => None,
+    }
+}
+
+/// Toast "`title`: error" with the matching recovery button, if any.
+pub fn error_toast(title: &str, error: &anyhow::Error) -> adw::Toast {
+    let toast = adw::Toast::new(&format!("{title}: {error}"));
+    toast.set_timeout(5);
+    if let Some((label, action)) = api_error(error).and_then(recovery_action) {
+        toast.set_button_label(Some(label));
+        toast.set_action_name(Some(action));
+    }
+    toast
+}
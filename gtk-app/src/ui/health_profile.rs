@@ -0,0 +1,335 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use chrono::NaiveDate;
+
+use crate::api::{types::*, ApiError};
+use crate::state::{spawn_task, AppStore};
+use crate::ui::error_actions::error_toast;
+
+/// (API value, label) pairs of the choice fields. `None` is "Keine Angabe".
+type Choices = &'static [(&'static str, &'static str)];
+
+const GENDERS: Choices = &[("male", "Männlich"), ("female", "Weiblich")];
+const SMOKING: Choices = &[
+    ("never", "Nie"),
+    ("former", "Ehemals"),
+    ("occasional", "Gelegentlich"),
+    ("regular", "Regelmäßig"),
+];
+const ALCOHOL: Choices = &[
+    ("never", "Nie"),
+    ("rarely", "Selten"),
+    ("moderate", "Mäßig"),
+    ("regular", "Regelmäßig"),
+];
+const EXERCISE: Choices = &[
+    ("none", "Keine"),
+    ("light", "Leicht"),
+    ("moderate", "Mäßig"),
+    ("active", "Aktiv"),
+    ("very_active", "Sehr aktiv"),
+];
+const DIET: Choices = &[
+    ("mixed", "Mischkost"),
+    ("vegetarian", "Vegetarisch"),
+    ("vegan", "Vegan"),
+    ("pescatarian", "Pescetarisch"),
+    ("keto", "Ketogen"),
+    ("other", "Andere"),
+];
+const STRESS: Choices = &[
+    ("low", "Niedrig"),
+    ("moderate", "Mittel"),
+    ("high", "Hoch"),
+    ("very_high", "Sehr hoch"),
+];
+
+/// Row index in a combo of `choices` (0 is "Keine Angabe").
+pub fn choice_index(choices: Choices, value: Option<&str>) -> u32 {
+    value
+        .and_then(|v| choices.iter().position(|(key, _)| *key == v))
+        .map_or(0, |i| i as u32 + 1)
+}
+
+/// API value for a combo row index, `None` for "Keine Angabe".
+pub fn choice_value(choices: Choices, index: u32) -> Option<String> {
+    let i = (index as usize).checked_sub(1)?;
+    choices.get(i).map(|(key, _)| key.to_string())
+}
+
+/// Date of birth as typed (TT.MM.JJJJ or YYYY-MM-DD) in API format,
+/// `None` for an empty field. Dates in the future are rejected.
+pub fn parse_birth_date(text: &str, today: NaiveDate) -> Result<Option<String>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(text, "%d.%m.%Y")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y-%m-%d"))
+        .map_err(|_| format!("Ungültiges Geburtsdatum „{text}“ (TT.MM.JJJJ)"))?;
+    if date > today {
+        return Err("Das Geburtsdatum liegt in der Zukunft".to_string());
+    }
+    Ok(Some(date.format("%Y-%m-%d").to_string()))
+}
+
+/// Date of birth from the API as shown in the form.
+fn format_birth_date(date: &str) -> String {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.format("%d.%m.%Y").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+/// Page for gender, date of birth, diagnoses, medications and lifestyle.
+/// Saving updates the store, so all status badges are re-evaluated for the
+/// new gender and age.
+pub fn build_health_profile_page(store: &AppStore, toast_overlay: &adw::ToastOverlay) -> adw::NavigationPage {
+    let page = adw::NavigationPage::new(&gtk4::Label::new(None), "Gesundheitsprofil");
+    let prefs = adw::PreferencesPage::new();
+
+    // Person
+    let person_group = adw::PreferencesGroup::new();
+    person_group.set_title("Person");
+    person_group.set_description(Some(
+        "Geschlecht und Alter bestimmen, welche Referenzbereiche für deine Werte gelten.",
+    ));
+    let gender_row = choice_row("Geschlecht", GENDERS);
+    person_group.add(&gender_row);
+    let birth_date_row = adw::EntryRow::new();
+    birth_date_row.set_title("Geburtsdatum (TT.MM.JJJJ)");
+    person_group.add(&birth_date_row);
+    prefs.add(&person_group);
+
+    // Diagnoses and medications
+    let diagnoses = StringListGroup::new("Diagnosen", "Diagnose hinzufügen");
+    let medications = StringListGroup::new("Medikamente", "Medikament hinzufügen");
+    prefs.add(&diagnoses.group);
+    prefs.add(&medications.group);
+
+    // Lifestyle
+    let lifestyle_group = adw::PreferencesGroup::new();
+    lifestyle_group.set_title("Lebensstil");
+    lifestyle_group.set_description(Some("Hilft dem KI-Doktor, deine Werte einzuordnen."));
+    let smoking_row = choice_row("Rauchen", SMOKING);
+    let alcohol_row = choice_row("Alkohol", ALCOHOL);
+    let exercise_row = choice_row("Bewegung", EXERCISE);
+    let diet_row = choice_row("Ernährung", DIET);
+    let stress_row = choice_row("Stress", STRESS);
+
+    let sleep_row = adw::ExpanderRow::new();
+    sleep_row.set_title("Schlaf");
+    sleep_row.set_show_enable_switch(true);
+    let sleep_hours_row = adw::SpinRow::with_range(4.0, 12.0, 0.5);
+    sleep_hours_row.set_title("Stunden pro Nacht");
+    sleep_hours_row.set_digits(1);
+    sleep_row.add_row(&sleep_hours_row);
+
+    for row in [&smoking_row, &alcohol_row, &exercise_row, &diet_row, &stress_row] {
+        lifestyle_group.add(row);
+    }
+    lifestyle_group.add(&sleep_row);
+    prefs.add(&lifestyle_group);
+
+    // Save
+    let save_group = adw::PreferencesGroup::new();
+    let save_btn = adw::ButtonRow::new();
+    save_btn.set_title("Speichern");
+    save_btn.add_css_class("suggested-action");
+    save_group.add(&save_btn);
+    prefs.add(&save_group);
+
+    page.set_child(Some(&prefs));
+
+    // Shows the stored profile in the form
+    let load_fields = {
+        let gender_row = gender_row.clone();
+        let birth_date_row = birth_date_row.clone();
+        let diagnoses = diagnoses.clone();
+        let medications = medications.clone();
+        let smoking_row = smoking_row.clone();
+        let alcohol_row = alcohol_row.clone();
+        let exercise_row = exercise_row.clone();
+        let diet_row = diet_row.clone();
+        let stress_row = stress_row.clone();
+        let sleep_row = sleep_row.clone();
+        let sleep_hours_row = sleep_hours_row.clone();
+        let prefs = prefs.clone();
+        Rc::new(move |store: &AppStore| {
+            let profile = store.user_data().health_profile();
+            let lifestyle = profile.lifestyle.unwrap_or_default();
+            gender_row.set_selected(choice_index(GENDERS, profile.gender.as_deref()));
+            birth_date_row.set_text(&profile.birth_date.as_deref().map(format_birth_date).unwrap_or_default());
+            diagnoses.set_items(profile.diagnoses);
+            medications.set_items(profile.medications);
+            smoking_row.set_selected(choice_index(SMOKING, lifestyle.smoking.as_deref()));
+            alcohol_row.set_selected(choice_index(ALCOHOL, lifestyle.alcohol.as_deref()));
+            exercise_row.set_selected(choice_index(EXERCISE, lifestyle.exercise.as_deref()));
+            diet_row.set_selected(choice_index(DIET, lifestyle.diet.as_deref()));
+            stress_row.set_selected(choice_index(STRESS, lifestyle.stress_level.as_deref()));
+            sleep_row.set_enable_expansion(lifestyle.sleep_hours.is_some());
+            sleep_hours_row.set_value(lifestyle.sleep_hours.unwrap_or(7.0));
+            // Editing needs the server
+            prefs.set_sensitive(!store.is_read_only());
+        })
+    };
+    load_fields(store);
+
+    // Another session (reconnect, other profile) replaces the form content
+    let seen_generation = Rc::new(Cell::new(store.generation()));
+    store.connect_changed_for(&page, clone!(#[strong] load_fields, move |store| {
+        if store.generation() != seen_generation.get() {
+            seen_generation.set(store.generation());
+            load_fields(store);
+        }
+    }));
+
+    let store = store.clone();
+    let toast_overlay = toast_overlay.clone();
+    save_btn.connect_activated(move |btn| {
+        let Some(client) = store.client() else { return };
+
+        let today = chrono::Local::now().date_naive();
+        let birth_date = match parse_birth_date(&birth_date_row.text(), today) {
+            Ok(date) => date,
+            Err(msg) => {
+                toast_overlay.add_toast(adw::Toast::new(&msg));
+                return;
+            }
+        };
+
+        let sleep_hours = sleep_row.enables_expansion().then(|| sleep_hours_row.value());
+        let lifestyle = Lifestyle {
+            smoking: choice_value(SMOKING, smoking_row.selected()),
+            alcohol: choice_value(ALCOHOL, alcohol_row.selected()),
+            exercise: choice_value(EXERCISE, exercise_row.selected()),
+            diet: choice_value(DIET, diet_row.selected()),
+            sleep_hours,
+            stress_level: choice_value(STRESS, stress_row.selected()),
+        };
+        let profile = HealthProfile {
+            gender: choice_value(GENDERS, gender_row.selected()),
+            birth_date,
+            diagnoses: diagnoses.items(),
+            medications: medications.items(),
+            lifestyle: Some(lifestyle),
+        };
+        btn.set_sensitive(false);
+
+        let (tx, rx) = async_channel::bounded::<Result<HealthProfile, ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.update_profile(&profile).await).await.ok();
+        });
+
+        let store = store.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::MainContext::default().spawn_local(clone!(#[weak] btn, async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(saved) => {
+                        store.set_health_profile(saved);
+                        let toast = adw::Toast::new("Gesundheitsprofil gespeichert");
+                        toast.set_timeout(3);
+                        toast_overlay.add_toast(toast);
+                    }
+                    Err(e) => {
+                        toast_overlay.add_toast(error_toast("Speichern fehlgeschlagen", &anyhow::Error::new(e)));
+                    }
+                }
+                btn.set_sensitive(true);
+            }
+        }));
+    });
+
+    page
+}
+
+fn choice_row(title: &str, choices: Choices) -> adw::ComboRow {
+    let mut labels = vec!["Keine Angabe"];
+    labels.extend(choices.iter().map(|(_, label)| *label));
+    let row = adw::ComboRow::new();
+    row.set_title(title);
+    row.set_model(Some(&gtk4::StringList::new(&labels)));
+    row
+}
+
+/// Group with one removable row per string and an entry row to add more.
+struct StringListGroup {
+    group: adw::PreferencesGroup,
+    list: gtk4::ListBox,
+    items: RefCell<Vec<String>>,
+}
+
+impl StringListGroup {
+    fn new(title: &str, add_title: &str) -> Rc<Self> {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(title);
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+
+        let add_row = adw::EntryRow::new();
+        add_row.set_title(add_title);
+        add_row.set_show_apply_button(true);
+        list.append(&add_row);
+        group.add(&list);
+
+        let this = Rc::new(Self {
+            group,
+            list,
+            items: RefCell::new(Vec::new()),
+        });
+        // The rows hold the handlers, so these only keep a weak reference
+        let weak = Rc::downgrade(&this);
+        add_row.connect_apply(move |row| {
+            let Some(this) = weak.upgrade() else { return };
+            let text = row.text().trim().to_string();
+            if text.is_empty() || this.items.borrow().contains(&text) {
+                return;
+            }
+            this.items.borrow_mut().push(text);
+            row.set_text("");
+            this.rebuild();
+        });
+        this
+    }
+
+    fn items(&self) -> Vec<String> {
+        self.items.borrow().clone()
+    }
+
+    fn set_items(self: &Rc<Self>, items: Vec<String>) {
+        *self.items.borrow_mut() = items;
+        self.rebuild();
+    }
+
+    /// Replaces the item rows; the entry row stays last.
+    fn rebuild(self: &Rc<Self>) {
+        while let Some(row) = self.list.first_child().and_downcast::<adw::ActionRow>() {
+            self.list.remove(&row);
+        }
+        for (i, item) in self.items.borrow().iter().enumerate() {
+            let row = adw::ActionRow::new();
+            row.set_title(&glib::markup_escape_text(item));
+
+            let remove_btn = gtk4::Button::from_icon_name("list-remove-symbolic");
+            remove_btn.add_css_class("flat");
+            remove_btn.set_valign(gtk4::Align::Center);
+            remove_btn.set_tooltip_text(Some("Entfernen"));
+            let weak = Rc::downgrade(self);
+            remove_btn.connect_clicked(move |_| {
+                let Some(this) = weak.upgrade() else { return };
+                this.items.borrow_mut().remove(i);
+                this.rebuild();
+            });
+            row.add_suffix(&remove_btn);
+
+            self.list.insert(&row, i as i32);
+        }
+    }
+}
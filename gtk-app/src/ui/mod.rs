@@ -4,3 +4,14 @@ pub mod settings;
 pub mod dashboard;
 pub mod value_detail;
 pub mod ai_chat;
+pub mod entry_editor;
+pub mod scan_import;
+pub mod passphrase_dialog;
+pub mod error_actions;
+pub mod received_shares;
+pub mod shares;
+pub mod api_tokens;
+pub mod health_profile;
+pub mod reference_editor;
+pub mod comparison;
+pub mod report;
@@ -0,0 +1,123 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use std::rc::Rc;
+
+use crate::config::Config;
+use crate::secrets::{set_passphrase, token_file_path, TokenFileError};
+use crate::state::spawn_task;
+
+/// Runs `then` once the tokens of `config` can be read and written, asking
+/// for the passphrase of the encrypted token file first if there is no keyring.
+pub fn with_token_store(
+    parent: &impl IsA<gtk4::Widget>,
+    config: &Config,
+    then: impl Fn() + 'static,
+) {
+    // Looking for a keyring talks to D-Bus
+    let (tx, rx) = async_channel::bounded::<bool>(1);
+    let config = config.clone();
+    spawn_task(async move {
+        tx.send(config.needs_passphrase().await).await.ok();
+    });
+
+    let parent = parent.upcast_ref::<gtk4::Widget>().clone();
+    glib::MainContext::default().spawn_local(async move {
+        let Ok(needs_passphrase) = rx.recv().await else { return };
+        if !needs_passphrase {
+            then();
+            return;
+        }
+        show_passphrase_dialog(&parent, move |passphrase| {
+            set_passphrase(passphrase).map_err(|e| match e.downcast_ref::<TokenFileError>() {
+                Some(TokenFileError::WrongPassphrase) => "Falsche Passphrase oder beschädigte Token-Datei.".to_string(),
+                _ => format!("Die Token-Datei konnte nicht gelesen werden: {e:#}"),
+            })?;
+            then();
+            Ok(())
+        });
+    });
+}
+
+/// Asks for the passphrase of the encrypted token file, or lets the user
+/// choose one if the file does not exist yet. An error returned by
+/// `on_entered` is shown and the dialog asks again.
+pub fn show_passphrase_dialog(
+    parent: &impl IsA<gtk4::Widget>,
+    on_entered: impl Fn(&str) -> Result<(), String> + 'static,
+) {
+    present(parent.upcast_ref(), None, Rc::new(on_entered));
+}
+
+fn present(
+    parent: &gtk4::Widget,
+    error: Option<&str>,
+    on_entered: Rc<dyn Fn(&str) -> Result<(), String>>,
+) {
+    let create = !token_file_path().exists();
+
+    let (heading, body, action) = if create {
+        (
+            "Passphrase festlegen",
+            "Es ist kein Schlüsselbund verfügbar. Deine API-Tokens werden deshalb \
+             verschlüsselt in einer Datei gespeichert. Lege dafür eine Passphrase fest.",
+            "Festlegen",
+        )
+    } else {
+        (
+            "API-Tokens entsperren",
+            "Gib die Passphrase ein, mit der deine API-Tokens verschlüsselt sind.",
+            "Entsperren",
+        )
+    };
+
+    let alert = adw::AlertDialog::new(Some(heading), Some(body));
+
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    let group = adw::PreferencesGroup::new();
+
+    let pass_row = adw::PasswordEntryRow::new();
+    pass_row.set_title("Passphrase");
+    group.add(&pass_row);
+
+    let confirm_row = adw::PasswordEntryRow::new();
+    confirm_row.set_title("Passphrase wiederholen");
+    if create {
+        group.add(&confirm_row);
+    }
+    vbox.append(&group);
+
+    if let Some(error) = error {
+        let error_label = gtk4::Label::new(Some(error));
+        error_label.add_css_class("error");
+        error_label.set_wrap(true);
+        vbox.append(&error_label);
+    }
+
+    alert.set_extra_child(Some(&vbox));
+    alert.add_response("cancel", "Abbrechen");
+    alert.add_response("ok", action);
+    alert.set_response_appearance("ok", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("ok"));
+    alert.set_close_response("cancel");
+
+    let parent_for_retry = parent.clone();
+    alert.connect_response(None, move |_, response| {
+        if response != "ok" {
+            return;
+        }
+        let passphrase = pass_row.text().to_string();
+        let result = if passphrase.is_empty() {
+            Err("Bitte eine Passphrase eingeben.".to_string())
+        } else if create && passphrase != confirm_row.text().as_str() {
+            Err("Die Passphrasen stimmen nicht überein.".to_string())
+        } else {
+            on_entered(&passphrase)
+        };
+        if let Err(msg) = result {
+            present(&parent_for_retry, Some(&msg), on_entered.clone());
+        }
+    });
+
+    alert.present(Some(parent));
+}
@@ -0,0 +1,51 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+
+use crate::api::types::ReceivedShare;
+
+/// Sidebar section "Mit mir geteilt". Returns the section (hidden until
+/// there are shares) and the list to fill with [`fill_shares_list`].
+pub fn build_shares_section() -> (gtk4::Box, gtk4::ListBox) {
+    let section = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    section.set_visible(false);
+
+    let heading = gtk4::Label::new(Some("Mit mir geteilt"));
+    heading.add_css_class("heading");
+    heading.add_css_class("dim-label");
+    heading.set_halign(gtk4::Align::Start);
+    heading.set_margin_start(12);
+    heading.set_margin_top(12);
+    heading.set_margin_bottom(4);
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::Single);
+    list.add_css_class("navigation-sidebar");
+
+    section.append(&heading);
+    section.append(&list);
+    (section, list)
+}
+
+/// One row per share, in the order of `shares`.
+pub fn fill_shares_list(section: &gtk4::Box, list: &gtk4::ListBox, shares: &[ReceivedShare]) {
+    list.remove_all();
+    for share in shares {
+        let row = adw::ActionRow::new();
+        row.set_title(&glib::markup_escape_text(&share.owner_display_name));
+        if let Some(until) = share.expires_at.as_deref().and_then(format_share_date) {
+            row.set_subtitle(&format!("bis {until}"));
+        }
+        row.add_prefix(&gtk4::Image::from_icon_name("avatar-default-symbolic"));
+        row.set_activatable(true);
+        row.set_tooltip_text(Some("Nur lesen"));
+        list.append(&row);
+    }
+    section.set_visible(!shares.is_empty());
+}
+
+/// "31.12.2025" for an RFC 3339 timestamp of the shares API.
+pub fn format_share_date(timestamp: &str) -> Option<String> {
+    let at = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(at.with_timezone(&chrono::Local).format("%d.%m.%Y").to_string())
+}
@@ -0,0 +1,433 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use std::rc::Rc;
+
+use crate::api::{types::*, ApiError};
+use crate::state::{spawn_task, AppStore};
+use crate::ui::dashboard::search_references;
+use crate::ui::entry_editor::{format_number, parse_number};
+use crate::ui::error_actions::error_toast;
+
+/// Admin page: searchable list of the reference database. Activating a row
+/// opens the form for that value.
+pub fn build_reference_editor_page(
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    toast_overlay: &adw::ToastOverlay,
+) -> adw::NavigationPage {
+    let page = adw::NavigationPage::new(&gtk4::Label::new(None), "Referenzwerte");
+
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let top_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    let search = gtk4::SearchEntry::new();
+    search.set_placeholder_text(Some("Name, Kürzel oder Alias suchen"));
+    search.set_hexpand(true);
+    let new_btn = gtk4::Button::with_label("Neuer Wert");
+    new_btn.add_css_class("suggested-action");
+    new_btn.add_css_class("pill");
+    top_box.append(&search);
+    top_box.append(&new_btn);
+    vbox.append(&top_box);
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    let placeholder = gtk4::Label::new(Some("Keine Referenzwerte gefunden."));
+    placeholder.add_css_class("dim-label");
+    placeholder.set_margin_top(12);
+    placeholder.set_margin_bottom(12);
+    list.set_placeholder(Some(&placeholder));
+    vbox.append(&list);
+
+    let scrolled = gtk4::ScrolledWindow::new();
+    scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+    scrolled.set_vexpand(true);
+    scrolled.set_child(Some(&vbox));
+    page.set_child(Some(&scrolled));
+
+    let fill = Rc::new(clone!(#[weak] list, #[weak] search, #[weak] nav_view, #[strong] toast_overlay, move |store: &AppStore| {
+        fill_list(&list, &search.text(), &nav_view, store, &toast_overlay);
+    }));
+    fill(store);
+
+    search.connect_search_changed(clone!(#[strong] fill, #[strong] store, move |_| fill(&store)));
+    store.connect_changed_for(&page, clone!(#[strong] fill, move |store| fill(store)));
+
+    new_btn.connect_clicked(clone!(#[weak] nav_view, #[strong] store, #[strong] toast_overlay, move |_| {
+        let form = build_reference_form(&nav_view, &store, &toast_overlay, None);
+        nav_view.push(&form);
+    }));
+
+    page
+}
+
+fn fill_list(
+    list: &gtk4::ListBox,
+    query: &str,
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    toast_overlay: &adw::ToastOverlay,
+) {
+    list.remove_all();
+    let db = store.reference_db();
+    let values: Vec<&ReferenceValue> = if query.trim().is_empty() {
+        let mut all: Vec<_> = db.iter().collect();
+        all.sort_by(|a, b| a.category.cmp(&b.category).then_with(|| a.name.cmp(&b.name)));
+        all
+    } else {
+        search_references(&db, query, usize::MAX)
+    };
+
+    for value in values {
+        let row = adw::ActionRow::new();
+        row.set_title(&glib::markup_escape_text(&value.name));
+        row.set_subtitle(&glib::markup_escape_text(&format!("{} · {}", value.category, value.unit)));
+        row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        row.set_activatable(true);
+
+        let value = value.clone();
+        row.connect_activated(clone!(#[weak] nav_view, #[strong] store, #[strong] toast_overlay, move |_| {
+            let form = build_reference_form(&nav_view, &store, &toast_overlay, Some(value.clone()));
+            nav_view.push(&form);
+        }));
+        list.append(&row);
+    }
+}
+
+/// Checks a reference value before it is sent: required fields, every
+/// min below its max, and critical bounds outside all normal ranges.
+pub fn validate_reference_value(v: &ReferenceValue) -> Result<(), String> {
+    if v.name.trim().is_empty() || v.category.trim().is_empty() || v.unit.trim().is_empty() {
+        return Err("Name, Kategorie und Einheit sind Pflichtfelder.".to_string());
+    }
+
+    let ranges = [
+        ("Referenzbereich", v.ref_min, v.ref_max),
+        ("Referenzbereich Frauen", v.ref_min_female, v.ref_max_female),
+        ("Referenzbereich Männer", v.ref_min_male, v.ref_max_male),
+        ("Optimalbereich", v.optimal_min, v.optimal_max),
+    ];
+    for (label, min, max) in ranges {
+        if let (Some(min), Some(max)) = (min, max) {
+            if min >= max {
+                return Err(format!("{label}: Minimum muss kleiner als Maximum sein."));
+            }
+        }
+    }
+
+    let normal_bounds: Vec<f64> = ranges[..3]
+        .iter()
+        .flat_map(|(_, min, max)| [*min, *max])
+        .flatten()
+        .collect();
+    if let Some(low) = v.critical_low {
+        if normal_bounds.iter().any(|&b| low >= b) {
+            return Err("Die kritische Untergrenze muss unter dem Referenzbereich liegen.".to_string());
+        }
+    }
+    if let Some(high) = v.critical_high {
+        if normal_bounds.iter().any(|&b| high <= b) {
+            return Err("Die kritische Obergrenze muss über dem Referenzbereich liegen.".to_string());
+        }
+    }
+    if let (Some(low), Some(high)) = (v.critical_low, v.critical_high) {
+        if low >= high {
+            return Err("Die kritische Untergrenze muss kleiner als die Obergrenze sein.".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Form for one reference value; `existing` is `None` for a new one.
+fn build_reference_form(
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    toast_overlay: &adw::ToastOverlay,
+    existing: Option<ReferenceValue>,
+) -> adw::NavigationPage {
+    let title = existing.as_ref().map_or("Neuer Referenzwert".to_string(), |v| v.name.clone());
+    let page = adw::NavigationPage::new(&gtk4::Label::new(None), &title);
+    let prefs = adw::PreferencesPage::new();
+
+    let general = adw::PreferencesGroup::new();
+    general.set_title("Allgemein");
+    let name_row = text_row(&general, "Name");
+    let short_name_row = text_row(&general, "Kurzname");
+    let long_name_row = text_row(&general, "Langname");
+    let category_row = text_row(&general, "Kategorie");
+    let unit_row = text_row(&general, "Einheit");
+    let aliases_row = text_row(&general, "Aliase (durch Komma getrennt)");
+    prefs.add(&general);
+
+    let normal = adw::PreferencesGroup::new();
+    normal.set_title("Referenzbereich");
+    normal.set_description(Some("Die geschlechtsspezifischen Bereiche haben Vorrang, wenn sie gesetzt sind."));
+    let ref_min_row = text_row(&normal, "Minimum");
+    let ref_max_row = text_row(&normal, "Maximum");
+    let ref_min_female_row = text_row(&normal, "Minimum Frauen");
+    let ref_max_female_row = text_row(&normal, "Maximum Frauen");
+    let ref_min_male_row = text_row(&normal, "Minimum Männer");
+    let ref_max_male_row = text_row(&normal, "Maximum Männer");
+    prefs.add(&normal);
+
+    let bounds = adw::PreferencesGroup::new();
+    bounds.set_title("Optimal- und Grenzwerte");
+    let optimal_min_row = text_row(&bounds, "Optimal ab");
+    let optimal_max_row = text_row(&bounds, "Optimal bis");
+    let critical_low_row = text_row(&bounds, "Kritisch unter");
+    let critical_high_row = text_row(&bounds, "Kritisch über");
+    prefs.add(&bounds);
+
+    let description_view = text_area(&prefs, "Beschreibung");
+    let high_info_view = text_area(&prefs, "Bei erhöhtem Wert");
+    let low_info_view = text_area(&prefs, "Bei erniedrigtem Wert");
+    let recommendations_view = text_area(&prefs, "Empfehlungen");
+
+    let actions = adw::PreferencesGroup::new();
+    let save_btn = adw::ButtonRow::new();
+    save_btn.set_title("Speichern");
+    save_btn.add_css_class("suggested-action");
+    actions.add(&save_btn);
+    let delete_btn = adw::ButtonRow::new();
+    delete_btn.set_title("Löschen");
+    delete_btn.add_css_class("destructive-action");
+    delete_btn.set_visible(existing.is_some());
+    actions.add(&delete_btn);
+    prefs.add(&actions);
+
+    page.set_child(Some(&prefs));
+
+    // Fill in the existing value
+    let base = existing.clone().unwrap_or_default();
+    name_row.set_text(&base.name);
+    short_name_row.set_text(base.short_name.as_deref().unwrap_or(""));
+    long_name_row.set_text(base.long_name.as_deref().unwrap_or(""));
+    category_row.set_text(&base.category);
+    unit_row.set_text(&base.unit);
+    aliases_row.set_text(&base.aliases.join(", "));
+    let numbers = [
+        (&ref_min_row, base.ref_min),
+        (&ref_max_row, base.ref_max),
+        (&ref_min_female_row, base.ref_min_female),
+        (&ref_max_female_row, base.ref_max_female),
+        (&ref_min_male_row, base.ref_min_male),
+        (&ref_max_male_row, base.ref_max_male),
+        (&optimal_min_row, base.optimal_min),
+        (&optimal_max_row, base.optimal_max),
+        (&critical_low_row, base.critical_low),
+        (&critical_high_row, base.critical_high),
+    ];
+    for (row, value) in numbers {
+        row.set_text(&value.map(format_number).unwrap_or_default());
+    }
+    description_view.buffer().set_text(&base.description);
+    high_info_view.buffer().set_text(&base.high_info);
+    low_info_view.buffer().set_text(&base.low_info);
+    recommendations_view.buffer().set_text(&base.recommendations);
+
+    // Save
+    let store_clone = store.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    save_btn.connect_activated(clone!(#[weak] nav_view, move |btn| {
+        let number = |row: &adw::EntryRow| optional_number(row);
+        let read = || -> Result<ReferenceValue, String> {
+            Ok(ReferenceValue {
+                id: base.id.clone(),
+                name: name_row.text().trim().to_string(),
+                short_name: optional_text(&short_name_row),
+                long_name: optional_text(&long_name_row),
+                aliases: aliases_row
+                    .text()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(String::from)
+                    .collect(),
+                category: category_row.text().trim().to_string(),
+                unit: unit_row.text().trim().to_string(),
+                ref_min: number(&ref_min_row)?,
+                ref_max: number(&ref_max_row)?,
+                ref_min_female: number(&ref_min_female_row)?,
+                ref_max_female: number(&ref_max_female_row)?,
+                ref_min_male: number(&ref_min_male_row)?,
+                ref_max_male: number(&ref_max_male_row)?,
+                optimal_min: number(&optimal_min_row)?,
+                optimal_max: number(&optimal_max_row)?,
+                critical_low: number(&critical_low_row)?,
+                critical_high: number(&critical_high_row)?,
+                description: text_of(&description_view),
+                high_info: text_of(&high_info_view),
+                low_info: text_of(&low_info_view),
+                recommendations: text_of(&recommendations_view),
+                // Not editable here yet
+                age_ranges: base.age_ranges.clone(),
+            })
+        };
+        let value = match read().and_then(|v| validate_reference_value(&v).map(|_| v)) {
+            Ok(value) => value,
+            Err(msg) => {
+                toast_overlay_clone.add_toast(adw::Toast::new(&msg));
+                return;
+            }
+        };
+        let Some(client) = store_clone.client() else { return };
+        btn.set_sensitive(false);
+
+        let is_new = value.id.is_empty();
+        let (tx, rx) = async_channel::bounded::<Result<ReferenceValue, ApiError>>(1);
+        spawn_task(async move {
+            let r = if is_new {
+                client.create_reference_value(&value).await
+            } else {
+                client.update_reference_value(&value).await
+            };
+            tx.send(r).await.ok();
+        });
+
+        let store = store_clone.clone();
+        let toast_overlay = toast_overlay_clone.clone();
+        glib::MainContext::default().spawn_local(clone!(#[weak] btn, #[weak] nav_view, async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(saved) => {
+                        let mut values = (*store.reference_db()).clone();
+                        match values.iter_mut().find(|v| v.id == saved.id) {
+                            Some(v) => *v = saved,
+                            None => values.push(saved),
+                        }
+                        store.set_reference_db(values);
+                        toast_overlay.add_toast(adw::Toast::new("Referenzwert gespeichert"));
+                        nav_view.pop();
+                    }
+                    Err(e) => {
+                        toast_overlay.add_toast(error_toast("Speichern fehlgeschlagen", &anyhow::Error::new(e)));
+                    }
+                }
+                btn.set_sensitive(true);
+            }
+        }));
+    }));
+
+    // Delete
+    if let Some(existing) = existing {
+        let store = store.clone();
+        let toast_overlay = toast_overlay.clone();
+        delete_btn.connect_activated(clone!(#[weak] nav_view, move |btn| {
+            confirm_delete(btn, &nav_view, &store, &toast_overlay, &existing);
+        }));
+    }
+
+    page
+}
+
+fn confirm_delete(
+    parent: &adw::ButtonRow,
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    toast_overlay: &adw::ToastOverlay,
+    value: &ReferenceValue,
+) {
+    let alert = adw::AlertDialog::new(
+        Some("Referenzwert löschen?"),
+        Some(&format!(
+            "„{}“ wird für alle Benutzer entfernt. Messwerte mit diesem Namen haben danach keinen Referenzbereich mehr.",
+            value.name
+        )),
+    );
+    alert.add_response("cancel", "Abbrechen");
+    alert.add_response("delete", "Löschen");
+    alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+    alert.set_default_response(Some("cancel"));
+    alert.set_close_response("cancel");
+
+    let store = store.clone();
+    let toast_overlay = toast_overlay.clone();
+    let id = value.id.clone();
+    alert.connect_response(None, clone!(#[weak] nav_view, move |_, response| {
+        if response != "delete" {
+            return;
+        }
+        let Some(client) = store.client() else { return };
+
+        let task_id = id.clone();
+        let (tx, rx) = async_channel::bounded::<Result<(), ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.delete_reference_value(&task_id).await).await.ok();
+        });
+
+        let store = store.clone();
+        let toast_overlay = toast_overlay.clone();
+        let id = id.clone();
+        glib::MainContext::default().spawn_local(clone!(#[weak] nav_view, async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(()) => {
+                        let values = store.reference_db().iter().filter(|v| v.id != id).cloned().collect();
+                        store.set_reference_db(values);
+                        toast_overlay.add_toast(adw::Toast::new("Referenzwert gelöscht"));
+                        nav_view.pop();
+                    }
+                    Err(e) => {
+                        toast_overlay.add_toast(error_toast("Löschen fehlgeschlagen", &anyhow::Error::new(e)));
+                    }
+                }
+            }
+        }));
+    }));
+
+    alert.present(Some(parent));
+}
+
+fn text_row(group: &adw::PreferencesGroup, title: &str) -> adw::EntryRow {
+    let row = adw::EntryRow::new();
+    row.set_title(title);
+    group.add(&row);
+    row
+}
+
+/// Multi-line text field in its own group.
+fn text_area(prefs: &adw::PreferencesPage, title: &str) -> gtk4::TextView {
+    let group = adw::PreferencesGroup::new();
+    group.set_title(title);
+
+    let view = gtk4::TextView::new();
+    view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    view.set_top_margin(8);
+    view.set_bottom_margin(8);
+    view.set_left_margin(8);
+    view.set_right_margin(8);
+    view.set_size_request(-1, 80);
+
+    let frame = gtk4::Frame::new(None);
+    frame.set_child(Some(&view));
+    group.add(&frame);
+    prefs.add(&group);
+    view
+}
+
+fn text_of(view: &gtk4::TextView) -> String {
+    let buffer = view.buffer();
+    buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).trim().to_string()
+}
+
+fn optional_text(row: &adw::EntryRow) -> Option<String> {
+    let text = row.text().trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn optional_number(row: &adw::EntryRow) -> Result<Option<f64>, String> {
+    let text = row.text();
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_number(&text)
+        .map(Some)
+        .ok_or_else(|| format!("{}: keine gültige Zahl", row.title()))
+}
@@ -0,0 +1,427 @@
+//! Content, pagination and drawing of the A4 report. Pure Cairo, so the
+//! same pages go to a PDF file and to the printer.
+
+use cairo::Context;
+use chrono::NaiveDate;
+
+use crate::api::types::*;
+use crate::ui::dashboard::{category_group::collect_history_for, collect_latest_values, find_reference};
+use crate::ui::value_detail::chart::ChartState;
+use crate::ui::value_detail::export::{render_export, ExportChart};
+use crate::ui::value_detail::format_date;
+use crate::units::DisplayData;
+
+/// A4 in points
+pub const PAGE_WIDTH: f64 = 595.28;
+pub const PAGE_HEIGHT: f64 = 841.89;
+const MARGIN: f64 = 40.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+const FOOTER_HEIGHT: f64 = 20.0;
+const AVAILABLE_HEIGHT: f64 = PAGE_HEIGHT - 2.0 * MARGIN - FOOTER_HEIGHT;
+
+const HEADER_HEIGHT: f64 = 96.0;
+const CATEGORY_HEIGHT: f64 = 36.0;
+const ROW_HEIGHT: f64 = 18.0;
+const CHART_HEIGHT: f64 = 240.0;
+
+/// Table columns: (title, width)
+const COLUMNS: [(&str, f64); 6] = [
+    ("Wert", 150.0),
+    ("Ergebnis", 80.0),
+    ("Referenzbereich", 95.0),
+    ("Status", 75.0),
+    ("Datum", 55.0),
+    ("Verlauf", 60.0),
+];
+
+/// Everything the report shows, collected from the store.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub patient: String,
+    pub birth_date: Option<String>,
+    pub gender: Option<String>,
+    /// Dates of the first and last entry
+    pub period: Option<(String, String)>,
+    pub created: NaiveDate,
+    pub categories: Vec<ReportCategory>,
+    /// Full charts of the chosen values
+    pub charts: Vec<ExportChart>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportCategory {
+    pub name: String,
+    pub rows: Vec<ReportRow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportRow {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+    pub date: String,
+    pub status: ValueStatus,
+    pub range: (Option<f64>, Option<f64>),
+    pub derived: bool,
+    pub history: Vec<ValueHistoryPoint>,
+}
+
+impl ReportRow {
+    pub fn out_of_range(&self) -> bool {
+        matches!(
+            self.status,
+            ValueStatus::High | ValueStatus::Low | ValueStatus::CriticalHigh | ValueStatus::CriticalLow
+        )
+    }
+}
+
+/// Collects the latest value of each analyte by category, like the
+/// dashboard, plus full charts of the values named in `charts`.
+pub fn build_report(
+    data: &DisplayData,
+    evaluation: &Evaluation,
+    charts: &[String],
+    created: NaiveDate,
+) -> Report {
+    let user_data = &data.user_data;
+    let mut categories: Vec<ReportCategory> = Vec::new();
+
+    for (date, bv) in collect_latest_values(user_data) {
+        let ref_val = find_reference(&data.reference_db, &bv.name);
+        let row = ReportRow {
+            status: ref_val.map_or(ValueStatus::Unknown, |r| evaluation.status(bv.value, r, &date)),
+            range: ref_val.map_or((None, None), |r| evaluation.range(r, &date)),
+            history: collect_history_for(user_data, &bv.name),
+            name: bv.name,
+            value: bv.value,
+            unit: bv.unit,
+            date,
+            derived: bv.derived,
+        };
+        match categories.iter_mut().find(|c| c.name == bv.category) {
+            Some(category) => category.rows.push(row),
+            None => categories.push(ReportCategory { name: bv.category, rows: vec![row] }),
+        }
+    }
+
+    let charts = charts
+        .iter()
+        .filter_map(|name| {
+            let history = collect_history_for(user_data, name);
+            let ref_val = find_reference(&data.reference_db, name).cloned();
+            let unit = ref_val
+                .as_ref()
+                .map(|r| r.unit.clone())
+                .or_else(|| history.last().map(|p| p.unit.clone()))?;
+            Some(ExportChart {
+                title: name.clone(),
+                unit,
+                history,
+                ref_val,
+                evaluation: evaluation.clone(),
+                state: ChartState::default(),
+            })
+        })
+        .collect();
+
+    let dates = || user_data.entries.iter().map(|e| e.date.as_str());
+    let period = dates().min().zip(dates().max()).map(|(a, b)| (a.to_string(), b.to_string()));
+
+    Report {
+        patient: user_data.display_name.clone(),
+        birth_date: user_data.birth_date.clone(),
+        gender: evaluation.gender.clone(),
+        period,
+        created,
+        categories,
+        charts,
+    }
+}
+
+/// Something placed on a page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Block {
+    Header,
+    /// Category title and column heads; `true` when continued from the
+    /// previous page
+    Category(usize, bool),
+    /// Row of a category
+    Row(usize, usize),
+    Chart(usize),
+}
+
+impl Block {
+    fn height(&self) -> f64 {
+        match self {
+            Block::Header => HEADER_HEIGHT,
+            Block::Category(..) => CATEGORY_HEIGHT,
+            Block::Row(..) => ROW_HEIGHT,
+            Block::Chart(_) => CHART_HEIGHT,
+        }
+    }
+}
+
+/// Distributes the report over pages. A category title never ends a
+/// page, and a category cut by a page break gets its title again.
+pub fn paginate(report: &Report) -> Vec<Vec<Block>> {
+    let mut pages: Vec<Vec<Block>> = vec![vec![Block::Header]];
+    let mut used = HEADER_HEIGHT;
+
+    for (c, category) in report.categories.iter().enumerate() {
+        place(&mut pages, &mut used, Block::Category(c, false), ROW_HEIGHT);
+        for r in 0..category.rows.len() {
+            if used + ROW_HEIGHT > AVAILABLE_HEIGHT {
+                start_page(&mut pages, &mut used);
+                place(&mut pages, &mut used, Block::Category(c, true), ROW_HEIGHT);
+            }
+            place(&mut pages, &mut used, Block::Row(c, r), 0.0);
+        }
+    }
+    for i in 0..report.charts.len() {
+        place(&mut pages, &mut used, Block::Chart(i), 0.0);
+    }
+    pages
+}
+
+/// Adds `block` to the last page, or to a new one if it doesn't fit
+/// together with `keep_with` points of what follows.
+fn place(pages: &mut Vec<Vec<Block>>, used: &mut f64, block: Block, keep_with: f64) {
+    if *used > 0.0 && *used + block.height() + keep_with > AVAILABLE_HEIGHT {
+        start_page(pages, used);
+    }
+    pages.last_mut().expect("at least one page").push(block);
+    *used += block.height();
+}
+
+fn start_page(pages: &mut Vec<Vec<Block>>, used: &mut f64) {
+    pages.push(Vec::new());
+    *used = 0.0;
+}
+
+/// Draws page `index` of `pages`, in points on an A4 page.
+pub fn render_page(cr: &Context, report: &Report, pages: &[Vec<Block>], index: usize) {
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    let _ = cr.paint();
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    let mut y = MARGIN;
+    for block in pages.get(index).into_iter().flatten() {
+        match *block {
+            Block::Header => draw_header(cr, report, y),
+            Block::Category(c, continued) => draw_category(cr, &report.categories[c], continued, y),
+            Block::Row(c, r) => draw_row(cr, &report.categories[c].rows[r], r, y),
+            Block::Chart(i) => {
+                cr.save().ok();
+                cr.translate(MARGIN, y + 8.0);
+                cr.rectangle(0.0, 0.0, CONTENT_WIDTH, CHART_HEIGHT - 16.0);
+                cr.clip();
+                render_export(cr, CONTENT_WIDTH, CHART_HEIGHT - 16.0, &report.charts[i]);
+                cr.restore().ok();
+                // Frame around the chart
+                cr.set_source_rgb(0.8, 0.8, 0.8);
+                cr.set_line_width(0.5);
+                cr.rectangle(MARGIN, y + 8.0, CONTENT_WIDTH, CHART_HEIGHT - 16.0);
+                let _ = cr.stroke();
+            }
+        }
+        y += block.height();
+    }
+
+    // Footer
+    cr.set_source_rgb(0.5, 0.5, 0.5);
+    cr.set_font_size(8.0);
+    let left = format!("Blutwerte-Bericht · {}", report.patient);
+    let _ = cr.move_to(MARGIN, PAGE_HEIGHT - MARGIN);
+    let _ = cr.show_text(&left);
+    let right = format!("Seite {} von {}", index + 1, pages.len());
+    let right_w = cr.text_extents(&right).map(|e| e.x_advance()).unwrap_or(0.0);
+    let _ = cr.move_to(PAGE_WIDTH - MARGIN - right_w, PAGE_HEIGHT - MARGIN);
+    let _ = cr.show_text(&right);
+}
+
+fn draw_header(cr: &Context, report: &Report, y: f64) {
+    cr.set_source_rgb(0.1, 0.1, 0.1);
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+    cr.set_font_size(18.0);
+    let _ = cr.move_to(MARGIN, y + 18.0);
+    let _ = cr.show_text("Blutwerte-Bericht");
+
+    cr.set_font_size(12.0);
+    let _ = cr.move_to(MARGIN, y + 40.0);
+    let _ = cr.show_text(&report.patient);
+
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    cr.set_font_size(9.0);
+    cr.set_source_rgb(0.35, 0.35, 0.35);
+    let mut details = Vec::new();
+    if let Some(birth) = &report.birth_date {
+        details.push(format!("geboren am {}", format_date(birth)));
+    }
+    match report.gender.as_deref() {
+        Some("female") => details.push("weiblich".to_string()),
+        Some("male") => details.push("männlich".to_string()),
+        _ => {}
+    }
+    if let Some((first, last)) = &report.period {
+        details.push(format!("Befunde vom {} bis {}", format_date(first), format_date(last)));
+    }
+    let _ = cr.move_to(MARGIN, y + 56.0);
+    let _ = cr.show_text(&details.join(" · "));
+
+    let total: usize = report.categories.iter().map(|c| c.rows.len()).sum();
+    let outside = report.categories.iter().flat_map(|c| &c.rows).filter(|r| r.out_of_range()).count();
+    let summary = format!(
+        "{total} Werte, davon {outside} außerhalb des Referenzbereichs · erstellt am {}",
+        report.created.format("%d.%m.%Y")
+    );
+    let _ = cr.move_to(MARGIN, y + 70.0);
+    let _ = cr.show_text(&summary);
+
+    cr.set_source_rgb(0.8, 0.8, 0.8);
+    cr.set_line_width(0.5);
+    let _ = cr.move_to(MARGIN, y + 82.0);
+    let _ = cr.line_to(MARGIN + CONTENT_WIDTH, y + 82.0);
+    let _ = cr.stroke();
+}
+
+fn draw_category(cr: &Context, category: &ReportCategory, continued: bool, y: f64) {
+    cr.set_source_rgb(0.1, 0.1, 0.1);
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+    cr.set_font_size(11.0);
+    let title = if continued {
+        format!("{} (Fortsetzung)", category.name)
+    } else {
+        category.name.clone()
+    };
+    let _ = cr.move_to(MARGIN, y + 16.0);
+    let _ = cr.show_text(&title);
+
+    cr.set_source_rgb(0.4, 0.4, 0.4);
+    cr.set_font_size(8.0);
+    let mut x = MARGIN;
+    for (title, width) in COLUMNS {
+        let _ = cr.move_to(x + 2.0, y + 31.0);
+        let _ = cr.show_text(title);
+        x += width;
+    }
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    cr.set_source_rgb(0.6, 0.6, 0.6);
+    cr.set_line_width(0.5);
+    let _ = cr.move_to(MARGIN, y + CATEGORY_HEIGHT - 1.0);
+    let _ = cr.line_to(MARGIN + CONTENT_WIDTH, y + CATEGORY_HEIGHT - 1.0);
+    let _ = cr.stroke();
+}
+
+fn draw_row(cr: &Context, row: &ReportRow, index: usize, y: f64) {
+    // Values outside the range on a red background, others striped
+    if row.out_of_range() {
+        cr.set_source_rgb(0.996, 0.886, 0.886); // red-100
+        cr.rectangle(MARGIN, y, CONTENT_WIDTH, ROW_HEIGHT);
+        let _ = cr.fill();
+    } else if index % 2 == 1 {
+        cr.set_source_rgb(0.97, 0.97, 0.97);
+        cr.rectangle(MARGIN, y, CONTENT_WIDTH, ROW_HEIGHT);
+        let _ = cr.fill();
+    }
+
+    let name = if row.derived { format!("{} (berechnet)", row.name) } else { row.name.clone() };
+    let cells = [
+        name,
+        format!("{} {}", format_number(row.value), row.unit),
+        format_range(row.range),
+        row.status.label().to_string(),
+        format_date(&row.date),
+    ];
+    let baseline = y + 12.5;
+    cr.set_font_size(8.5);
+    let mut x = MARGIN;
+    for (i, text) in cells.iter().enumerate() {
+        let width = COLUMNS[i].1;
+        let bold = i == 1 && row.out_of_range();
+        let weight = if bold { cairo::FontWeight::Bold } else { cairo::FontWeight::Normal };
+        let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, weight);
+        if i == 3 {
+            let (r, g, b) = row.status.color();
+            cr.set_source_rgb(r, g, b);
+        } else {
+            cr.set_source_rgb(0.1, 0.1, 0.1);
+        }
+        let _ = cr.move_to(x + 2.0, baseline);
+        let _ = cr.show_text(&fit_text(cr, text, width - 4.0));
+        x += width;
+    }
+    let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+
+    draw_sparkline(cr, row, x + 2.0, y + 3.0, COLUMNS[5].1 - 4.0, ROW_HEIGHT - 6.0);
+}
+
+/// Mini trend of a row: the values as a line over the latest range.
+fn draw_sparkline(cr: &Context, row: &ReportRow, x: f64, y: f64, w: f64, h: f64) {
+    let values: Vec<f64> = row.history.iter().map(|p| p.value).collect();
+    if values.len() < 2 {
+        return;
+    }
+    let bounds = values.iter().copied().chain([row.range.0, row.range.1].into_iter().flatten());
+    let (min, max) = bounds.fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), v| (a.min(v), b.max(v)));
+    let span = if max - min > f64::EPSILON { max - min } else { 1.0 };
+    let to_y = |v: f64| y + h - (v - min) / span * h;
+    let to_x = |i: usize| x + i as f64 / (values.len() - 1) as f64 * w;
+
+    if let (Some(lo), Some(hi)) = row.range {
+        cr.set_source_rgba(0.133, 0.773, 0.369, 0.2);
+        cr.rectangle(x, to_y(hi), w, to_y(lo) - to_y(hi));
+        let _ = cr.fill();
+    }
+
+    cr.set_source_rgb(0.231, 0.510, 0.965);
+    cr.set_line_width(1.0);
+    for (i, v) in values.iter().enumerate() {
+        if i == 0 {
+            let _ = cr.move_to(to_x(i), to_y(*v));
+        } else {
+            let _ = cr.line_to(to_x(i), to_y(*v));
+        }
+    }
+    let _ = cr.stroke();
+
+    let (r, g, b) = row.status.color();
+    cr.set_source_rgb(r, g, b);
+    cr.arc(to_x(values.len() - 1), to_y(values[values.len() - 1]), 1.8, 0.0, 2.0 * std::f64::consts::PI);
+    let _ = cr.fill();
+}
+
+/// `text` shortened with "…" to fit `max_width`.
+fn fit_text(cr: &Context, text: &str, max_width: f64) -> String {
+    let width = |s: &str| cr.text_extents(s).map(|e| e.x_advance()).unwrap_or(0.0);
+    if width(text) <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().collect::<String>() + "…";
+        if width(&candidate) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+fn format_range(range: (Option<f64>, Option<f64>)) -> String {
+    match range {
+        (Some(mn), Some(mx)) => format!("{} – {}", format_number(mn), format_number(mx)),
+        (Some(mn), None) => format!("≥ {}", format_number(mn)),
+        (None, Some(mx)) => format!("≤ {}", format_number(mx)),
+        (None, None) => "–".to_string(),
+    }
+}
+
+fn format_number(v: f64) -> String {
+    if v.fract().abs() < f64::EPSILON {
+        format!("{}", v as i64)
+    } else {
+        let s = format!("{:.2}", v);
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
@@ -0,0 +1,181 @@
+//! "Bericht erstellen": all latest values by category with status and
+//! reference ranges, plus charts of chosen values, as A4 PDF or printout.
+
+pub mod layout;
+
+use anyhow::{Context as _, Result};
+use gtk4::prelude::*;
+use gtk4::gio;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::state::AppStore;
+use layout::{build_report, paginate, render_page, Report, PAGE_HEIGHT, PAGE_WIDTH};
+
+/// Writes the report as multi-page A4 PDF.
+pub fn write_report_pdf(path: &Path, report: &Report) -> Result<()> {
+    let surface = cairo::PdfSurface::new(PAGE_WIDTH, PAGE_HEIGHT, path)
+        .context("Failed to create PDF surface")?;
+    let cr = cairo::Context::new(&surface).context("Failed to create drawing context")?;
+    let pages = paginate(report);
+    for index in 0..pages.len() {
+        render_page(&cr, report, &pages, index);
+        cr.show_page().context("Failed to finish PDF page")?;
+    }
+    drop(cr);
+    surface.finish();
+    surface.status().context("Failed to write file")
+}
+
+/// Asks which values get a chart and whether to print or save as PDF.
+/// Values outside their range are picked at first.
+pub fn show_report_dialog(parent: &impl IsA<gtk4::Widget>, store: &AppStore) {
+    let data = store.display_data();
+    let evaluation = store.evaluation();
+    let today = chrono::Local::now().date_naive();
+    let overview = build_report(&data, &evaluation, &[], today);
+
+    let alert = adw::AlertDialog::new(
+        Some("Bericht erstellen"),
+        Some("Alle aktuellen Werte nach Kategorie mit Status und Referenzbereich. \
+              Für die ausgewählten Werte kommt ein Verlaufsdiagramm dazu."),
+    );
+
+    let picked: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(
+        overview
+            .categories
+            .iter()
+            .flat_map(|c| &c.rows)
+            .filter(|r| r.out_of_range())
+            .map(|r| r.name.clone())
+            .collect(),
+    ));
+
+    let group = adw::PreferencesGroup::new();
+    group.set_title("Diagramme");
+    for row in overview.categories.iter().flat_map(|c| &c.rows) {
+        let action_row = adw::ActionRow::new();
+        action_row.set_title(&glib::markup_escape_text(&row.name));
+        let check = gtk4::CheckButton::new();
+        check.set_active(picked.borrow().contains(&row.name));
+        action_row.add_prefix(&check);
+        action_row.set_activatable_widget(Some(&check));
+        group.add(&action_row);
+
+        let name = row.name.clone();
+        check.connect_toggled(clone!(#[strong] picked, move |check| {
+            let mut picked = picked.borrow_mut();
+            if check.is_active() {
+                if !picked.contains(&name) {
+                    picked.push(name.clone());
+                }
+            } else {
+                picked.retain(|n| *n != name);
+            }
+        }));
+    }
+
+    let scrolled = gtk4::ScrolledWindow::new();
+    scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
+    scrolled.set_propagate_natural_height(true);
+    scrolled.set_max_content_height(320);
+    scrolled.set_child(Some(&group));
+
+    alert.set_extra_child(Some(&scrolled));
+    alert.add_response("cancel", "Abbrechen");
+    alert.add_response("print", "Drucken …");
+    alert.add_response("pdf", "Als PDF speichern …");
+    alert.set_response_appearance("pdf", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("pdf"));
+    alert.set_close_response("cancel");
+
+    let Some(window) = parent.root().and_downcast::<gtk4::Window>() else {
+        return;
+    };
+    alert.connect_response(None, clone!(#[weak] window, move |_, response| {
+        // Charts in dashboard order, not in the order they were ticked
+        let picked = picked.borrow();
+        let charts: Vec<String> = overview
+            .categories
+            .iter()
+            .flat_map(|c| &c.rows)
+            .filter(|r| picked.contains(&r.name))
+            .map(|r| r.name.clone())
+            .collect();
+        let report = build_report(&data, &evaluation, &charts, today);
+        match response {
+            "print" => print_report(&window, report),
+            "pdf" => choose_file(&window, report),
+            _ => {}
+        }
+    }));
+
+    alert.present(Some(parent));
+}
+
+fn choose_file(window: &gtk4::Window, report: Report) {
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some("PDF"));
+    filter.add_mime_type("application/pdf");
+    let filters = gio::ListStore::new::<gtk4::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk4::FileDialog::new();
+    dialog.set_title("Bericht speichern");
+    dialog.set_filters(Some(&filters));
+    dialog.set_default_filter(Some(&filter));
+    dialog.set_initial_name(Some(&format!("Blutwerte-Bericht_{}.pdf", report.created.format("%Y-%m-%d"))));
+
+    dialog.save(Some(window), gio::Cancellable::NONE, clone!(#[weak] window, move |result| {
+        let Ok(file) = result else { return };
+        let Some(path) = file.path() else { return };
+        if let Err(e) = write_report_pdf(&with_pdf_extension(path), &report) {
+            show_error(&window, "Bericht konnte nicht gespeichert werden", &format!("{e:#}"));
+        }
+    }));
+}
+
+/// Prints through the GTK print dialog, which can also save as PDF.
+/// Pages are scaled down to the printable area of the chosen paper.
+fn print_report(window: &gtk4::Window, report: Report) {
+    let pages = paginate(&report);
+
+    let page_setup = gtk4::PageSetup::new();
+    page_setup.set_paper_size(&gtk4::PaperSize::new(Some("iso_a4")));
+
+    let operation = gtk4::PrintOperation::new();
+    operation.set_job_name("Blutwerte-Bericht");
+    operation.set_default_page_setup(Some(&page_setup));
+    operation.set_unit(gtk4::Unit::Points);
+    operation.set_n_pages(pages.len() as i32);
+    operation.connect_draw_page(move |_, context, page_nr| {
+        let cr = context.cairo_context();
+        let scale = (context.width() / PAGE_WIDTH).min(context.height() / PAGE_HEIGHT);
+        cr.scale(scale, scale);
+        render_page(&cr, &report, &pages, page_nr as usize);
+    });
+
+    if let Err(e) = operation.run(gtk4::PrintOperationAction::PrintDialog, Some(window)) {
+        show_error(window, "Drucken fehlgeschlagen", &e.to_string());
+    }
+}
+
+fn with_pdf_extension(path: PathBuf) -> PathBuf {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf")) {
+        path
+    } else {
+        let mut name = path.into_os_string();
+        name.push(".pdf");
+        PathBuf::from(name)
+    }
+}
+
+fn show_error(window: &gtk4::Window, heading: &str, body: &str) {
+    let alert = adw::AlertDialog::new(Some(heading), Some(body));
+    alert.add_response("ok", "OK");
+    alert.present(Some(window));
+}
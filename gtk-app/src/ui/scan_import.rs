@@ -0,0 +1,246 @@
+use gtk4::prelude::*;
+use gtk4::{gdk, gio};
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use std::path::Path;
+
+use crate::api::{types::*, ApiError};
+use crate::state::spawn_task;
+use crate::ui::dashboard::find_reference;
+use crate::ui::entry_editor::{show_entry_draft, EntryEditorContext};
+
+/// Same limit as the multer upload config in the backend.
+const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+const SUPPORTED_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp", "application/pdf"];
+
+/// Lets the user pick a photo or PDF of a lab report and scans it.
+pub fn choose_and_scan(parent: &impl IsA<gtk4::Widget>, ctx: &EntryEditorContext) {
+    let Some(window) = parent.root().and_downcast::<gtk4::Window>() else {
+        return;
+    };
+
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some("Laborbefunde (JPEG, PNG, WebP, PDF)"));
+    for mime in SUPPORTED_TYPES {
+        filter.add_mime_type(mime);
+    }
+    let filters = gio::ListStore::new::<gtk4::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk4::FileDialog::new();
+    dialog.set_title("Befund auswählen");
+    dialog.set_filters(Some(&filters));
+    dialog.set_default_filter(Some(&filter));
+
+    let ctx = ctx.clone();
+    dialog.open(Some(&window), gio::Cancellable::NONE, clone!(#[weak] window, move |result| {
+        if let Ok(file) = result {
+            scan_file(&window, &ctx, &file);
+        }
+    }));
+}
+
+/// Accepts lab reports dropped onto `widget`. `editor` returns `None`
+/// while the data is read-only, which rejects the drop.
+pub fn install_drop_target(
+    widget: &impl IsA<gtk4::Widget>,
+    editor: impl Fn() -> Option<EntryEditorContext> + 'static,
+) {
+    let target = gtk4::DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+
+    let widget_weak = widget.upcast_ref::<gtk4::Widget>().downgrade();
+    target.connect_drop(move |_, value, _, _| {
+        let Some(ctx) = editor() else {
+            return false;
+        };
+        let Ok(file) = value.get::<gio::File>() else {
+            return false;
+        };
+        let Some(window) = widget_weak
+            .upgrade()
+            .and_then(|w| w.root())
+            .and_downcast::<gtk4::Window>()
+        else {
+            return false;
+        };
+        scan_file(&window, &ctx, &file);
+        true
+    });
+
+    widget.add_controller(target);
+}
+
+/// Uploads `file` to `/api/ai/scan` and opens the entry editor with the result.
+pub fn scan_file(window: &gtk4::Window, ctx: &EntryEditorContext, file: &gio::File) {
+    let Some(path) = file.path() else {
+        show_scan_error(window, "Es können nur lokale Dateien importiert werden.");
+        return;
+    };
+    let Some(mime_type) = mime_type_for(&path) else {
+        show_scan_error(
+            window,
+            "Nicht unterstütztes Dateiformat. Erlaubt sind JPEG, PNG, WebP und PDF.",
+        );
+        return;
+    };
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "befund".to_string());
+
+    let Some(client) = ctx.client() else {
+        show_scan_error(window, "Befunde können nur mit Serververbindung gescannt werden.");
+        return;
+    };
+
+    let progress = build_progress_dialog(&file_name);
+    progress.present(Some(window));
+
+    let (tx, rx) = async_channel::bounded::<Result<ScanResult, String>>(1);
+    spawn_task(async move {
+        let result = async {
+            let data = tokio::fs::read(&path)
+                .await
+                .map_err(|e| format!("Datei konnte nicht gelesen werden: {e}"))?;
+            if data.len() > MAX_UPLOAD_BYTES {
+                return Err("Die Datei ist größer als 10 MB.".to_string());
+            }
+            client
+                .scan_report(&file_name, mime_type, data)
+                .await
+                .map_err(|e| scan_error_message(&e))
+        }
+        .await;
+        tx.send(result).await.ok();
+    });
+
+    let ctx = ctx.clone();
+    glib::MainContext::default().spawn_local(clone!(#[weak] window, #[weak] progress, async move {
+        if let Ok(result) = rx.recv().await {
+            progress.force_close();
+            match result {
+                Ok(scan) if scan.values.is_empty() => {
+                    show_scan_error(&window, "Im Befund wurden keine Blutwerte erkannt.");
+                }
+                Ok(scan) => {
+                    let draft = scan_to_draft(&scan, &ctx.reference_db());
+                    show_entry_draft(&window, &ctx, "Befund prüfen", &draft);
+                }
+                Err(e) => show_scan_error(&window, &e),
+            }
+        }
+    }));
+}
+
+/// Turns the scan result into an editable entry, using reference names,
+/// units and categories where the value could be matched.
+pub fn scan_to_draft(scan: &ScanResult, reference_db: &[ReferenceValue]) -> BloodEntryInput {
+    let values = scan
+        .values
+        .iter()
+        .map(|v| {
+            let reference = v
+                .ref_id
+                .as_deref()
+                .and_then(|id| reference_db.iter().find(|r| r.id == id))
+                .or_else(|| find_reference(reference_db, &v.name));
+
+            let unit = if v.unit.trim().is_empty() {
+                reference.map(|r| r.unit.clone()).unwrap_or_default()
+            } else {
+                v.unit.clone()
+            };
+
+            BloodValue {
+                name: reference.map(|r| r.name.clone()).unwrap_or_else(|| v.name.clone()),
+                value: v.value,
+                unit,
+                category: v
+                    .category
+                    .clone()
+                    .or_else(|| reference.map(|r| r.category.clone()))
+                    .unwrap_or_default(),
+                short_name: v.short_name.clone(),
+                long_name: v.long_name.clone(),
+                derived: false,
+            }
+        })
+        .collect();
+
+    let date = scan
+        .date
+        .clone()
+        .filter(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+        .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+
+    BloodEntryInput {
+        date,
+        lab_name: scan.lab_name.clone().filter(|l| !l.trim().is_empty()),
+        notes: None,
+        values,
+    }
+}
+
+pub fn mime_type_for(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "webp" => Some("image/webp"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+fn scan_error_message(e: &ApiError) -> String {
+    match e {
+        ApiError::RateLimited { .. } => e.to_string(),
+        ApiError::Validation(msg) => format!("Der Befund konnte nicht ausgewertet werden: {msg}"),
+        _ => format!("Fehler: {e}"),
+    }
+}
+
+fn build_progress_dialog(file_name: &str) -> adw::Dialog {
+    let dialog = adw::Dialog::new();
+    dialog.set_title("Befund scannen");
+    dialog.set_content_width(360);
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    vbox.set_margin_top(24);
+    vbox.set_margin_bottom(24);
+    vbox.set_margin_start(24);
+    vbox.set_margin_end(24);
+
+    let spinner = gtk4::Spinner::new();
+    spinner.set_size_request(48, 48);
+    spinner.start();
+
+    let label = gtk4::Label::new(Some("Die KI liest deinen Befund.\nDas kann einige Sekunden dauern."));
+    label.set_justify(gtk4::Justification::Center);
+    label.set_wrap(true);
+
+    let file_label = gtk4::Label::new(Some(file_name));
+    file_label.add_css_class("caption");
+    file_label.add_css_class("dim-label");
+    file_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+
+    vbox.append(&spinner);
+    vbox.append(&label);
+    vbox.append(&file_label);
+
+    toolbar_view.set_content(Some(&vbox));
+    dialog.set_child(Some(&toolbar_view));
+    dialog
+}
+
+fn show_scan_error(window: &gtk4::Window, message: &str) {
+    let alert = adw::AlertDialog::new(Some("Scan fehlgeschlagen"), Some(message));
+    alert.add_response("ok", "OK");
+    alert.set_default_response(Some("ok"));
+    alert.present(Some(window));
+}
@@ -2,10 +2,15 @@ use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
 use glib::clone;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
-use crate::api::ApiClient;
-use crate::config::{save_config, Config};
+use crate::api::{types::EvaluationMode, ApiClient};
+use crate::config::{save_config, Config, Profile, DEFAULT_PROFILE_NAME};
 use crate::state::spawn_task;
+use crate::units::UnitSystem;
+use crate::ui::api_tokens::build_tokens_page;
+use crate::ui::passphrase_dialog::with_token_store;
 
 pub fn show_settings_window(
     parent: &adw::ApplicationWindow,
@@ -19,6 +24,51 @@ pub fn show_settings_window(
     page.set_title("Verbindung");
     page.set_icon_name(Some("network-wired-symbolic"));
 
+    // Profiles are edited on a copy and only written on "Speichern"
+    let mut draft = config.clone();
+    if draft.profiles.is_empty() {
+        draft.profiles.push(Profile {
+            name: DEFAULT_PROFILE_NAME.to_string(),
+            ..Profile::default()
+        });
+    }
+    let current_index = draft
+        .profiles
+        .iter()
+        .position(|p| Some(p) == config.active())
+        .unwrap_or(0);
+    let draft = Rc::new(RefCell::new(draft));
+    let current = Rc::new(Cell::new(current_index));
+    // Set while the combo model is replaced, so the selection handler ignores it
+    let updating = Rc::new(Cell::new(false));
+
+    let profile_group = adw::PreferencesGroup::new();
+    profile_group.set_title("Profil");
+    profile_group.set_description(Some(
+        "Jedes Profil verbindet sich mit einem eigenen Server oder Konto.",
+    ));
+
+    let profile_actions = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+    let add_btn = gtk4::Button::from_icon_name("list-add-symbolic");
+    add_btn.add_css_class("flat");
+    add_btn.set_tooltip_text(Some("Profil hinzufügen"));
+    let remove_btn = gtk4::Button::from_icon_name("user-trash-symbolic");
+    remove_btn.add_css_class("flat");
+    remove_btn.set_tooltip_text(Some("Profil löschen"));
+    profile_actions.append(&add_btn);
+    profile_actions.append(&remove_btn);
+    profile_group.set_header_suffix(Some(&profile_actions));
+
+    let profile_row = adw::ComboRow::new();
+    profile_row.set_title("Profil");
+    profile_group.add(&profile_row);
+
+    let name_row = adw::EntryRow::new();
+    name_row.set_title("Name");
+    profile_group.add(&name_row);
+
+    page.add(&profile_group);
+
     let group = adw::PreferencesGroup::new();
     group.set_title("Server");
     group.set_description(Some(
@@ -27,17 +77,150 @@ pub fn show_settings_window(
 
     let url_row = adw::EntryRow::new();
     url_row.set_title("Server-URL");
-    url_row.set_text(&config.server_url);
     url_row.set_input_hints(gtk4::InputHints::NO_SPELLCHECK | gtk4::InputHints::LOWERCASE);
     group.add(&url_row);
 
     let token_row = adw::PasswordEntryRow::new();
     token_row.set_title("API-Token");
-    token_row.set_text(&config.api_token);
     group.add(&token_row);
 
+    // Writes the form into the draft profile at `current`
+    let store_fields = {
+        let draft = draft.clone();
+        let current = current.clone();
+        let name_row = name_row.clone();
+        let url_row = url_row.clone();
+        let token_row = token_row.clone();
+        Rc::new(move || {
+            if let Some(profile) = draft.borrow_mut().profiles.get_mut(current.get()) {
+                profile.name = name_row.text().trim().to_string();
+                profile.server_url = url_row.text().trim().to_string();
+                profile.api_token = token_row.text().trim().to_string();
+            }
+        })
+    };
+
+    // Shows the draft profile at `current` in the form
+    let load_fields = {
+        let draft = draft.clone();
+        let current = current.clone();
+        let name_row = name_row.clone();
+        let url_row = url_row.clone();
+        let token_row = token_row.clone();
+        let remove_btn = remove_btn.clone();
+        Rc::new(move || {
+            let draft = draft.borrow();
+            if let Some(profile) = draft.profiles.get(current.get()) {
+                name_row.set_text(&profile.name);
+                url_row.set_text(&profile.server_url);
+                token_row.set_text(&profile.api_token);
+            }
+            remove_btn.set_sensitive(draft.profiles.len() > 1);
+        })
+    };
+
+    let refresh_profiles = {
+        let draft = draft.clone();
+        let current = current.clone();
+        let updating = updating.clone();
+        let profile_row = profile_row.clone();
+        Rc::new(move || {
+            let names: Vec<String> = draft
+                .borrow()
+                .profiles
+                .iter()
+                .map(|p| if p.name.is_empty() { "(ohne Namen)".to_string() } else { p.name.clone() })
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            updating.set(true);
+            profile_row.set_model(Some(&gtk4::StringList::new(&names)));
+            profile_row.set_selected(current.get() as u32);
+            updating.set(false);
+        })
+    };
+
+    refresh_profiles();
+    load_fields();
+
+    profile_row.connect_selected_notify({
+        let current = current.clone();
+        let updating = updating.clone();
+        let store_fields = store_fields.clone();
+        let load_fields = load_fields.clone();
+        let refresh_profiles = refresh_profiles.clone();
+        move |row| {
+            if updating.get() || row.selected() == gtk4::INVALID_LIST_POSITION {
+                return;
+            }
+            store_fields();
+            current.set(row.selected() as usize);
+            // Picks up a renamed profile
+            refresh_profiles();
+            load_fields();
+        }
+    });
+
+    add_btn.connect_clicked({
+        let draft = draft.clone();
+        let current = current.clone();
+        let store_fields = store_fields.clone();
+        let load_fields = load_fields.clone();
+        let refresh_profiles = refresh_profiles.clone();
+        move |_| {
+            store_fields();
+            let index = {
+                let mut draft = draft.borrow_mut();
+                let name = new_profile_name(&draft);
+                draft.profiles.push(Profile { name, ..Profile::default() });
+                draft.profiles.len() - 1
+            };
+            current.set(index);
+            refresh_profiles();
+            load_fields();
+        }
+    });
+
+    remove_btn.connect_clicked({
+        let draft = draft.clone();
+        let current = current.clone();
+        let load_fields = load_fields.clone();
+        let refresh_profiles = refresh_profiles.clone();
+        move |_| {
+            {
+                let mut draft = draft.borrow_mut();
+                if draft.profiles.len() <= 1 {
+                    return;
+                }
+                draft.remove_profile(current.get());
+                current.set(current.get().min(draft.profiles.len() - 1));
+            }
+            refresh_profiles();
+            load_fields();
+        }
+    });
+
     page.add(&group);
 
+    let display_group = adw::PreferencesGroup::new();
+    display_group.set_title("Darstellung");
+    let units_row = adw::ComboRow::new();
+    units_row.set_title("Einheiten");
+    units_row.set_subtitle("Werte in anderen Einheiten werden umgerechnet");
+    let labels: Vec<&str> = UnitSystem::ALL.iter().map(|u| u.label()).collect();
+    units_row.set_model(Some(&gtk4::StringList::new(&labels)));
+    let units_index = UnitSystem::ALL.iter().position(|u| *u == config.units).unwrap_or(0);
+    units_row.set_selected(units_index as u32);
+    display_group.add(&units_row);
+    let evaluation_row = adw::ComboRow::new();
+    evaluation_row.set_title("Bewertung");
+    evaluation_row.set_subtitle("Optimalbereiche gelten nur für Werte, die einen haben");
+    let labels: Vec<&str> = EvaluationMode::ALL.iter().map(|m| m.label()).collect();
+    evaluation_row.set_model(Some(&gtk4::StringList::new(&labels)));
+    let evaluation_index = EvaluationMode::ALL.iter().position(|m| *m == config.evaluation).unwrap_or(0);
+    evaluation_row.set_selected(evaluation_index as u32);
+    display_group.add(&evaluation_row);
+    page.add(&display_group);
+
     let actions_group = adw::PreferencesGroup::new();
     actions_group.set_title("Aktionen");
 
@@ -62,6 +245,14 @@ pub fn show_settings_window(
     page.add(&actions_group);
     window.add(&page);
 
+    // Tokens of the account the app is currently logged in to
+    if let Some(profile) = config.active().filter(|p| p.is_configured()) {
+        let client = ApiClient::with_options(profile.server_url.clone(), profile.api_token.clone(), config.network);
+        if let Ok(client) = client {
+            window.add(&build_tokens_page(&window, client));
+        }
+    }
+
     // Connection test
     {
         let url_row = url_row.clone();
@@ -103,25 +294,77 @@ pub fn show_settings_window(
 
     // Save
     {
-        let url_row = url_row.clone();
-        let token_row = token_row.clone();
+        let draft = draft.clone();
+        let current = current.clone();
         let window_clone = window.clone();
+        let on_saved = Rc::new(on_saved);
 
         save_btn.connect_clicked(move |_| {
-            let config = Config {
-                server_url: url_row.text().to_string().trim().to_string(),
-                api_token: token_row.text().to_string().trim().to_string(),
-            };
+            store_fields();
 
-            if let Err(e) = save_config(&config) {
-                eprintln!("Failed to save config: {e}");
+            let mut config = draft.borrow().clone();
+            if let Err(msg) = validate_profiles(&config) {
+                window_clone.add_toast(adw::Toast::new(msg));
                 return;
             }
+            config.active_profile = config.profiles[current.get()].name.clone();
+            config.units = UnitSystem::ALL
+                .get(units_row.selected() as usize)
+                .copied()
+                .unwrap_or_default();
+            config.evaluation = EvaluationMode::ALL
+                .get(evaluation_row.selected() as usize)
+                .copied()
+                .unwrap_or_default();
+
+            let window = window_clone.clone();
+            let on_saved = on_saved.clone();
+            with_token_store(&window_clone, &config, clone!(#[strong] config, move || {
+                let mut config = config.clone();
+                let (tx, rx) = async_channel::bounded::<(Config, anyhow::Result<()>)>(1);
+                spawn_task(async move {
+                    let result = save_config(&mut config).await;
+                    tx.send((config, result)).await.ok();
+                });
+
+                let window = window.clone();
+                let on_saved = on_saved.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    let Ok((config, result)) = rx.recv().await else { return };
+                    if let Err(e) = result {
+                        eprintln!("Failed to save config: {e}");
+                        window.add_toast(adw::Toast::new("Einstellungen konnten nicht gespeichert werden."));
+                        return;
+                    }
 
-            on_saved(config);
-            window_clone.close();
+                    on_saved(config);
+                    window.close();
+                });
+            }));
         });
     }
 
     window.present(Some(parent));
 }
+
+fn new_profile_name(config: &Config) -> String {
+    (2..)
+        .map(|n| format!("Profil {n}"))
+        .find(|name| config.profile(name).is_none())
+        .unwrap_or_default()
+}
+
+pub fn validate_profiles(config: &Config) -> Result<(), &'static str> {
+    for (i, profile) in config.profiles.iter().enumerate() {
+        if profile.name.is_empty() {
+            return Err("Jedes Profil braucht einen Namen.");
+        }
+        if config.profiles[..i].iter().any(|p| p.name == profile.name) {
+            return Err("Profilnamen müssen eindeutig sein.");
+        }
+        if !profile.is_configured() {
+            return Err("Bitte Server-URL und API-Token für jedes Profil eingeben.");
+        }
+    }
+    Ok(())
+}
@@ -2,15 +2,18 @@ use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
 use glib::clone;
+use std::rc::Rc;
 
 use crate::api::ApiClient;
 use crate::config::{save_config, Config};
 use crate::state::spawn_task;
+use crate::ui::passphrase_dialog::with_token_store;
 
+/// Asks for server and token of the active profile of `config`. Other
+/// profiles and settings are kept when saving.
 pub fn show_setup_dialog(
     parent: &adw::ApplicationWindow,
-    initial_url: &str,
-    initial_token: &str,
+    config: Config,
     on_saved: impl Fn(Config) + 'static,
 ) {
     let dialog = adw::Dialog::new();
@@ -51,13 +54,14 @@ pub fn show_setup_dialog(
 
     let url_row = adw::EntryRow::new();
     url_row.set_title("Server-URL");
-    url_row.set_text(initial_url);
+    let active = config.active();
+    url_row.set_text(active.map_or("", |p| p.server_url.as_str()));
     url_row.set_input_hints(gtk4::InputHints::NO_SPELLCHECK | gtk4::InputHints::LOWERCASE);
     prefs_group.add(&url_row);
 
     let token_row = adw::PasswordEntryRow::new();
     token_row.set_title("API-Token");
-    token_row.set_text(initial_token);
+    token_row.set_text(active.map_or("", |p| p.api_token.as_str()));
     prefs_group.add(&token_row);
 
     let status_label = gtk4::Label::new(None);
@@ -144,6 +148,7 @@ pub fn show_setup_dialog(
         let token_row = token_row.clone();
         let dialog_clone = dialog.clone();
         let status_label = status_label.clone();
+        let on_saved = Rc::new(on_saved);
 
         save_btn.connect_clicked(move |_| {
             let url = url_row.text().to_string().trim().to_string();
@@ -158,18 +163,37 @@ pub fn show_setup_dialog(
                 return;
             }
 
-            let config = Config { server_url: url, api_token: token };
-
-            if let Err(e) = save_config(&config) {
-                status_label.set_markup(&format!(
-                    "<span foreground='red'>Fehler beim Speichern: {}</span>",
-                    glib::markup_escape_text(&e.to_string())
-                ));
-                return;
-            }
+            let mut config = config.clone();
+            config.set_active_server(url, token);
+
+            let status_label = status_label.clone();
+            let dialog = dialog_clone.clone();
+            let on_saved = on_saved.clone();
+            with_token_store(&dialog_clone, &config, clone!(#[strong] config, move || {
+                let mut config = config.clone();
+                let (tx, rx) = async_channel::bounded::<(Config, anyhow::Result<()>)>(1);
+                spawn_task(async move {
+                    let result = save_config(&mut config).await;
+                    tx.send((config, result)).await.ok();
+                });
+
+                let status_label = status_label.clone();
+                let dialog = dialog.clone();
+                let on_saved = on_saved.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    let Ok((config, result)) = rx.recv().await else { return };
+                    if let Err(e) = result {
+                        status_label.set_markup(&format!(
+                            "<span foreground='red'>Fehler beim Speichern: {}</span>",
+                            glib::markup_escape_text(&e.to_string())
+                        ));
+                        return;
+                    }
 
-            on_saved(config);
-            dialog_clone.close();
+                    on_saved(config);
+                    dialog.close();
+                });
+            }));
         });
     }
 
@@ -0,0 +1,256 @@
+use gtk4::prelude::*;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::api::{types::*, ApiError};
+use crate::state::{spawn_task, AppStore};
+use crate::ui::error_actions::error_toast;
+use crate::ui::received_shares::format_share_date;
+
+/// Choices for how long a new share is valid, in days.
+const EXPIRY_OPTIONS: &[(&str, Option<i64>)] = &[
+    ("Unbegrenzt", None),
+    ("1 Tag", Some(1)),
+    ("1 Woche", Some(7)),
+    ("1 Monat", Some(30)),
+    ("1 Jahr", Some(365)),
+];
+
+/// Expiry timestamp for a share valid `days` from `now`, as the API expects it.
+pub fn share_expires_at(days: Option<i64>, now: DateTime<Utc>) -> Option<String> {
+    days.map(|d| (now + Duration::days(d)).to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// "Freigaben" page: share my values with another user, list who has
+/// access and revoke it. Needs a server connection.
+pub fn build_shares_page(store: &AppStore, toast_overlay: &adw::ToastOverlay) -> adw::NavigationPage {
+    let page = adw::NavigationPage::new(&gtk4::Label::new(None), "Freigaben");
+    let prefs = adw::PreferencesPage::new();
+
+    // New share
+    let create_group = adw::PreferencesGroup::new();
+    create_group.set_title("Neue Freigabe");
+    create_group.set_description(Some(
+        "Die Person kann deine Blutwerte ansehen, aber nicht ändern. \
+         Sie braucht ein Konto auf diesem Server.",
+    ));
+
+    let email_row = adw::EntryRow::new();
+    email_row.set_title("E-Mail-Adresse");
+    email_row.set_input_purpose(gtk4::InputPurpose::Email);
+    email_row.set_input_hints(gtk4::InputHints::NO_SPELLCHECK | gtk4::InputHints::LOWERCASE);
+    create_group.add(&email_row);
+
+    let expiry_row = adw::ComboRow::new();
+    expiry_row.set_title("Gültig");
+    let labels: Vec<&str> = EXPIRY_OPTIONS.iter().map(|(label, _)| *label).collect();
+    expiry_row.set_model(Some(&gtk4::StringList::new(&labels)));
+    create_group.add(&expiry_row);
+
+    let share_btn = adw::ButtonRow::new();
+    share_btn.set_title("Freigeben");
+    share_btn.set_start_icon_name(Some("emblem-shared-symbolic"));
+    share_btn.add_css_class("suggested-action");
+    create_group.add(&share_btn);
+
+    prefs.add(&create_group);
+
+    // Current shares
+    let list_group = adw::PreferencesGroup::new();
+    list_group.set_title("Zugriff haben");
+
+    let list = gtk4::ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    let placeholder = gtk4::Label::new(Some("Du teilst deine Werte mit niemandem."));
+    placeholder.add_css_class("dim-label");
+    placeholder.set_margin_top(12);
+    placeholder.set_margin_bottom(12);
+    list.set_placeholder(Some(&placeholder));
+    list_group.add(&list);
+
+    prefs.add(&list_group);
+    page.set_child(Some(&prefs));
+
+    let view = SharesView {
+        store: store.clone(),
+        list,
+        create_group: create_group.clone(),
+        list_group,
+        toast_overlay: toast_overlay.clone(),
+    };
+    view.reload();
+
+    // Create
+    share_btn.connect_activated(clone!(#[strong] view, #[weak] email_row, #[weak] expiry_row, move |btn| {
+        let email = email_row.text().trim().to_lowercase();
+        if !email.contains('@') {
+            view.toast("Bitte eine gültige E-Mail-Adresse eingeben.");
+            return;
+        }
+        let Some(client) = view.store.client() else { return };
+
+        let days = EXPIRY_OPTIONS
+            .get(expiry_row.selected() as usize)
+            .and_then(|(_, days)| *days);
+        let input = ShareInput {
+            email: email.clone(),
+            expires_at: share_expires_at(days, Utc::now()),
+        };
+        btn.set_sensitive(false);
+
+        let (tx, rx) = async_channel::bounded::<Result<Share, ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.create_share(&input).await).await.ok();
+        });
+
+        let view = view.clone();
+        glib::MainContext::default().spawn_local(clone!(#[weak] btn, #[weak] email_row, async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(_) => {
+                        email_row.set_text("");
+                        view.toast(&format!("Freigabe für {email} erstellt"));
+                        view.reload();
+                    }
+                    Err(e) => view.error("Freigabe fehlgeschlagen", e),
+                }
+                btn.set_sensitive(true);
+            }
+        }));
+    }));
+
+    // A new session (reconnect, other profile) reloads the list
+    let seen_generation = Rc::new(Cell::new(store.generation()));
+    store.connect_changed_for(&page, clone!(#[strong] view, move |store| {
+        if store.generation() != seen_generation.get() {
+            seen_generation.set(store.generation());
+            view.reload();
+        }
+    }));
+
+    page
+}
+
+#[derive(Clone)]
+struct SharesView {
+    store: AppStore,
+    list: gtk4::ListBox,
+    create_group: adw::PreferencesGroup,
+    list_group: adw::PreferencesGroup,
+    toast_overlay: adw::ToastOverlay,
+}
+
+impl SharesView {
+    fn reload(&self) {
+        self.list.remove_all();
+
+        let client = self.store.client();
+        self.create_group.set_sensitive(client.is_some());
+        let Some(client) = client else {
+            self.list_group.set_description(Some("Freigaben sind offline nicht verfügbar."));
+            return;
+        };
+        self.list_group.set_description(None);
+
+        let (tx, rx) = async_channel::bounded::<Result<Vec<Share>, ApiError>>(1);
+        spawn_task(async move {
+            tx.send(client.get_given_shares().await).await.ok();
+        });
+
+        let view = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            if let Ok(result) = rx.recv().await {
+                match result {
+                    Ok(shares) => view.fill(&shares),
+                    Err(e) => view.error("Freigaben konnten nicht geladen werden", e),
+                }
+            }
+        });
+    }
+
+    fn fill(&self, shares: &[Share]) {
+        self.list.remove_all();
+        for share in shares {
+            let row = adw::ActionRow::new();
+            row.set_title(&glib::markup_escape_text(&share.shared_with_email));
+            row.set_subtitle(&share_subtitle(share));
+
+            let revoke_btn = gtk4::Button::from_icon_name("user-trash-symbolic");
+            revoke_btn.add_css_class("flat");
+            revoke_btn.set_valign(gtk4::Align::Center);
+            revoke_btn.set_tooltip_text(Some("Freigabe widerrufen"));
+            let view = self.clone();
+            let share = share.clone();
+            revoke_btn.connect_clicked(move |btn| view.confirm_revoke(btn, &share));
+            row.add_suffix(&revoke_btn);
+
+            self.list.append(&row);
+        }
+    }
+
+    fn confirm_revoke(&self, parent: &gtk4::Button, share: &Share) {
+        let alert = adw::AlertDialog::new(
+            Some("Freigabe widerrufen?"),
+            Some(&format!(
+                "{} verliert sofort den Zugriff auf deine Blutwerte.",
+                share.shared_with_email
+            )),
+        );
+        alert.add_response("cancel", "Abbrechen");
+        alert.add_response("revoke", "Widerrufen");
+        alert.set_response_appearance("revoke", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+
+        let view = self.clone();
+        let id = share.id.clone();
+        alert.connect_response(None, move |_, response| {
+            if response != "revoke" {
+                return;
+            }
+            let Some(client) = view.store.client() else { return };
+
+            let id = id.clone();
+            let (tx, rx) = async_channel::bounded::<Result<(), ApiError>>(1);
+            spawn_task(async move {
+                tx.send(client.delete_share(&id).await).await.ok();
+            });
+
+            let view = view.clone();
+            glib::MainContext::default().spawn_local(async move {
+                if let Ok(result) = rx.recv().await {
+                    match result {
+                        Ok(()) => view.toast("Freigabe widerrufen"),
+                        Err(e) => view.error("Widerrufen fehlgeschlagen", e),
+                    }
+                    view.reload();
+                }
+            });
+        });
+
+        alert.present(Some(parent));
+    }
+
+    fn toast(&self, message: &str) {
+        let toast = adw::Toast::new(message);
+        toast.set_timeout(3);
+        self.toast_overlay.add_toast(toast);
+    }
+
+    fn error(&self, title: &str, error: ApiError) {
+        self.toast_overlay.add_toast(error_toast(title, &anyhow::Error::new(error)));
+    }
+}
+
+fn share_subtitle(share: &Share) -> String {
+    let since = format_share_date(&share.created_at).unwrap_or_default();
+    match share.expires_at.as_deref().and_then(format_share_date) {
+        Some(until) => format!("Seit {since} · bis {until}"),
+        None => format!("Seit {since} · unbegrenzt"),
+    }
+}
@@ -1,27 +1,238 @@
 use cairo::Context;
 use crate::api::types::*;
 
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 16.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+
+/// Radius around a point in which a click hits it, in pixels.
+pub const HIT_RADIUS: f64 = 10.0;
+/// Narrowest zoom: two neighbouring measurements fill the plot.
+const MIN_SPAN: f64 = 1.0;
+
+/// Visible part of the time axis, in point indexes (fractional while
+/// zoomed or panned).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Viewport {
+    /// All `len` points.
+    pub fn full(len: usize) -> Self {
+        Self { start: 0.0, end: len.saturating_sub(1) as f64 }
+    }
+
+    pub fn span(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// Scaled by `factor` (below 1 zooms in) around the index `anchor`,
+    /// which stays where it is on screen.
+    pub fn zoom(&self, factor: f64, anchor: f64, len: usize) -> Self {
+        let full = Self::full(len);
+        if full.span() <= 0.0 || !factor.is_finite() || factor <= 0.0 {
+            return full;
+        }
+        let span = (self.span() * factor).clamp(MIN_SPAN.min(full.span()), full.span());
+        let anchor = anchor.clamp(self.start, self.end);
+        let frac = if self.span() > 0.0 { (anchor - self.start) / self.span() } else { 0.5 };
+        let start = anchor - frac * span;
+        Self { start, end: start + span }.clamped(len)
+    }
+
+    /// Moved by `delta` indexes, without leaving the data.
+    pub fn pan(&self, delta: f64, len: usize) -> Self {
+        Self { start: self.start + delta, end: self.end + delta }.clamped(len)
+    }
+
+    fn clamped(self, len: usize) -> Self {
+        let full = Self::full(len);
+        let span = self.span().clamp(0.0, full.span());
+        let start = self.start.clamp(full.start, full.end - span);
+        Self { start, end: start + span }
+    }
+}
+
+/// Interaction state of a chart: zoom, hovered and selected point. Point
+/// indexes refer to the history passed to [`build_chart`].
+#[derive(Debug, Clone, Default)]
+pub struct ChartState {
+    /// `None` shows all points
+    pub viewport: Option<Viewport>,
+    pub hovered: Option<usize>,
+    pub selected: Option<usize>,
+}
+
+impl ChartState {
+    pub fn is_zoomed(&self, len: usize) -> bool {
+        self.viewport.is_some_and(|v| v != Viewport::full(len))
+    }
+}
+
+/// Screen geometry of a chart, shared by drawing and hit-testing.
+#[derive(Debug, Clone)]
+pub struct ChartLayout {
+    pub plot_x: f64,
+    pub plot_y: f64,
+    pub plot_w: f64,
+    pub plot_h: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+    pub viewport: Viewport,
+    /// Normal range of each point; it can change with age
+    pub ranges: Vec<(Option<f64>, Option<f64>)>,
+    len: usize,
+}
+
+impl ChartLayout {
+    /// `None` if there are no points or no room to draw them.
+    pub fn new(
+        width: f64,
+        height: f64,
+        history: &[ValueHistoryPoint],
+        ref_val: Option<&ReferenceValue>,
+        evaluation: &Evaluation,
+        viewport: Option<Viewport>,
+    ) -> Option<Self> {
+        let plot_w = width - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_h = height - MARGIN_TOP - MARGIN_BOTTOM;
+        if plot_w <= 0.0 || plot_h <= 0.0 || history.is_empty() {
+            return None;
+        }
+
+        // Compute Y domain
+        let data_min = history.iter().map(|h| h.value).fold(f64::INFINITY, f64::min);
+        let data_max = history.iter().map(|h| h.value).fold(f64::NEG_INFINITY, f64::max);
+
+        let ranges: Vec<(Option<f64>, Option<f64>)> = history
+            .iter()
+            .map(|p| ref_val.map(|r| evaluation.range(r, &p.date)).unwrap_or((None, None)))
+            .collect();
+
+        let y_min_raw = ranges
+            .iter()
+            .map(|(mn, _)| mn.unwrap_or(data_min))
+            .chain([data_min, ref_val.and_then(|r| r.critical_low).unwrap_or(data_min)])
+            .fold(f64::INFINITY, f64::min);
+
+        let y_max_raw = ranges
+            .iter()
+            .map(|(_, mx)| mx.unwrap_or(data_max))
+            .chain([data_max, ref_val.and_then(|r| r.critical_high).unwrap_or(data_max)])
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let pad = (y_max_raw - y_min_raw) * 0.15;
+        let pad = if pad < 0.001 { 1.0 } else { pad };
+
+        let len = history.len();
+        let viewport = viewport.map_or(Viewport::full(len), |v| v.clamped(len));
+
+        Some(Self {
+            plot_x: MARGIN_LEFT,
+            plot_y: MARGIN_TOP,
+            plot_w,
+            plot_h,
+            y_min: y_min_raw - pad,
+            y_max: y_max_raw + pad,
+            viewport,
+            ranges,
+            len,
+        })
+    }
+
+    /// Horizontal position of the point index `idx`.
+    pub fn to_x(&self, idx: f64) -> f64 {
+        if self.viewport.span() <= 0.0 {
+            self.plot_x + self.plot_w / 2.0
+        } else {
+            self.plot_x + (idx - self.viewport.start) / self.viewport.span() * self.plot_w
+        }
+    }
+
+    /// Point index (fractional) at the horizontal position `x`.
+    pub fn index_at(&self, x: f64) -> f64 {
+        self.viewport.start + (x - self.plot_x) * self.indexes_per_pixel()
+    }
+
+    pub fn to_y(&self, val: f64) -> f64 {
+        let frac = (val - self.y_min) / (self.y_max - self.y_min);
+        self.plot_y + (1.0 - frac) * self.plot_h
+    }
+
+    /// Indexes per pixel, to turn a drag distance into a pan.
+    pub fn indexes_per_pixel(&self) -> f64 {
+        self.viewport.span() / self.plot_w
+    }
+
+    pub fn in_plot(&self, x: f64, y: f64) -> bool {
+        (self.plot_x..=self.plot_x + self.plot_w).contains(&x)
+            && (self.plot_y..=self.plot_y + self.plot_h).contains(&y)
+    }
+
+    /// Whether point `idx` lies on the visible part of the time axis.
+    pub fn is_visible(&self, idx: usize) -> bool {
+        let x = self.to_x(idx as f64);
+        x >= self.plot_x - 0.5 && x <= self.plot_x + self.plot_w + 0.5
+    }
+
+    /// Horizontal extent of point `idx`'s range: halfway to its neighbours,
+    /// the outer ones up to the plot edges.
+    pub fn column(&self, idx: usize) -> (f64, f64) {
+        let x = self.to_x(idx as f64);
+        let left = if idx == 0 { self.plot_x.min(x) } else { (self.to_x(idx as f64 - 1.0) + x) / 2.0 };
+        let right = if idx + 1 == self.len {
+            (self.plot_x + self.plot_w).max(x)
+        } else {
+            (x + self.to_x(idx as f64 + 1.0)) / 2.0
+        };
+        (left, right)
+    }
+
+    /// The visible point horizontally closest to `(x, y)`, for hover
+    /// tooltips. `None` outside the plot.
+    pub fn nearest_point(&self, x: f64, y: f64) -> Option<usize> {
+        if !self.in_plot(x, y) {
+            return None;
+        }
+        (0..self.len)
+            .filter(|&i| self.is_visible(i))
+            .min_by(|&a, &b| {
+                let da = (self.to_x(a as f64) - x).abs();
+                let db = (self.to_x(b as f64) - x).abs();
+                da.total_cmp(&db)
+            })
+    }
+
+    /// The visible point drawn within `radius` pixels of `(x, y)`, the
+    /// closest if several are.
+    pub fn hit_point(&self, history: &[ValueHistoryPoint], x: f64, y: f64, radius: f64) -> Option<usize> {
+        history
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.is_visible(*i))
+            .map(|(i, p)| (i, (self.to_x(i as f64) - x).hypot(self.to_y(p.value) - y)))
+            .filter(|(_, d)| *d <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
 pub fn build_chart(
     cr: &Context,
     width: i32,
     height: i32,
     history: &[ValueHistoryPoint],
     ref_val: Option<&ReferenceValue>,
-    gender: Option<&str>,
+    evaluation: &Evaluation,
+    state: &ChartState,
 ) {
     let w = width as f64;
     let h = height as f64;
 
-    // Margins
-    let margin_left = 60.0;
-    let margin_right = 20.0;
-    let margin_top = 16.0;
-    let margin_bottom = 40.0;
-
-    let plot_w = w - margin_left - margin_right;
-    let plot_h = h - margin_top - margin_bottom;
-
-    if plot_w <= 0.0 || plot_h <= 0.0 || history.is_empty() {
+    let Some(layout) = ChartLayout::new(w, h, history, ref_val, evaluation, state.viewport) else {
         // Draw empty state
         cr.set_source_rgb(0.5, 0.5, 0.5);
         let _ = cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
@@ -31,52 +242,15 @@ pub fn build_chart(
         let _ = cr.move_to(w / 2.0 - text_w / 2.0, h / 2.0);
         let _ = cr.show_text(text);
         return;
-    }
-
-    // Compute Y domain
-    let values: Vec<f64> = history.iter().map(|h| h.value).collect();
-    let data_min = values.iter().cloned().fold(f64::INFINITY, f64::min);
-    let data_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-
-    let (ref_min, ref_max) = ref_val
-        .map(|r| get_effective_range(r, gender))
-        .unwrap_or((None, None));
-
-    let y_min_raw = [
-        data_min,
-        ref_min.unwrap_or(data_min),
-        ref_val.and_then(|r| r.critical_low).unwrap_or(data_min),
-    ]
-    .iter()
-    .cloned()
-    .fold(f64::INFINITY, f64::min);
-
-    let y_max_raw = [
-        data_max,
-        ref_max.unwrap_or(data_max),
-        ref_val.and_then(|r| r.critical_high).unwrap_or(data_max),
-    ]
-    .iter()
-    .cloned()
-    .fold(f64::NEG_INFINITY, f64::max);
-
-    let pad = (y_max_raw - y_min_raw) * 0.15;
-    let pad = if pad < 0.001 { 1.0 } else { pad };
-    let y_min = y_min_raw - pad;
-    let y_max = y_max_raw + pad;
-
-    let to_x = |idx: usize| -> f64 {
-        if history.len() == 1 {
-            margin_left + plot_w / 2.0
-        } else {
-            margin_left + idx as f64 / (history.len() - 1) as f64 * plot_w
-        }
     };
 
-    let to_y = |val: f64| -> f64 {
-        let frac = (val - y_min) / (y_max - y_min);
-        margin_top + (1.0 - frac) * plot_h
-    };
+    let margin_left = layout.plot_x;
+    let margin_top = layout.plot_y;
+    let plot_w = layout.plot_w;
+    let plot_h = layout.plot_h;
+    let (y_min, y_max) = (layout.y_min, layout.y_max);
+    let to_x = |idx: usize| layout.to_x(idx as f64);
+    let to_y = |val: f64| layout.to_y(val);
 
     // Background
     cr.set_source_rgb(1.0, 1.0, 1.0);
@@ -109,26 +283,50 @@ pub fn build_chart(
         let _ = cr.show_text(&label);
     }
 
-    // Reference range shaded area
-    if let (Some(mn), Some(mx)) = (ref_min, ref_max) {
-        let y1 = to_y(mx);
-        let y2 = to_y(mn);
-        cr.set_source_rgba(0.133, 0.773, 0.369, 0.12); // green
-        cr.rectangle(margin_left, y1, plot_w, y2 - y1);
-        let _ = cr.fill();
+    // Ranges stay inside the plot, also when zoomed
+    cr.save().ok();
+    cr.rectangle(margin_left, margin_top, plot_w, plot_h);
+    cr.clip();
+
+    for (i, range) in layout.ranges.iter().enumerate() {
+        let (x1, x2) = layout.column(i);
+
+        // Reference range shaded area
+        if let (Some(mn), Some(mx)) = *range {
+            let y1 = to_y(mx);
+            let y2 = to_y(mn);
+            cr.set_source_rgba(0.133, 0.773, 0.369, 0.12); // green
+            cr.rectangle(x1, y1, x2 - x1, y2 - y1);
+            let _ = cr.fill();
+
+            // Reference range borders
+            cr.set_source_rgba(0.133, 0.773, 0.369, 0.6);
+            cr.set_line_width(1.0);
+            cr.set_dash(&[4.0, 4.0], 0.0);
+            let _ = cr.move_to(x1, y1);
+            let _ = cr.line_to(x2, y1);
+            let _ = cr.stroke();
+            let _ = cr.move_to(x1, y2);
+            let _ = cr.line_to(x2, y2);
+            let _ = cr.stroke();
+            cr.set_dash(&[], 0.0);
+        }
 
-        // Reference range borders
-        cr.set_source_rgba(0.133, 0.773, 0.369, 0.6);
-        cr.set_line_width(1.0);
-        cr.set_dash(&[4.0, 4.0], 0.0);
-        let _ = cr.move_to(margin_left, y1);
-        let _ = cr.line_to(margin_left + plot_w, y1);
-        let _ = cr.stroke();
-        let _ = cr.move_to(margin_left, y2);
-        let _ = cr.line_to(margin_left + plot_w, y2);
-        let _ = cr.stroke();
-        cr.set_dash(&[], 0.0);
+        // Optimal range, stronger green inside the reference range
+        if evaluation.mode == EvaluationMode::Optimal {
+            if let Some(r) = ref_val.filter(|r| r.optimal_min.is_some() || r.optimal_max.is_some()) {
+                let (ref_min, ref_max) = *range;
+                let top = r.optimal_max.or(ref_max).unwrap_or(y_max).min(y_max);
+                let bottom = r.optimal_min.or(ref_min).unwrap_or(y_min).max(y_min);
+                let y1 = to_y(top);
+                let y2 = to_y(bottom);
+                cr.set_source_rgba(0.133, 0.773, 0.369, 0.22);
+                cr.rectangle(x1, y1, x2 - x1, y2 - y1);
+                let _ = cr.fill();
+            }
+        }
     }
+    cr.restore().ok();
 
     // Critical lines
     if let Some(r) = ref_val {
@@ -151,6 +349,11 @@ pub fn build_chart(
         cr.set_dash(&[], 0.0);
     }
 
+    // Line and points may reach a little beyond the plot edges
+    cr.save().ok();
+    cr.rectangle(margin_left - 8.0, 0.0, plot_w + 16.0, h);
+    cr.clip();
+
     // Data line
     cr.set_source_rgb(0.231, 0.510, 0.965); // blue-500
     cr.set_line_width(2.0);
@@ -167,36 +370,69 @@ pub fn build_chart(
     let _ = cr.stroke();
 
     // Data points (colored by status)
-    for (i, point) in history.iter().enumerate() {
+    for (i, point) in history.iter().enumerate().filter(|(i, _)| layout.is_visible(*i)) {
         let x = to_x(i);
         let y = to_y(point.value);
 
         let status = ref_val
-            .map(|r| get_value_status(point.value, r, gender))
+            .map(|r| evaluation.status(point.value, r, &point.date))
             .unwrap_or(ValueStatus::Unknown);
         let (r, g, b) = status.color();
+        let grow = if state.hovered == Some(i) || state.selected == Some(i) { 2.0 } else { 0.0 };
 
         // Outer white ring
         cr.set_source_rgb(1.0, 1.0, 1.0);
-        cr.arc(x, y, 6.0, 0.0, 2.0 * std::f64::consts::PI);
+        cr.arc(x, y, 6.0 + grow, 0.0, 2.0 * std::f64::consts::PI);
         let _ = cr.fill();
 
-        // Colored dot
+        // Colored dot, a ring for calculated values
         cr.set_source_rgb(r, g, b);
-        cr.arc(x, y, 5.0, 0.0, 2.0 * std::f64::consts::PI);
-        let _ = cr.fill();
+        if point.derived {
+            cr.set_line_width(2.0);
+            cr.arc(x, y, 4.0 + grow, 0.0, 2.0 * std::f64::consts::PI);
+            let _ = cr.stroke();
+        } else {
+            cr.arc(x, y, 5.0 + grow, 0.0, 2.0 * std::f64::consts::PI);
+            let _ = cr.fill();
+        }
+
+        // Selection ring
+        if state.selected == Some(i) {
+            cr.set_source_rgb(0.231, 0.510, 0.965);
+            cr.set_line_width(2.0);
+            cr.arc(x, y, 11.0, 0.0, 2.0 * std::f64::consts::PI);
+            let _ = cr.stroke();
+        }
+    }
+    cr.restore().ok();
+
+    let mut hints = Vec::new();
+    if history.iter().any(|p| p.derived) {
+        hints.push("○ berechnet");
+    }
+    if state.is_zoomed(history.len()) {
+        hints.push("Doppelklick: alles zeigen");
+    }
+    if !hints.is_empty() {
+        cr.set_source_rgb(0.4, 0.4, 0.4);
+        cr.set_font_size(10.0);
+        let text = hints.join(" · ");
+        let text_w = cr.text_extents(&text).map(|e| e.width()).unwrap_or(0.0);
+        let _ = cr.move_to(margin_left + plot_w - text_w - 4.0, margin_top + 12.0);
+        let _ = cr.show_text(&text);
     }
 
     // X axis date labels (rotated)
     cr.set_source_rgb(0.4, 0.4, 0.4);
     cr.set_font_size(9.0);
 
+    let visible: Vec<usize> = (0..history.len()).filter(|&i| layout.is_visible(i)).collect();
     let max_labels = 8;
-    let step = (history.len() / max_labels).max(1);
+    let step = (visible.len() / max_labels).max(1);
 
-    for (i, point) in history.iter().enumerate().step_by(step) {
+    for &i in visible.iter().step_by(step) {
         let x = to_x(i);
-        let label = format_date_short(&point.date);
+        let label = format_date_short(&history[i].date);
 
         cr.save().ok();
         let _ = cr.translate(x, margin_top + plot_h + 6.0);
@@ -225,3 +461,179 @@ fn format_date_short(date_str: &str) -> String {
         date_str.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 400 × 200 pixel plot at (60, 16)
+    const WIDTH: f64 = 480.0;
+    const HEIGHT: f64 = 256.0;
+
+    fn history(values: &[f64]) -> Vec<ValueHistoryPoint> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| ValueHistoryPoint {
+                date: format!("2024-01-{:02}", i % 28 + 1),
+                value,
+                unit: "mg/dL".into(),
+                entry_id: format!("e{i}"),
+                derived: false,
+            })
+            .collect()
+    }
+
+    fn layout(history: &[ValueHistoryPoint], viewport: Option<Viewport>) -> ChartLayout {
+        ChartLayout::new(WIDTH, HEIGHT, history, None, &Evaluation::default(), viewport).expect("layout")
+    }
+
+    fn assert_viewport(actual: Viewport, start: f64, end: f64) {
+        assert!(
+            (actual.start - start).abs() < 1e-9 && (actual.end - end).abs() < 1e-9,
+            "expected {start}..{end}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn hit_point_within_radius() {
+        let history = history(&[10.0, 20.0, 30.0]);
+        let layout = layout(&history, None);
+        let (x, y) = (layout.to_x(1.0), layout.to_y(20.0));
+
+        assert_eq!(layout.hit_point(&history, x, y, HIT_RADIUS), Some(1));
+        assert_eq!(layout.hit_point(&history, x + HIT_RADIUS - 0.5, y, HIT_RADIUS), Some(1));
+        assert_eq!(layout.hit_point(&history, x + 6.0, y - 6.0, HIT_RADIUS), Some(1));
+        assert_eq!(layout.hit_point(&history, x + 8.0, y - 8.0, HIT_RADIUS), None);
+        assert_eq!(layout.hit_point(&history, x, y + HIT_RADIUS + 0.5, HIT_RADIUS), None);
+    }
+
+    #[test]
+    fn nearest_of_overlapping_points_is_hit() {
+        // 4 pixels apart, so several points are within the radius
+        let history = history(&[10.0; 101]);
+        let layout = layout(&history, None);
+        let (x, y) = (layout.to_x(50.0), layout.to_y(10.0));
+
+        assert_eq!(layout.hit_point(&history, x + 1.5, y, HIT_RADIUS), Some(50));
+        assert_eq!(layout.hit_point(&history, x + 2.5, y, HIT_RADIUS), Some(51));
+        assert_eq!(layout.hit_point(&history, x - 3.0, y + 2.0, HIT_RADIUS), Some(49));
+    }
+
+    #[test]
+    fn points_outside_the_viewport_are_ignored() {
+        let history = history(&[10.0, 12.0, 14.0, 16.0, 18.0, 20.0, 22.0, 24.0, 26.0, 28.0]);
+        // Point 1 lies 2.7 pixels left of the plot
+        let layout = layout(&history, Some(Viewport { start: 1.02, end: 4.02 }));
+        assert!(!layout.is_visible(1));
+        assert!(layout.is_visible(2));
+        assert!(!layout.is_visible(5));
+
+        let y = layout.to_y(12.0);
+        assert!(layout.to_x(1.0) > layout.plot_x - HIT_RADIUS);
+        assert_eq!(layout.hit_point(&history, layout.plot_x + 1.0, y, HIT_RADIUS), None);
+        assert_eq!(layout.nearest_point(layout.plot_x + 1.0, y), Some(2));
+        assert_eq!(layout.nearest_point(layout.plot_x + layout.plot_w, y), Some(4));
+    }
+
+    #[test]
+    fn nearest_point_only_inside_the_plot() {
+        let history = history(&[10.0, 20.0, 30.0]);
+        let layout = layout(&history, None);
+        let (mid_x, mid_y) = (layout.plot_x + layout.plot_w / 2.0, layout.plot_y + layout.plot_h / 2.0);
+
+        assert_eq!(layout.nearest_point(mid_x, mid_y), Some(1));
+        assert_eq!(layout.nearest_point(layout.plot_x + 10.0, layout.plot_y), Some(0));
+        assert_eq!(layout.nearest_point(layout.plot_x - 1.0, mid_y), None);
+        assert_eq!(layout.nearest_point(layout.plot_x + layout.plot_w + 1.0, mid_y), None);
+        assert_eq!(layout.nearest_point(mid_x, layout.plot_y - 1.0), None);
+        assert_eq!(layout.nearest_point(mid_x, layout.plot_y + layout.plot_h + 1.0), None);
+    }
+
+    #[test]
+    fn zoom_keeps_the_anchor() {
+        let full = Viewport::full(11);
+        let zoomed = full.zoom(0.5, 4.0, 11);
+        assert_viewport(zoomed, 2.0, 7.0);
+
+        let frac = |v: Viewport, anchor: f64| (anchor - v.start) / v.span();
+        let again = zoomed.zoom(0.5, 6.0, 11);
+        assert!((frac(again, 6.0) - frac(zoomed, 6.0)).abs() < 1e-9);
+        assert!((again.span() - 2.5).abs() < 1e-9);
+
+        // An anchor outside the view is moved to its edge
+        assert_viewport(zoomed.zoom(0.5, 100.0, 11), 4.5, 7.0);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut viewport = Viewport::full(11);
+        for _ in 0..10 {
+            viewport = viewport.zoom(0.1, 5.0, 11);
+        }
+        assert_viewport(viewport, 4.5, 5.5);
+        assert!((viewport.span() - MIN_SPAN).abs() < 1e-9);
+
+        // Zooming out never goes beyond the data, even off-centre
+        assert_viewport(viewport.zoom(100.0, 5.0, 11), 0.0, 10.0);
+        assert_viewport(Viewport { start: 0.0, end: 4.0 }.zoom(2.0, 0.0, 11), 0.0, 8.0);
+        assert_viewport(Viewport { start: 6.0, end: 10.0 }.zoom(2.0, 10.0, 11), 2.0, 10.0);
+
+        // Invalid factors reset the zoom
+        assert_viewport(viewport.zoom(0.0, 5.0, 11), 0.0, 10.0);
+        assert_viewport(viewport.zoom(f64::NAN, 5.0, 11), 0.0, 10.0);
+    }
+
+    #[test]
+    fn pan_stops_at_the_data_edges() {
+        let viewport = Viewport { start: 2.0, end: 7.0 };
+        assert_viewport(viewport.pan(1.5, 11), 3.5, 8.5);
+        assert_viewport(viewport.pan(-10.0, 11), 0.0, 5.0);
+        assert_viewport(viewport.pan(100.0, 11), 5.0, 10.0);
+        assert_viewport(Viewport::full(11).pan(3.0, 11), 0.0, 10.0);
+    }
+
+    #[test]
+    fn stale_viewport_is_clamped_to_the_history() {
+        let history = history(&[1.0, 2.0, 3.0, 4.0]);
+        let layout = layout(&history, Some(Viewport { start: 5.0, end: 20.0 }));
+        assert_viewport(layout.viewport, 0.0, 3.0);
+
+        let state = ChartState { viewport: Some(Viewport::full(4)), ..Default::default() };
+        assert!(!state.is_zoomed(4));
+        assert!(state.is_zoomed(5));
+    }
+
+    #[test]
+    fn empty_history() {
+        assert_viewport(Viewport::full(0), 0.0, 0.0);
+        assert_viewport(Viewport::full(0).zoom(0.5, 0.0, 0), 0.0, 0.0);
+        assert_viewport(Viewport::full(0).pan(1.0, 0), 0.0, 0.0);
+        assert!(ChartLayout::new(WIDTH, HEIGHT, &[], None, &Evaluation::default(), None).is_none());
+        assert!(!ChartState::default().is_zoomed(0));
+    }
+
+    #[test]
+    fn single_point() {
+        let history = history(&[42.0]);
+        assert_viewport(Viewport::full(1).zoom(0.5, 0.0, 1), 0.0, 0.0);
+        assert_viewport(Viewport::full(1).pan(-1.0, 1), 0.0, 0.0);
+
+        let layout = layout(&history, Some(Viewport { start: 0.0, end: 3.0 }));
+        assert_viewport(layout.viewport, 0.0, 0.0);
+        let (x, y) = (layout.to_x(0.0), layout.to_y(42.0));
+        assert_eq!(x, layout.plot_x + layout.plot_w / 2.0);
+        assert!(y > layout.plot_y && y < layout.plot_y + layout.plot_h);
+        assert_eq!(layout.hit_point(&history, x, y, HIT_RADIUS), Some(0));
+        assert_eq!(layout.nearest_point(layout.plot_x + 1.0, y), Some(0));
+        assert_eq!(layout.column(0), (layout.plot_x, layout.plot_x + layout.plot_w));
+    }
+
+    #[test]
+    fn no_layout_without_room() {
+        let history = history(&[1.0, 2.0]);
+        let evaluation = Evaluation::default();
+        assert!(ChartLayout::new(MARGIN_LEFT + MARGIN_RIGHT, HEIGHT, &history, None, &evaluation, None).is_none());
+        assert!(ChartLayout::new(WIDTH, MARGIN_TOP + MARGIN_BOTTOM, &history, None, &evaluation, None).is_none());
+    }
+}
@@ -0,0 +1,465 @@
+//! Export of the value chart as PNG, SVG or PDF, e.g. to send it to a
+//! doctor. The image has a title, the unit and a legend of the reference
+//! lines, and renders through [`build_chart`] like the screen.
+
+use anyhow::{Context as _, Result};
+use cairo::Context;
+use gtk4::prelude::*;
+use gtk4::gio;
+use libadwaita::prelude::*;
+use libadwaita as adw;
+use glib::clone;
+use std::path::{Path, PathBuf};
+
+use crate::api::types::*;
+use super::chart::{build_chart, ChartState};
+use super::format_date;
+
+/// Points per centimetre
+const PT_PER_CM: f64 = 72.0 / 2.54;
+const HEADER_HEIGHT: f64 = 44.0;
+const FOOTER_HEIGHT: f64 = 26.0;
+const PADDING: f64 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Png, ExportFormat::Svg, ExportFormat::Pdf];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG-Bild",
+            ExportFormat::Svg => "SVG-Grafik",
+            ExportFormat::Pdf => "PDF-Dokument",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Svg => "image/svg+xml",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// Size of the exported image. Vector formats use the size only; PNG
+/// renders it at `dpi`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub width_cm: f64,
+    pub height_cm: f64,
+    pub dpi: f64,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { format: ExportFormat::Png, width_cm: 16.0, height_cm: 10.0, dpi: 150.0 }
+    }
+}
+
+impl ExportOptions {
+    /// Size in points (1/72 inch), the unit the chart is laid out in.
+    pub fn size_pt(&self) -> (f64, f64) {
+        (self.width_cm * PT_PER_CM, self.height_cm * PT_PER_CM)
+    }
+
+    /// Pixel size of a PNG.
+    pub fn size_px(&self) -> (i32, i32) {
+        let (w, h) = self.size_pt();
+        let scale = self.dpi / 72.0;
+        ((w * scale).round() as i32, (h * scale).round() as i32)
+    }
+}
+
+/// Everything that ends up in the exported image.
+#[derive(Debug, Clone)]
+pub struct ExportChart {
+    pub title: String,
+    pub unit: String,
+    pub history: Vec<ValueHistoryPoint>,
+    pub ref_val: Option<ReferenceValue>,
+    pub evaluation: Evaluation,
+    /// Zoom as on screen; hover and selection are left out
+    pub state: ChartState,
+}
+
+/// Draws the whole export, `width` × `height` in points.
+pub fn render_export(cr: &Context, width: f64, height: f64, chart: &ExportChart) {
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    let _ = cr.paint();
+
+    // Title and unit
+    cr.set_source_rgb(0.1, 0.1, 0.1);
+    cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+    cr.set_font_size(15.0);
+    cr.move_to(PADDING, PADDING + 14.0);
+    let _ = cr.show_text(&chart.title);
+
+    cr.set_source_rgb(0.4, 0.4, 0.4);
+    cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    cr.set_font_size(10.0);
+    let mut subtitle = Vec::new();
+    if !chart.unit.is_empty() {
+        subtitle.push(format!("Einheit: {}", chart.unit));
+    }
+    if let (Some(first), Some(last)) = (chart.history.first(), chart.history.last()) {
+        subtitle.push(format!("{} – {}", format_date(&first.date), format_date(&last.date)));
+    }
+    cr.move_to(PADDING, PADDING + 30.0);
+    let _ = cr.show_text(&subtitle.join(" · "));
+
+    // The chart, laid out in the remaining space
+    let chart_h = height - HEADER_HEIGHT - FOOTER_HEIGHT;
+    cr.save().ok();
+    cr.translate(0.0, HEADER_HEIGHT);
+    cr.rectangle(0.0, 0.0, width, chart_h);
+    cr.clip();
+    build_chart(
+        cr,
+        width as i32,
+        chart_h as i32,
+        &chart.history,
+        chart.ref_val.as_ref(),
+        &chart.evaluation,
+        &chart.state,
+    );
+    cr.restore().ok();
+
+    // Legend of the reference lines
+    let y = height - FOOTER_HEIGHT + 16.0;
+    let mut x = PADDING;
+    cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    cr.set_font_size(9.0);
+    for (color, dashed, text) in legend_items(chart) {
+        let (r, g, b, a) = color;
+        cr.set_source_rgba(r, g, b, a);
+        if dashed {
+            cr.set_line_width(1.5);
+            cr.set_dash(&[3.0, 3.0], 0.0);
+            cr.move_to(x, y - 3.0);
+            cr.line_to(x + 14.0, y - 3.0);
+            let _ = cr.stroke();
+            cr.set_dash(&[], 0.0);
+        } else {
+            cr.rectangle(x, y - 8.0, 14.0, 9.0);
+            let _ = cr.fill();
+        }
+        cr.set_source_rgb(0.3, 0.3, 0.3);
+        cr.move_to(x + 18.0, y);
+        let _ = cr.show_text(&text);
+        x += 18.0 + cr.text_extents(&text).map(|e| e.x_advance()).unwrap_or(0.0) + 14.0;
+    }
+}
+
+/// RGBA, dashed line instead of area, text.
+type LegendItem = ((f64, f64, f64, f64), bool, String);
+
+fn legend_items(chart: &ExportChart) -> Vec<LegendItem> {
+    let mut items = Vec::new();
+    let Some(r) = &chart.ref_val else { return items };
+    let unit = &chart.unit;
+
+    // The range at the last measurement; the chart shows each one's own
+    let date = chart.history.last().map_or("", |p| p.date.as_str());
+    let text = match chart.evaluation.range(r, date) {
+        (Some(mn), Some(mx)) => Some(format!("Referenzbereich {mn} – {mx} {unit}")),
+        (Some(mn), None) => Some(format!("Referenzbereich ≥ {mn} {unit}")),
+        (None, Some(mx)) => Some(format!("Referenzbereich ≤ {mx} {unit}")),
+        (None, None) => None,
+    };
+    if let Some(text) = text {
+        items.push(((0.133, 0.773, 0.369, 0.35), false, text));
+    }
+    if chart.evaluation.mode == EvaluationMode::Optimal {
+        if let (Some(mn), Some(mx)) = (r.optimal_min, r.optimal_max) {
+            items.push(((0.133, 0.773, 0.369, 0.6), false, format!("Optimalbereich {mn} – {mx} {unit}")));
+        }
+    }
+    let critical = match (r.critical_low, r.critical_high) {
+        (Some(cl), Some(ch)) => Some(format!("Kritisch ≤ {cl} / ≥ {ch} {unit}")),
+        (Some(cl), None) => Some(format!("Kritisch ≤ {cl} {unit}")),
+        (None, Some(ch)) => Some(format!("Kritisch ≥ {ch} {unit}")),
+        (None, None) => None,
+    };
+    if let Some(text) = critical {
+        items.push(((0.937, 0.267, 0.267, 0.8), true, text));
+    }
+    items
+}
+
+/// Renders `chart` to `path` in the format and size of `options`.
+pub fn export_chart(path: &Path, options: &ExportOptions, chart: &ExportChart) -> Result<()> {
+    let (w, h) = options.size_pt();
+    match options.format {
+        ExportFormat::Png => {
+            let (px_w, px_h) = options.size_px();
+            let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, px_w, px_h)
+                .context("Failed to create image surface")?;
+            let cr = Context::new(&surface).context("Failed to create drawing context")?;
+            cr.scale(px_w as f64 / w, px_h as f64 / h);
+            render_export(&cr, w, h, chart);
+            drop(cr);
+            let mut file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            surface.write_to_png(&mut file).context("Failed to write PNG")?;
+        }
+        ExportFormat::Svg => {
+            let surface = cairo::SvgSurface::new(w, h, Some(path)).context("Failed to create SVG surface")?;
+            render_to(&surface, w, h, chart)?;
+        }
+        ExportFormat::Pdf => {
+            let surface = cairo::PdfSurface::new(w, h, path).context("Failed to create PDF surface")?;
+            render_to(&surface, w, h, chart)?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders to a file-backed vector surface and finishes the file.
+fn render_to(surface: &cairo::Surface, w: f64, h: f64, chart: &ExportChart) -> Result<()> {
+    let cr = Context::new(surface).context("Failed to create drawing context")?;
+    render_export(&cr, w, h, chart);
+    drop(cr);
+    surface.finish();
+    surface.status().context("Failed to write file")
+}
+
+/// `path` with the extension of `format`, unless it already has it.
+pub fn with_extension(path: PathBuf, format: ExportFormat) -> PathBuf {
+    let has_it = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(format.extension()));
+    if has_it {
+        path
+    } else {
+        let mut name = path.into_os_string();
+        name.push(".");
+        name.push(format.extension());
+        PathBuf::from(name)
+    }
+}
+
+/// Asks for format and size, then for the file, and exports `chart`.
+pub fn show_export_dialog(parent: &impl IsA<gtk4::Widget>, chart: ExportChart) {
+    let alert = adw::AlertDialog::new(
+        Some("Diagramm exportieren"),
+        Some("Als Bild oder PDF speichern, z. B. für die Arztpraxis."),
+    );
+
+    let group = adw::PreferencesGroup::new();
+    let format_row = adw::ComboRow::new();
+    format_row.set_title("Format");
+    let labels: Vec<&str> = ExportFormat::ALL.iter().map(|f| f.label()).collect();
+    format_row.set_model(Some(&gtk4::StringList::new(&labels)));
+    group.add(&format_row);
+
+    let defaults = ExportOptions::default();
+    let width_row = adw::SpinRow::with_range(5.0, 50.0, 0.5);
+    width_row.set_title("Breite (cm)");
+    width_row.set_digits(1);
+    width_row.set_value(defaults.width_cm);
+    group.add(&width_row);
+
+    let height_row = adw::SpinRow::with_range(4.0, 40.0, 0.5);
+    height_row.set_title("Höhe (cm)");
+    height_row.set_digits(1);
+    height_row.set_value(defaults.height_cm);
+    group.add(&height_row);
+
+    let dpi_row = adw::SpinRow::with_range(72.0, 600.0, 1.0);
+    dpi_row.set_title("Auflösung (DPI)");
+    dpi_row.set_subtitle("Nur für PNG");
+    dpi_row.set_value(defaults.dpi);
+    group.add(&dpi_row);
+
+    format_row.connect_selected_notify(clone!(#[weak] dpi_row, move |row| {
+        dpi_row.set_sensitive(row.selected() == 0);
+    }));
+
+    alert.set_extra_child(Some(&group));
+    alert.add_response("cancel", "Abbrechen");
+    alert.add_response("export", "Speichern …");
+    alert.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("export"));
+    alert.set_close_response("cancel");
+
+    let Some(window) = parent.root().and_downcast::<gtk4::Window>() else {
+        return;
+    };
+    alert.connect_response(None, clone!(#[weak] window, move |_, response| {
+        if response != "export" {
+            return;
+        }
+        let options = ExportOptions {
+            format: ExportFormat::ALL.get(format_row.selected() as usize).copied().unwrap_or(ExportFormat::Png),
+            width_cm: width_row.value(),
+            height_cm: height_row.value(),
+            dpi: dpi_row.value(),
+        };
+        choose_file(&window, options, chart.clone());
+    }));
+
+    alert.present(Some(parent));
+}
+
+fn choose_file(window: &gtk4::Window, options: ExportOptions, chart: ExportChart) {
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some(options.format.label()));
+    filter.add_mime_type(options.format.mime_type());
+    let filters = gio::ListStore::new::<gtk4::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk4::FileDialog::new();
+    dialog.set_title("Diagramm speichern");
+    dialog.set_filters(Some(&filters));
+    dialog.set_default_filter(Some(&filter));
+    dialog.set_initial_name(Some(&format!("{}.{}", file_stem(&chart.title), options.format.extension())));
+
+    dialog.save(Some(window), gio::Cancellable::NONE, clone!(#[weak] window, move |result| {
+        let Ok(file) = result else { return };
+        let Some(path) = file.path() else { return };
+        let path = with_extension(path, options.format);
+        if let Err(e) = export_chart(&path, &options, &chart) {
+            let alert = adw::AlertDialog::new(Some("Export fehlgeschlagen"), Some(&format!("{e:#}")));
+            alert.add_response("ok", "OK");
+            alert.present(Some(&window));
+        }
+    }));
+}
+
+/// File name for a value name, without characters file systems reject.
+fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let stem = stem.trim_matches('_');
+    if stem.is_empty() { "diagramm".to_string() } else { stem.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::value_detail::chart::Viewport;
+
+    fn point(date: &str, value: f64) -> ValueHistoryPoint {
+        ValueHistoryPoint {
+            date: date.into(),
+            value,
+            unit: "ng/mL".into(),
+            entry_id: date.into(),
+            derived: false,
+        }
+    }
+
+    fn chart(history: Vec<ValueHistoryPoint>) -> ExportChart {
+        let reference_db: ReferenceDatabase =
+            serde_json::from_str(include_str!("../../../../data/reference_values.json")).expect("reference database");
+        ExportChart {
+            title: "Ferritin".into(),
+            unit: "ng/mL".into(),
+            history,
+            ref_val: reference_db.values.into_iter().find(|r| r.id == "ferritin"),
+            evaluation: Evaluation::new(Some("female".into()), Some("1980-05-01"), EvaluationMode::Reference),
+            state: ChartState::default(),
+        }
+    }
+
+    fn history() -> Vec<ValueHistoryPoint> {
+        vec![point("2023-02-01", 18.0), point("2023-09-12", 35.5), point("2024-04-30", 62.0)]
+    }
+
+    /// Exports in every format and checks the files.
+    fn export_all(chart: &ExportChart) {
+        let dir = tempfile::tempdir().expect("temp dir");
+        for format in ExportFormat::ALL {
+            let options = ExportOptions { format, width_cm: 12.0, height_cm: 8.0, dpi: 96.0 };
+            let path = dir.path().join(format!("diagramm.{}", format.extension()));
+            export_chart(&path, &options, chart).unwrap();
+
+            let content = std::fs::read(&path).unwrap();
+            assert!(!content.is_empty(), "{} is empty", path.display());
+            match format {
+                ExportFormat::Png => {
+                    assert!(content.starts_with(b"\x89PNG"));
+                    let image = cairo::ImageSurface::create_from_png(&mut content.as_slice()).unwrap();
+                    assert_eq!((image.width(), image.height()), options.size_px());
+                }
+                ExportFormat::Svg => assert!(String::from_utf8_lossy(&content).contains("<svg")),
+                ExportFormat::Pdf => assert!(content.starts_with(b"%PDF")),
+            }
+        }
+    }
+
+    #[test]
+    fn exports_every_format() {
+        export_all(&chart(history()));
+    }
+
+    #[test]
+    fn exports_zoomed_chart() {
+        let mut chart = chart(history());
+        chart.state.viewport = Some(Viewport { start: 0.5, end: 2.0 });
+        export_all(&chart);
+    }
+
+    #[test]
+    fn exports_empty_history() {
+        export_all(&chart(Vec::new()));
+        export_all(&ExportChart { ref_val: None, unit: String::new(), ..chart(Vec::new()) });
+    }
+
+    #[test]
+    fn png_size_follows_dpi() {
+        let options = ExportOptions { width_cm: 2.54, height_cm: 5.08, dpi: 300.0, ..Default::default() };
+        assert_eq!(options.size_px(), (300, 600));
+        let (w, h) = ExportOptions::default().size_pt();
+        assert!((w - 453.54).abs() < 0.01 && (h - 283.46).abs() < 0.01);
+    }
+
+    #[test]
+    fn unwritable_path_is_an_error() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let chart = chart(history());
+        for format in ExportFormat::ALL {
+            let options = ExportOptions { format, ..Default::default() };
+            let path = dir.path().join("fehlt").join(format!("diagramm.{}", format.extension()));
+            assert!(export_chart(&path, &options, &chart).is_err(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn legend_lists_reference_lines() {
+        let texts = |chart: &ExportChart| legend_items(chart).into_iter().map(|(_, _, text)| text).collect::<Vec<_>>();
+        let chart = chart(history());
+        let texts = texts(&chart);
+        assert!(texts[0].starts_with("Referenzbereich "), "{texts:?}");
+        assert!(texts.iter().all(|t| t.ends_with("ng/mL")), "{texts:?}");
+        assert!(legend_items(&ExportChart { ref_val: None, ..chart }).is_empty());
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(with_extension(PathBuf::from("/tmp/a"), ExportFormat::Svg), PathBuf::from("/tmp/a.svg"));
+        assert_eq!(with_extension(PathBuf::from("/tmp/a.PDF"), ExportFormat::Pdf), PathBuf::from("/tmp/a.PDF"));
+        assert_eq!(with_extension(PathBuf::from("/tmp/a.png"), ExportFormat::Pdf), PathBuf::from("/tmp/a.png.pdf"));
+        assert_eq!(file_stem("LDL/HDL"), "LDL_HDL");
+        assert_eq!(file_stem("Vitamin D (25-OH)"), "Vitamin_D__25-OH");
+        assert_eq!(file_stem("///"), "diagramm");
+    }
+}
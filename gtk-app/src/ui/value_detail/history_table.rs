@@ -1,24 +1,47 @@
 use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
+use std::collections::HashMap;
 
 use crate::api::types::*;
+use crate::ui::entry_editor::{show_entry_editor, EntryEditorContext};
 use super::format_date;
 
+/// The measurement list of a detail page, with its rows by entry id so
+/// the chart can point at them.
+#[derive(Clone)]
+pub struct HistoryTable {
+    pub list_box: gtk4::ListBox,
+    rows: HashMap<String, adw::ActionRow>,
+}
+
+impl HistoryTable {
+    /// Selects the row of `entry_id` and scrolls it into view.
+    pub fn highlight(&self, entry_id: &str) {
+        if let Some(row) = self.rows.get(entry_id) {
+            self.list_box.select_row(Some(row));
+            row.grab_focus();
+        }
+    }
+}
+
 pub fn build_history_table(
     history: &[ValueHistoryPoint],
     ref_val: Option<&ReferenceValue>,
-    gender: Option<&str>,
-) -> gtk4::Widget {
+    evaluation: &Evaluation,
+    editor: Option<&EntryEditorContext>,
+) -> HistoryTable {
     let list_box = gtk4::ListBox::new();
-    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    // Shows the measurement picked in the chart
+    list_box.set_selection_mode(gtk4::SelectionMode::Single);
     list_box.add_css_class("boxed-list");
+    let mut rows = HashMap::new();
 
     if history.is_empty() {
         let row = adw::ActionRow::new();
         row.set_title("Keine Messwerte vorhanden");
         list_box.append(&row);
-        return list_box.upcast();
+        return HistoryTable { list_box, rows };
     }
 
     // Reverse order (newest first)
@@ -27,7 +50,7 @@ pub fn build_history_table(
 
     for point in &sorted {
         let status = ref_val
-            .map(|r| get_value_status(point.value, r, gender))
+            .map(|r| evaluation.status(point.value, r, &point.date))
             .unwrap_or(ValueStatus::Unknown);
 
         let row = adw::ActionRow::new();
@@ -52,10 +75,26 @@ pub fn build_history_table(
         status_label.set_valign(gtk4::Align::Center);
         row.add_suffix(&status_label);
 
+        // Derived values change with their inputs, not in the editor
+        if point.derived {
+            row.set_subtitle("berechnet");
+        } else if let Some(ctx) = editor {
+            row.set_activatable(true);
+            row.add_suffix(&gtk4::Image::from_icon_name("document-edit-symbolic"));
+            let ctx = ctx.clone();
+            let entry_id = point.entry_id.clone();
+            row.connect_activated(move |row| {
+                if let Some(entry) = ctx.find_entry(&entry_id) {
+                    show_entry_editor(row, &ctx, Some(&entry));
+                }
+            });
+        }
+
         list_box.append(&row);
+        rows.insert(point.entry_id.clone(), row);
     }
 
-    list_box.upcast()
+    HistoryTable { list_box, rows }
 }
 
 fn format_value(v: f64, unit: &str) -> String {
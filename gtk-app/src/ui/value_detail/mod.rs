@@ -1,52 +1,36 @@
 pub mod chart;
+pub mod export;
 pub mod history_table;
+pub mod time_range;
 
 use gtk4::prelude::*;
 use libadwaita::prelude::*;
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
+use chrono::Datelike;
+use glib::clone;
 
 use crate::api::types::*;
-use chart::build_chart;
-use history_table::build_history_table;
-
-#[derive(Clone, Copy, PartialEq)]
-enum TimeRange {
-    SixMonths,
-    OneYear,
-    ThreeYears,
-    All,
-}
-
-impl TimeRange {
-    fn months(&self) -> Option<i64> {
-        match self {
-            TimeRange::SixMonths => Some(6),
-            TimeRange::OneYear => Some(12),
-            TimeRange::ThreeYears => Some(36),
-            TimeRange::All => None,
-        }
-    }
-
-    fn label(&self) -> &'static str {
-        match self {
-            TimeRange::SixMonths => "6 Monate",
-            TimeRange::OneYear => "1 Jahr",
-            TimeRange::ThreeYears => "3 Jahre",
-            TimeRange::All => "Alle",
-        }
-    }
-}
+use crate::state::AppStore;
+use crate::ui::dashboard::{category_group::collect_history_for, find_reference, value_card::derived_badge};
+use crate::ui::comparison::build_comparison_page;
+use crate::ui::entry_editor::{show_entry_editor, EntryEditorContext};
+use chart::{build_chart, ChartLayout, ChartState, Viewport, HIT_RADIUS};
+use export::{show_export_dialog, ExportChart};
+use history_table::{build_history_table, HistoryTable};
+use time_range::{filter_history, load_time_range, save_time_range, TimeRange};
 
 pub fn build_value_detail_page(
     name: &str,
-    history: &[ValueHistoryPoint],
-    ref_val: Option<&ReferenceValue>,
-    gender: Option<&str>,
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    editor: &EntryEditorContext,
 ) -> adw::NavigationPage {
     let page = adw::NavigationPage::new(&gtk4::Label::new(None), name);
     page.set_title(name);
+    page.set_tag(Some(&detail_page_tag(name)));
 
     let scrolled = gtk4::ScrolledWindow::new();
     scrolled.set_hscrollbar_policy(gtk4::PolicyType::Never);
@@ -58,10 +42,59 @@ pub fn build_value_detail_page(
     vbox.set_margin_start(16);
     vbox.set_margin_end(16);
 
+    // Kept across updates of the page and saved per value
+    let current_range = Rc::new(RefCell::new(load_time_range(name)));
+
+    fill_value_detail(&vbox, name, nav_view, store, editor, &current_range);
+
+    scrolled.set_child(Some(&vbox));
+    page.set_child(Some(&scrolled));
+
+    let name = name.to_string();
+    store.connect_changed_for(&page, clone!(#[weak] page, #[weak] vbox, #[weak] nav_view, #[strong] editor, move |store| {
+        if !store.is_loaded() {
+            return;
+        }
+        // The value disappeared together with its last entry
+        if !fill_value_detail(&vbox, &name, &nav_view, store, &editor, &current_range)
+            && nav_view.visible_page().as_ref() == Some(&page)
+        {
+            nav_view.pop();
+        }
+    }));
+
+    page
+}
+
+/// Builds the page content from the store. Returns `false` if there is no
+/// measurement of `name` (anymore).
+fn fill_value_detail(
+    vbox: &gtk4::Box,
+    name: &str,
+    nav_view: &adw::NavigationView,
+    store: &AppStore,
+    ctx: &EntryEditorContext,
+    current_range: &Rc<RefCell<TimeRange>>,
+) -> bool {
+    while let Some(child) = vbox.first_child() {
+        vbox.remove(&child);
+    }
+
+    let data = store.display_data();
+    let history = collect_history_for(&data.user_data, name);
+    if history.is_empty() {
+        return false;
+    }
+    let history = history.as_slice();
+    let ref_val = find_reference(&data.reference_db, name);
+    let evaluation = store.evaluation();
+    // Offline snapshots and shared data are read-only
+    let editor = (!store.is_read_only()).then_some(ctx);
+
     // Header: latest value + trend
     let latest = history.last();
     let latest_status = latest.and_then(|l| {
-        ref_val.map(|r| get_value_status(l.value, r, gender))
+        ref_val.map(|r| evaluation.status(l.value, r, &l.date))
     }).unwrap_or(ValueStatus::Unknown);
     let trend = get_trend(history);
 
@@ -131,12 +164,27 @@ pub fn build_value_detail_page(
 
         val_box.append(&status_label);
         val_box.append(&date_label);
+        if l.derived {
+            let badge = derived_badge();
+            badge.set_halign(gtk4::Align::End);
+            val_box.append(&badge);
+        }
+
+        // Unit or plausibility problems of this measurement
+        for warning in data.warnings(&l.entry_id, name) {
+            let warning_label = gtk4::Label::new(Some(&warning.message()));
+            warning_label.add_css_class("caption");
+            warning_label.add_css_class("warning");
+            warning_label.set_halign(gtk4::Align::End);
+            warning_label.set_wrap(true);
+            warning_label.set_justify(gtk4::Justification::Right);
+            val_box.append(&warning_label);
+        }
         header_box.append(&val_box);
     }
     vbox.append(&header_box);
 
     // Time filter buttons
-    let current_range = Rc::new(RefCell::new(TimeRange::OneYear));
     let time_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
 
     let chart_area = gtk4::DrawingArea::new();
@@ -147,67 +195,90 @@ pub fn build_value_detail_page(
     chart_frame.add_css_class("card");
     chart_frame.set_child(Some(&chart_area));
 
-    let history_owned = history.to_vec();
     let ref_val_owned = ref_val.cloned();
-    let gender_owned = gender.map(|s| s.to_string());
-
-    // Draw function (called when range changes or chart is drawn)
-    let setup_draw_func = {
-        let history = history_owned.clone();
-        let ref_val = ref_val_owned.clone();
-        let gender = gender_owned.clone();
-        let current_range = current_range.clone();
-
-        move |area: &gtk4::DrawingArea| {
-            let history = history.clone();
-            let ref_val = ref_val.clone();
-            let gender = gender.clone();
-            let current_range = current_range.clone();
-
-            area.set_draw_func(move |_, cr, width, height| {
-                let range = *current_range.borrow();
-                let filtered = filter_history(&history, range);
-                build_chart(cr, width, height, &filtered, ref_val.as_ref(), gender.as_deref());
-            });
-        }
-    };
-
-    setup_draw_func(&chart_area);
-
-    let time_ranges = [
-        TimeRange::SixMonths,
-        TimeRange::OneYear,
-        TimeRange::ThreeYears,
-        TimeRange::All,
-    ];
 
-    for tr in time_ranges {
-        let btn = gtk4::ToggleButton::with_label(tr.label());
-        if *current_range.borrow() == tr {
-            btn.set_active(true);
-        }
-        btn.add_css_class("pill");
-        btn.add_css_class("flat");
-
-        let current_range_clone = current_range.clone();
-        let chart_area_clone = chart_area.clone();
-
-        btn.connect_toggled(move |b| {
-            if b.is_active() {
-                *current_range_clone.borrow_mut() = tr;
-                chart_area_clone.queue_draw();
-            }
-        });
+    // Built here so clicks on the chart can highlight rows
+    let table = build_history_table(history, ref_val_owned.as_ref(), &evaluation, editor);
 
-        time_box.append(&btn);
-    }
+    let labs: HashMap<String, String> = data
+        .user_data
+        .entries
+        .iter()
+        .filter_map(|e| Some((e.id.clone(), e.lab_name.clone().filter(|l| !l.is_empty())?)))
+        .collect();
+    let model = Rc::new(ChartModel {
+        history: history.to_vec(),
+        ref_val: ref_val_owned.clone(),
+        evaluation: evaluation.clone(),
+        labs,
+        range: current_range.clone(),
+        state: RefCell::new(ChartState::default()),
+        pointer_x: Cell::new(0.0),
+        gesture_start: Cell::new(None),
+        dragged: Cell::new(false),
+    });
+
+    chart_area.set_draw_func(clone!(#[strong] model, move |_, cr, width, height| {
+        let points = model.points();
+        build_chart(cr, width, height, &points, model.ref_val.as_ref(), &model.evaluation, &model.state.borrow());
+    }));
+    setup_chart_interaction(&chart_area, &model, &table, editor.cloned());
+
+    let range_options = build_time_range_controls(
+        name,
+        current_range,
+        &time_box,
+        clone!(#[weak] chart_area, #[strong] model, move || {
+            // Indexes refer to the points of the old range
+            *model.state.borrow_mut() = ChartState::default();
+            chart_area.queue_draw();
+        }),
+    );
+
+    let compare_btn = gtk4::Button::with_label("Vergleichen");
+    compare_btn.add_css_class("pill");
+    compare_btn.add_css_class("flat");
+    compare_btn.set_hexpand(true);
+    compare_btn.set_halign(gtk4::Align::End);
+    compare_btn.set_tooltip_text(Some("Mit anderen Werten in einem Diagramm vergleichen"));
+    let compare_name = name.to_string();
+    compare_btn.connect_clicked(clone!(#[weak] nav_view, #[strong] store, move |_| {
+        nav_view.push(&build_comparison_page(&store, &[compare_name.clone()]));
+    }));
+    time_box.append(&compare_btn);
+
+    let export_btn = gtk4::Button::from_icon_name("document-save-symbolic");
+    export_btn.add_css_class("flat");
+    export_btn.set_tooltip_text(Some("Diagramm exportieren (PNG, SVG, PDF)"));
+    let export_title = name.to_string();
+    export_btn.connect_clicked(clone!(#[strong] model, move |btn| {
+        let unit = model
+            .ref_val
+            .as_ref()
+            .map(|r| r.unit.clone())
+            .or_else(|| model.history.last().map(|p| p.unit.clone()))
+            .unwrap_or_default();
+        let chart = ExportChart {
+            title: export_title.clone(),
+            unit,
+            history: model.points(),
+            ref_val: model.ref_val.clone(),
+            evaluation: model.evaluation.clone(),
+            state: ChartState { viewport: model.state.borrow().viewport, ..Default::default() },
+        };
+        show_export_dialog(btn, chart);
+    }));
+    time_box.append(&export_btn);
 
     vbox.append(&time_box);
+    vbox.append(&range_options);
     vbox.append(&chart_frame);
 
     // Reference range info
     if let Some(r) = &ref_val_owned {
-        let (ref_min, ref_max) = get_effective_range(r, gender_owned.as_deref());
+        // The range that applies at the current age
+        let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+        let (ref_min, ref_max) = evaluation.range(r, &today);
         let ref_group = adw::PreferencesGroup::new();
         ref_group.set_title("Referenzbereiche");
 
@@ -219,6 +290,23 @@ pub fn build_value_detail_page(
             row.add_suffix(&suffix);
             ref_group.add(&row);
         }
+        for band in &r.age_ranges {
+            let gendered = [band.ref_min_female, band.ref_max_female, band.ref_min_male, band.ref_max_male];
+            let text = match (band.ref_min, band.ref_max) {
+                (Some(mn), Some(mx)) => format!("{mn} – {mx} {}", r.unit),
+                (Some(mn), None) => format!("≥ {mn} {}", r.unit),
+                (None, Some(mx)) => format!("≤ {mx} {}", r.unit),
+                (None, None) if gendered.iter().any(Option::is_some) => "geschlechtsspezifisch".to_string(),
+                (None, None) => continue,
+            };
+            let row = adw::ActionRow::new();
+            row.set_title(&format_age_band(band));
+            let suffix = gtk4::Label::new(Some(&text));
+            suffix.add_css_class("numeric");
+            suffix.add_css_class("dim-label");
+            row.add_suffix(&suffix);
+            ref_group.add(&row);
+        }
         if let (Some(mn), Some(mx)) = (r.optimal_min, r.optimal_max) {
             let row = adw::ActionRow::new();
             row.set_title("Optimaler Bereich");
@@ -283,36 +371,401 @@ pub fn build_value_detail_page(
     // History table
     let table_group = adw::PreferencesGroup::new();
     table_group.set_title("Messverlauf");
-    let table = history_table::build_history_table(history, ref_val_owned.as_ref(), gender_owned.as_deref());
-    table_group.add(&table);
+    table_group.add(&table.list_box);
     vbox.append(&table_group);
+    true
+}
 
-    scrolled.set_child(Some(&vbox));
-    page.set_child(Some(&scrolled));
-    page
+/// What the chart's draw function and event controllers share.
+struct ChartModel {
+    history: Vec<ValueHistoryPoint>,
+    ref_val: Option<ReferenceValue>,
+    evaluation: Evaluation,
+    /// Lab name by entry id
+    labs: HashMap<String, String>,
+    range: Rc<RefCell<TimeRange>>,
+    state: RefCell<ChartState>,
+    /// Last pointer position, the anchor for scroll zooming
+    pointer_x: Cell<f64>,
+    /// Viewport when a drag or pinch began
+    gesture_start: Cell<Option<Viewport>>,
+    /// Whether the current press moved, so its release is no click
+    dragged: Cell<bool>,
 }
 
-pub fn filter_history(history: &[ValueHistoryPoint], range: TimeRange) -> Vec<ValueHistoryPoint> {
-    let months = match range.months() {
-        None => return history.to_vec(),
-        Some(m) => m,
-    };
+impl ChartModel {
+    /// The points in the selected time range, as drawn.
+    fn points(&self) -> Vec<ValueHistoryPoint> {
+        filter_history(&self.history, *self.range.borrow(), chrono::Local::now().date_naive())
+    }
 
-    let now = chrono::Utc::now();
-    history
-        .iter()
-        .filter(|h| {
-            if let Ok(date) = chrono::NaiveDate::parse_from_str(&h.date, "%Y-%m-%d") {
-                let date_dt = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
-                let diff_months =
-                    (now.year() - date_dt.year()) * 12 + now.month() as i32 - date_dt.month() as i32;
-                diff_months <= months as i32
-            } else {
-                true
+    fn layout(&self, area: &gtk4::DrawingArea, points: &[ValueHistoryPoint], viewport: Option<Viewport>) -> Option<ChartLayout> {
+        ChartLayout::new(
+            area.width() as f64,
+            area.height() as f64,
+            points,
+            self.ref_val.as_ref(),
+            &self.evaluation,
+            viewport,
+        )
+    }
+
+    /// Zooms by `factor` around the horizontal position `x`. Returns
+    /// whether the view changed.
+    fn zoom_at(&self, area: &gtk4::DrawingArea, start: Option<Viewport>, factor: f64, x: f64) -> bool {
+        let points = self.points();
+        let Some(layout) = self.layout(area, &points, start) else { return false };
+        let zoomed = layout.viewport.zoom(factor, layout.index_at(x), points.len());
+        let mut state = self.state.borrow_mut();
+        let changed = zoomed != state.viewport.unwrap_or(Viewport::full(points.len()));
+        state.viewport = Some(zoomed);
+        changed
+    }
+
+    /// Date, value, status and lab of a point.
+    fn tooltip_text(&self, point: &ValueHistoryPoint) -> String {
+        let status = self
+            .ref_val
+            .as_ref()
+            .map(|r| self.evaluation.status(point.value, r, &point.date))
+            .unwrap_or(ValueStatus::Unknown);
+        let mut lines = vec![
+            format_date(&point.date),
+            format!("{} · {}", format_value_unit(point.value, &point.unit), status.label()),
+        ];
+        if point.derived {
+            lines.push("berechnet".to_string());
+        } else if let Some(lab) = self.labs.get(&point.entry_id) {
+            lines.push(lab.clone());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Tooltips, hover, click to select, scroll/pinch to zoom, drag to pan and
+/// double-click to show everything again.
+fn setup_chart_interaction(
+    area: &gtk4::DrawingArea,
+    model: &Rc<ChartModel>,
+    table: &HistoryTable,
+    editor: Option<EntryEditorContext>,
+) {
+    area.set_has_tooltip(true);
+    area.connect_query_tooltip(clone!(#[strong] model, move |area, x, y, _keyboard, tooltip| {
+        let points = model.points();
+        let viewport = model.state.borrow().viewport;
+        let Some(layout) = model.layout(area, &points, viewport) else { return false };
+        let Some(i) = layout.nearest_point(x as f64, y as f64) else { return false };
+        tooltip.set_text(Some(&model.tooltip_text(&points[i])));
+        // GTK asks again once the pointer leaves the point's column
+        let (x1, x2) = layout.column(i);
+        tooltip.set_tip_area(&gtk4::gdk::Rectangle::new(
+            x1 as i32,
+            layout.plot_y as i32,
+            (x2 - x1).ceil() as i32,
+            layout.plot_h as i32,
+        ));
+        true
+    }));
+
+    let motion = gtk4::EventControllerMotion::new();
+    motion.connect_motion(clone!(#[strong] model, #[weak] area, move |_, x, y| {
+        model.pointer_x.set(x);
+        let points = model.points();
+        let viewport = model.state.borrow().viewport;
+        let hovered = model.layout(&area, &points, viewport).and_then(|l| l.nearest_point(x, y));
+        if model.state.borrow().hovered != hovered {
+            model.state.borrow_mut().hovered = hovered;
+            area.queue_draw();
+        }
+    }));
+    motion.connect_leave(clone!(#[strong] model, #[weak] area, move |_| {
+        if model.state.borrow_mut().hovered.take().is_some() {
+            area.queue_draw();
+        }
+    }));
+    area.add_controller(motion);
+
+    let scroll = gtk4::EventControllerScroll::new(gtk4::EventControllerScrollFlags::VERTICAL);
+    scroll.connect_scroll(clone!(#[strong] model, #[weak] area, #[upgrade_or] glib::Propagation::Proceed, move |_, _dx, dy| {
+        let viewport = model.state.borrow().viewport;
+        if model.zoom_at(&area, viewport, 1.2f64.powf(dy), model.pointer_x.get()) {
+            area.queue_draw();
+            glib::Propagation::Stop
+        } else {
+            // Fully zoomed out: let the page scroll
+            glib::Propagation::Proceed
+        }
+    }));
+    area.add_controller(scroll);
+
+    let pinch = gtk4::GestureZoom::new();
+    pinch.connect_begin(clone!(#[strong] model, move |_, _| {
+        model.gesture_start.set(model.state.borrow().viewport);
+    }));
+    pinch.connect_scale_changed(clone!(#[strong] model, #[weak] area, move |gesture, scale| {
+        let Some((x, _)) = gesture.bounding_box_center() else { return };
+        if scale > 0.0 && model.zoom_at(&area, model.gesture_start.get(), 1.0 / scale, x) {
+            area.queue_draw();
+        }
+    }));
+    area.add_controller(pinch);
+
+    let drag = gtk4::GestureDrag::new();
+    drag.connect_drag_begin(clone!(#[strong] model, move |_, _, _| {
+        model.gesture_start.set(model.state.borrow().viewport);
+        model.dragged.set(false);
+    }));
+    drag.connect_drag_update(clone!(#[strong] model, #[weak] area, move |_, offset_x, _| {
+        if offset_x.abs() > 3.0 {
+            model.dragged.set(true);
+        }
+        let points = model.points();
+        let Some(layout) = model.layout(&area, &points, model.gesture_start.get()) else { return };
+        let panned = layout.viewport.pan(-offset_x * layout.indexes_per_pixel(), points.len());
+        model.state.borrow_mut().viewport = Some(panned);
+        area.queue_draw();
+    }));
+    area.add_controller(drag);
+
+    let click = gtk4::GestureClick::new();
+    click.connect_pressed(clone!(#[strong] model, #[weak] area, move |_, n_press, _, _| {
+        if n_press == 2 {
+            model.state.borrow_mut().viewport = None;
+            area.queue_draw();
+        }
+    }));
+    click.connect_released(clone!(#[strong] model, #[strong] table, #[weak] area, move |_, n_press, x, y| {
+        if n_press != 1 || model.dragged.get() {
+            return;
+        }
+        let points = model.points();
+        let viewport = model.state.borrow().viewport;
+        let Some(layout) = model.layout(&area, &points, viewport) else { return };
+        let Some(i) = layout.hit_point(&points, x, y, HIT_RADIUS) else { return };
+        model.state.borrow_mut().selected = Some(i);
+        area.queue_draw();
+
+        let point = &points[i];
+        table.highlight(&point.entry_id);
+        // Derived values have no entry of their own
+        if let Some(ctx) = editor.as_ref().filter(|_| !point.derived) {
+            if let Some(entry) = ctx.find_entry(&point.entry_id) {
+                show_entry_editor(&area, ctx, Some(&entry));
             }
-        })
-        .cloned()
-        .collect()
+        }
+    }));
+    area.add_controller(click);
+}
+
+/// Adds the range buttons to `button_box` and returns the row with the
+/// dates or count of the custom options, shown while one of them is
+/// active. Changes are saved for `name`, then `on_change` runs.
+fn build_time_range_controls(
+    name: &str,
+    current_range: &Rc<RefCell<TimeRange>>,
+    button_box: &gtk4::Box,
+    on_change: impl Fn() + 'static,
+) -> gtk4::Box {
+    let today = chrono::Local::now().date_naive();
+    let year_ago = today.checked_sub_months(chrono::Months::new(12)).unwrap_or(today);
+    let current = *current_range.borrow();
+
+    let options_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+
+    let dates_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    let from_label = gtk4::Label::new(Some("Von"));
+    from_label.add_css_class("dim-label");
+    let from_btn = gtk4::MenuButton::new();
+    from_btn.add_css_class("flat");
+    let to_label = gtk4::Label::new(Some("bis"));
+    to_label.add_css_class("dim-label");
+    let to_btn = gtk4::MenuButton::new();
+    to_btn.add_css_class("flat");
+    dates_box.append(&from_label);
+    dates_box.append(&from_btn);
+    dates_box.append(&to_label);
+    dates_box.append(&to_btn);
+    options_box.append(&dates_box);
+
+    let count_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    let count_spin = gtk4::SpinButton::with_range(1.0, 999.0, 1.0);
+    count_spin.set_value(match current {
+        TimeRange::LastMeasurements { count } => count,
+        _ => TimeRange::DEFAULT_COUNT,
+    } as f64);
+    count_box.append(&gtk4::Label::new(Some("Letzte")));
+    count_box.append(&count_spin);
+    count_box.append(&gtk4::Label::new(Some("Messungen")));
+    options_box.append(&count_box);
+
+    sync_range_options(current, &options_box, &dates_box, &count_box, &from_btn, &to_btn);
+
+    let name = name.to_string();
+    let set_range: Rc<dyn Fn(TimeRange)> = Rc::new(clone!(
+        #[strong] current_range,
+        #[weak] options_box,
+        #[weak] dates_box,
+        #[weak] count_box,
+        #[weak] from_btn,
+        #[weak] to_btn,
+        move |range: TimeRange| {
+            *current_range.borrow_mut() = range;
+            sync_range_options(range, &options_box, &dates_box, &count_box, &from_btn, &to_btn);
+            if let Err(e) = save_time_range(&name, range) {
+                eprintln!("Failed to save time range: {e:#}");
+            }
+            on_change();
+        }
+    ));
+
+    from_btn.set_popover(Some(&date_popover(
+        year_ago,
+        clone!(#[strong] current_range, move || match *current_range.borrow() {
+            TimeRange::Custom { from, .. } => from,
+            _ => None,
+        }),
+        clone!(#[strong] current_range, #[strong] set_range, move |from| {
+            let current = *current_range.borrow();
+            if let TimeRange::Custom { to, .. } = current {
+                set_range(TimeRange::custom(from, to));
+            }
+        }),
+    )));
+    to_btn.set_popover(Some(&date_popover(
+        today,
+        clone!(#[strong] current_range, move || match *current_range.borrow() {
+            TimeRange::Custom { to, .. } => to,
+            _ => None,
+        }),
+        clone!(#[strong] current_range, #[strong] set_range, move |to| {
+            let current = *current_range.borrow();
+            if let TimeRange::Custom { from, .. } = current {
+                set_range(TimeRange::custom(from, to));
+            }
+        }),
+    )));
+
+    count_spin.connect_value_changed(clone!(#[strong] set_range, move |spin| {
+        set_range(TimeRange::LastMeasurements { count: spin.value_as_int().max(1) as usize });
+    }));
+
+    let kinds = TimeRange::PRESETS.into_iter().chain([
+        TimeRange::Custom { from: None, to: None },
+        TimeRange::LastMeasurements { count: TimeRange::DEFAULT_COUNT },
+    ]);
+    let mut group: Option<gtk4::ToggleButton> = None;
+    for kind in kinds {
+        let btn = gtk4::ToggleButton::with_label(kind.label());
+        btn.set_group(group.as_ref());
+        btn.set_active(kind.same_kind(&current));
+        btn.add_css_class("pill");
+        btn.add_css_class("flat");
+        match kind {
+            TimeRange::Custom { .. } => btn.set_tooltip_text(Some("Eigenen Zeitraum wählen")),
+            TimeRange::LastMeasurements { .. } => btn.set_tooltip_text(Some("Nur die letzten Messungen zeigen")),
+            _ => {}
+        }
+
+        btn.connect_toggled(clone!(#[strong] current_range, #[strong] set_range, #[weak] count_spin, move |b| {
+            if !b.is_active() || current_range.borrow().same_kind(&kind) {
+                return;
+            }
+            set_range(match kind {
+                TimeRange::Custom { .. } => TimeRange::Custom { from: Some(year_ago), to: None },
+                TimeRange::LastMeasurements { .. } => {
+                    TimeRange::LastMeasurements { count: count_spin.value_as_int().max(1) as usize }
+                }
+                preset => preset,
+            });
+        }));
+
+        button_box.append(&btn);
+        if group.is_none() {
+            group = Some(btn);
+        }
+    }
+
+    options_box
+}
+
+/// Shows the controls of the active option and the dates on the buttons.
+fn sync_range_options(
+    range: TimeRange,
+    options_box: &gtk4::Box,
+    dates_box: &gtk4::Box,
+    count_box: &gtk4::Box,
+    from_btn: &gtk4::MenuButton,
+    to_btn: &gtk4::MenuButton,
+) {
+    let custom = matches!(range, TimeRange::Custom { .. });
+    let last = matches!(range, TimeRange::LastMeasurements { .. });
+    options_box.set_visible(custom || last);
+    dates_box.set_visible(custom);
+    count_box.set_visible(last);
+    if let TimeRange::Custom { from, to } = range {
+        let label = |d: Option<chrono::NaiveDate>| d.map_or("offen".to_string(), |d| d.format("%d.%m.%Y").to_string());
+        from_btn.set_label(&label(from));
+        to_btn.set_label(&label(to));
+    }
+}
+
+/// Calendar for one end of a custom range. `get` returns that end when the
+/// popover opens (`fallback` if open), `set` applies a picked day or
+/// `None` for an open end.
+fn date_popover(
+    fallback: chrono::NaiveDate,
+    get: impl Fn() -> Option<chrono::NaiveDate> + 'static,
+    set: impl Fn(Option<chrono::NaiveDate>) + 'static,
+) -> gtk4::Popover {
+    let popover = gtk4::Popover::new();
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    let calendar = gtk4::Calendar::new();
+    let open_btn = gtk4::Button::with_label("Offen lassen");
+    open_btn.add_css_class("flat");
+    vbox.append(&calendar);
+    vbox.append(&open_btn);
+    popover.set_child(Some(&vbox));
+
+    // Selecting the shown day is no pick
+    let syncing = Rc::new(Cell::new(false));
+    popover.connect_show(clone!(#[weak] calendar, #[strong] syncing, move |_| {
+        let date = get().unwrap_or(fallback);
+        if let Ok(day) = glib::DateTime::from_local(date.year(), date.month() as i32, date.day() as i32, 0, 0, 0.0) {
+            syncing.set(true);
+            calendar.select_day(&day);
+            syncing.set(false);
+        }
+    }));
+
+    let set = Rc::new(set);
+    calendar.connect_day_selected(clone!(#[strong] set, move |calendar| {
+        if syncing.get() {
+            return;
+        }
+        let day = calendar.date();
+        set(chrono::NaiveDate::from_ymd_opt(day.year(), day.month() as u32, day.day_of_month() as u32));
+    }));
+    open_btn.connect_clicked(clone!(#[weak] popover, move |_| {
+        set(None);
+        popover.popdown();
+    }));
+    popover
+}
+
+/// Navigation tag of the detail page for `name`, used to find it again after a reload.
+pub fn detail_page_tag(name: &str) -> String {
+    format!("value:{name}")
+}
+
+/// "18 – 65 Jahre", "ab 65 Jahren" or "unter 18 Jahren".
+fn format_age_band(band: &AgeRange) -> String {
+    match (band.min_age, band.max_age) {
+        (Some(min), Some(max)) => format!("{min} – {max} Jahre"),
+        (Some(min), None) => format!("ab {min} Jahren"),
+        (None, Some(max)) => format!("unter {max} Jahren"),
+        (None, None) => "alle Altersgruppen".to_string(),
+    }
 }
 
 fn format_value_unit(v: f64, unit: &str) -> String {
@@ -334,4 +787,3 @@ pub fn format_date(date_str: &str) -> String {
     }
 }
 
-use chrono::Datelike;
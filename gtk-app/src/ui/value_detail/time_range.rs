@@ -0,0 +1,303 @@
+//! Time range of the detail page's chart, remembered per value in
+//! time_ranges.toml next to config.toml.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::api::types::ValueHistoryPoint;
+use crate::config::config_path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeRange {
+    SixMonths,
+    #[default]
+    OneYear,
+    ThreeYears,
+    All,
+    /// Both ends inclusive; a missing end is open
+    Custom { from: Option<NaiveDate>, to: Option<NaiveDate> },
+    /// The last `count` measurements, however old
+    LastMeasurements { count: usize },
+}
+
+impl TimeRange {
+    /// The fixed ranges offered as buttons
+    pub const PRESETS: [TimeRange; 4] =
+        [TimeRange::SixMonths, TimeRange::OneYear, TimeRange::ThreeYears, TimeRange::All];
+
+    pub const DEFAULT_COUNT: usize = 10;
+
+    fn months(&self) -> Option<i32> {
+        match self {
+            TimeRange::SixMonths => Some(6),
+            TimeRange::OneYear => Some(12),
+            TimeRange::ThreeYears => Some(36),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeRange::SixMonths => "6 Monate",
+            TimeRange::OneYear => "1 Jahr",
+            TimeRange::ThreeYears => "3 Jahre",
+            TimeRange::All => "Alle",
+            TimeRange::Custom { .. } => "Zeitraum",
+            TimeRange::LastMeasurements { .. } => "Letzte N",
+        }
+    }
+
+    /// Custom range with the ends in order.
+    pub fn custom(from: Option<NaiveDate>, to: Option<NaiveDate>) -> TimeRange {
+        match (from, to) {
+            (Some(f), Some(t)) if f > t => TimeRange::Custom { from: Some(t), to: Some(f) },
+            _ => TimeRange::Custom { from, to },
+        }
+    }
+
+    /// Whether both are the same option, ignoring dates and counts.
+    pub fn same_kind(&self, other: &TimeRange) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// The points of `history` (oldest first) in `range`, with month ranges
+/// counted back from `today`. Points with unreadable dates are kept.
+pub fn filter_history(history: &[ValueHistoryPoint], range: TimeRange, today: NaiveDate) -> Vec<ValueHistoryPoint> {
+    let parse = |h: &ValueHistoryPoint| NaiveDate::parse_from_str(&h.date, "%Y-%m-%d").ok();
+    // time_ranges.toml may have been edited by hand
+    let range = match range {
+        TimeRange::Custom { from, to } => TimeRange::custom(from, to),
+        range => range,
+    };
+    match range {
+        TimeRange::All => history.to_vec(),
+        TimeRange::LastMeasurements { count } => history[history.len().saturating_sub(count)..].to_vec(),
+        TimeRange::Custom { from, to } => history
+            .iter()
+            .filter(|h| {
+                let Some(date) = parse(h) else { return true };
+                !matches!(from, Some(f) if date < f) && !matches!(to, Some(t) if date > t)
+            })
+            .cloned()
+            .collect(),
+        preset => {
+            let months = preset.months().unwrap_or(0);
+            history
+                .iter()
+                .filter(|h| {
+                    let Some(date) = parse(h) else { return true };
+                    let diff_months =
+                        (today.year() - date.year()) * 12 + today.month() as i32 - date.month() as i32;
+                    diff_months <= months
+                })
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+pub fn time_ranges_path() -> PathBuf {
+    config_path().with_file_name("time_ranges.toml")
+}
+
+/// Saved ranges by value name.
+pub fn load_time_ranges() -> Result<BTreeMap<String, TimeRange>> {
+    let path = time_ranges_path();
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read time ranges from {:?}", path))?;
+    toml::from_str(&content).context("Failed to parse time_ranges.toml")
+}
+
+/// The saved range of `name`, or the default.
+pub fn load_time_range(name: &str) -> TimeRange {
+    match load_time_ranges() {
+        Ok(ranges) => ranges.get(name).copied().unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to load time ranges: {e:#}");
+            TimeRange::default()
+        }
+    }
+}
+
+pub fn save_time_range(name: &str, range: TimeRange) -> Result<()> {
+    // An unreadable file is replaced rather than blocking every save
+    let mut ranges = load_time_ranges().unwrap_or_default();
+    if range == TimeRange::default() {
+        ranges.remove(name);
+    } else {
+        ranges.insert(name.to_string(), range);
+    }
+
+    let path = time_ranges_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    let content = toml::to_string(&ranges).context("Failed to serialize time ranges")?;
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write time ranges to {:?}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_home;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn history(dates: &[&str]) -> Vec<ValueHistoryPoint> {
+        dates
+            .iter()
+            .enumerate()
+            .map(|(i, d)| ValueHistoryPoint {
+                date: d.to_string(),
+                value: i as f64,
+                unit: "mg/dL".into(),
+                entry_id: format!("e{i}"),
+                derived: false,
+            })
+            .collect()
+    }
+
+    fn dates(points: &[ValueHistoryPoint]) -> Vec<&str> {
+        points.iter().map(|p| p.date.as_str()).collect()
+    }
+
+    #[test]
+    fn presets_count_whole_months() {
+        let today = date("2025-06-15");
+        let history = history(&[
+            "2022-05-31", "2022-06-01", "2024-05-31", "2024-06-01", "2024-11-30", "2024-12-01", "2025-06-15",
+        ]);
+        let filter = |range| filter_history(&history, range, today);
+
+        assert_eq!(dates(&filter(TimeRange::SixMonths)), ["2024-12-01", "2025-06-15"]);
+        assert_eq!(dates(&filter(TimeRange::OneYear)), ["2024-06-01", "2024-11-30", "2024-12-01", "2025-06-15"]);
+        assert_eq!(filter(TimeRange::ThreeYears).len(), 6);
+        assert_eq!(dates(&filter(TimeRange::ThreeYears))[0], "2022-06-01");
+        assert_eq!(filter(TimeRange::All).len(), 7);
+    }
+
+    #[test]
+    fn presets_across_the_year_boundary() {
+        let past = history(&["2024-07-31", "2024-08-01", "2025-01-01"]);
+        assert_eq!(
+            dates(&filter_history(&past, TimeRange::SixMonths, date("2025-02-01"))),
+            ["2024-08-01", "2025-01-01"]
+        );
+        // Measurements after "today" are always shown
+        let future = history(&["2025-03-01"]);
+        assert_eq!(filter_history(&future, TimeRange::SixMonths, date("2025-02-01")).len(), 1);
+    }
+
+    #[test]
+    fn custom_range_is_inclusive() {
+        let history = history(&["2024-01-01", "2024-02-01", "2024-03-01", "2024-04-01"]);
+        let filter = |from: Option<&str>, to: Option<&str>| {
+            let range = TimeRange::Custom { from: from.map(date), to: to.map(date) };
+            dates(&filter_history(&history, range, date("2025-01-01")))
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(filter(Some("2024-02-01"), Some("2024-03-01")), ["2024-02-01", "2024-03-01"]);
+        assert_eq!(filter(Some("2024-02-02"), None), ["2024-03-01", "2024-04-01"]);
+        assert_eq!(filter(None, Some("2024-01-31")), ["2024-01-01"]);
+        assert_eq!(filter(None, None).len(), 4);
+        assert!(filter(Some("2024-02-02"), Some("2024-02-28")).is_empty());
+        // Reversed ends, e.g. from a hand-edited file
+        assert_eq!(filter(Some("2024-03-01"), Some("2024-02-01")), ["2024-02-01", "2024-03-01"]);
+    }
+
+    #[test]
+    fn custom_sorts_its_ends() {
+        let (early, late) = (Some(date("2024-01-01")), Some(date("2024-12-31")));
+        assert_eq!(TimeRange::custom(late, early), TimeRange::Custom { from: early, to: late });
+        assert_eq!(TimeRange::custom(early, late), TimeRange::Custom { from: early, to: late });
+        assert_eq!(TimeRange::custom(late, None), TimeRange::Custom { from: late, to: None });
+        assert_eq!(TimeRange::custom(None, early), TimeRange::Custom { from: None, to: early });
+    }
+
+    #[test]
+    fn last_measurements() {
+        let history = history(&["2020-01-01", "2021-01-01", "2022-01-01"]);
+        let last = |count| dates(&filter_history(&history, TimeRange::LastMeasurements { count }, date("2025-01-01")))
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(last(2), ["2021-01-01", "2022-01-01"]);
+        assert_eq!(last(3).len(), 3);
+        assert_eq!(last(10).len(), 3);
+        assert!(last(0).is_empty());
+        assert!(filter_history(&[], TimeRange::LastMeasurements { count: 5 }, date("2025-01-01")).is_empty());
+    }
+
+    #[test]
+    fn unreadable_dates_are_kept() {
+        let history = history(&["2010-01-01", "", "01.02.2024", "2024-13-01", "2025-06-01"]);
+        let today = date("2025-06-15");
+
+        assert_eq!(dates(&filter_history(&history, TimeRange::SixMonths, today)), ["", "01.02.2024", "2024-13-01", "2025-06-01"]);
+        let custom = TimeRange::custom(Some(date("2025-01-01")), Some(date("2025-12-31")));
+        assert_eq!(filter_history(&history, custom, today).len(), 4);
+        assert_eq!(filter_history(&history, TimeRange::LastMeasurements { count: 2 }, today).len(), 2);
+    }
+
+    #[test]
+    fn saved_ranges_round_trip() {
+        let _home = temp_home();
+        assert!(load_time_ranges().unwrap().is_empty());
+
+        let ranges = [
+            ("Ferritin", TimeRange::SixMonths),
+            ("HbA1c", TimeRange::ThreeYears),
+            ("TSH", TimeRange::All),
+            ("LDL-Cholesterin", TimeRange::custom(Some(date("2023-01-01")), None)),
+            ("Vitamin D", TimeRange::custom(Some(date("2023-01-01")), Some(date("2024-06-30")))),
+            ("Kreatinin", TimeRange::LastMeasurements { count: 7 }),
+        ];
+        for (name, range) in ranges {
+            save_time_range(name, range).unwrap();
+        }
+        for (name, range) in ranges {
+            assert_eq!(load_time_range(name), range, "{name}");
+        }
+        assert_eq!(load_time_range("Eisen"), TimeRange::default());
+
+        // The default is not stored
+        save_time_range("Ferritin", TimeRange::OneYear).unwrap();
+        let saved = load_time_ranges().unwrap();
+        assert_eq!(saved.len(), ranges.len() - 1);
+        assert!(!saved.contains_key("Ferritin"));
+
+        let content = std::fs::read_to_string(time_ranges_path()).unwrap();
+        assert_eq!(toml::from_str::<BTreeMap<String, TimeRange>>(&content).unwrap(), saved);
+    }
+
+    #[test]
+    fn unreadable_file_falls_back_to_default() {
+        let _home = temp_home();
+        let path = time_ranges_path();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "[Ferritin]\nkind = \"zehn_jahre\"\n").unwrap();
+
+        assert!(load_time_ranges().is_err());
+        assert_eq!(load_time_range("Ferritin"), TimeRange::default());
+
+        // Saving replaces the broken file
+        save_time_range("TSH", TimeRange::All).unwrap();
+        assert_eq!(load_time_range("TSH"), TimeRange::All);
+    }
+}
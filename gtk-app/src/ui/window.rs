@@ -1,14 +1,32 @@
 use gtk4::prelude::*;
+use gtk4::gio;
 use libadwaita::prelude::*;
 use libadwaita as adw;
 use glib::clone;
 
-use crate::api::ApiClient;
-use crate::config::Config;
-use crate::state::{spawn_task, DataBundle};
+use chrono::{DateTime, Utc};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::api::client::{is_network_error, ClientOptions};
+use crate::api::error::{api_error, ApiError};
+use crate::api::{ApiClient, ReceivedShare, UserData};
+use crate::cache::{format_age, load_snapshot, save_snapshot};
+use crate::config::{save_config, Config, Profile};
+use crate::state::{fetch_bundle, fetch_shared_bundle, spawn_task, AppStore, DataBundle, DataSource};
 use crate::ui::dashboard::build_dashboard_page;
+use crate::ui::entry_editor::EntryEditorContext;
+use crate::ui::health_profile::build_health_profile_page;
+use crate::ui::reference_editor::build_reference_editor_page;
+use crate::ui::scan_import::install_drop_target;
 use crate::ui::ai_chat::build_ai_chat_page;
+use crate::ui::error_actions::{error_toast, recovery_action};
+use crate::ui::received_shares::{build_shares_section, fill_shares_list};
 use crate::ui::settings::show_settings_window;
+use crate::ui::shares::build_shares_page;
+
+/// How often to retry the server while running from the offline snapshot.
+const RECONNECT_INTERVAL_SECS: u32 = 30;
 
 pub fn build_ui(app: &adw::Application, config: Config) {
     let window = adw::ApplicationWindow::new(app);
@@ -25,7 +43,23 @@ pub fn build_ui(app: &adw::Application, config: Config) {
     // ── Sidebar ─────────────────────────────────────────────────────────────
     let sidebar_page = adw::NavigationPage::new(&gtk4::Label::new(None), "Blutwerte");
     let sidebar_toolbar = adw::ToolbarView::new();
-    sidebar_toolbar.add_top_bar(&adw::HeaderBar::new());
+    let sidebar_header = adw::HeaderBar::new();
+    let sidebar_title = adw::WindowTitle::new("Blutwerte", "");
+    sidebar_header.set_title_widget(Some(&sidebar_title));
+
+    // Profile switcher, only shown with more than one profile
+    let profile_menu = gio::Menu::new();
+    let profile_button = gtk4::MenuButton::new();
+    profile_button.set_icon_name("system-users-symbolic");
+    profile_button.set_tooltip_text(Some("Profil wechseln"));
+    profile_button.set_menu_model(Some(&profile_menu));
+    sidebar_header.pack_end(&profile_button);
+
+    let refresh_btn = gtk4::Button::from_icon_name("view-refresh-symbolic");
+    refresh_btn.set_tooltip_text(Some("Aktualisieren (Strg+R)"));
+    refresh_btn.set_action_name(Some("win.refresh"));
+    sidebar_header.pack_start(&refresh_btn);
+    sidebar_toolbar.add_top_bar(&sidebar_header);
 
     let sidebar_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
     sidebar_box.set_vexpand(true);
@@ -33,22 +67,36 @@ pub fn build_ui(app: &adw::Application, config: Config) {
     let list_box = gtk4::ListBox::new();
     list_box.set_selection_mode(gtk4::SelectionMode::Single);
     list_box.add_css_class("navigation-sidebar");
-    list_box.set_vexpand(true);
 
     let dashboard_row = make_sidebar_row("Dashboard", "view-grid-symbolic");
-    let ai_row = make_sidebar_row("KI-Doktor", "dialog-information-symbolic");
     list_box.append(&dashboard_row);
+    let ai_row = make_sidebar_row("KI-Doktor", "dialog-information-symbolic");
     list_box.append(&ai_row);
+    let shares_row = make_sidebar_row("Freigaben", "emblem-shared-symbolic");
+    list_box.append(&shares_row);
+    let profile_row = make_sidebar_row("Gesundheitsprofil", "user-info-symbolic");
+    list_box.append(&profile_row);
+    // Only admins edit the reference database
+    let reference_row = make_sidebar_row("Referenzwerte", "accessories-dictionary-symbolic");
+    reference_row.set_visible(false);
+    list_box.append(&reference_row);
 
     let settings_btn = gtk4::Button::new();
     settings_btn.set_icon_name("preferences-system-symbolic");
     settings_btn.add_css_class("flat");
     settings_btn.set_tooltip_text(Some("Einstellungen"));
+    settings_btn.set_action_name(Some("win.settings"));
     settings_btn.set_halign(gtk4::Align::Center);
     settings_btn.set_margin_bottom(8);
     settings_btn.set_margin_top(4);
 
+    // People who share their values with me
+    let (shares_section, shares_list) = build_shares_section();
+    shares_section.set_vexpand(true);
+    shares_section.set_valign(gtk4::Align::Start);
+
     sidebar_box.append(&list_box);
+    sidebar_box.append(&shares_section);
     sidebar_box.append(&settings_btn);
     sidebar_toolbar.set_content(Some(&sidebar_box));
     sidebar_page.set_child(Some(&sidebar_toolbar));
@@ -60,11 +108,26 @@ pub fn build_ui(app: &adw::Application, config: Config) {
     let content_page = adw::NavigationPage::new(&nav_view, "Inhalt");
     split_view.set_content(Some(&content_page));
 
-    toast_overlay.set_child(Some(&split_view));
+    let offline_banner = adw::Banner::new("");
+    offline_banner.set_button_label(Some("Erneut verbinden"));
+
+    let root_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    split_view.set_vexpand(true);
+    root_box.append(&offline_banner);
+    root_box.append(&split_view);
+
+    toast_overlay.set_child(Some(&root_box));
     window.set_content(Some(&toast_overlay));
+
+    // Editing triggers a reload through this channel
+    let (reload_tx, reload_rx) = async_channel::unbounded::<()>();
+    let store = AppStore::new();
+    store.set_unit_system(config.units);
+    store.set_evaluation_mode(config.evaluation);
     window.present();
 
-    // Keyboard shortcuts: Ctrl+W = close window, Ctrl+Q = quit app
+    // Keyboard shortcuts: Ctrl+W = close window, Ctrl+Q = quit app,
+    // Ctrl+R / F5 = reload data
     {
         let app_for_keys = app.clone();
         let window_for_keys = window.downgrade();
@@ -81,8 +144,19 @@ pub fn build_ui(app: &adw::Application, config: Config) {
                         app_for_keys.quit();
                         glib::Propagation::Stop
                     }
+                    gtk4::gdk::Key::r | gtk4::gdk::Key::R => {
+                        if let Some(w) = window_for_keys.upgrade() {
+                            let _ = w.activate_action("win.refresh", None);
+                        }
+                        glib::Propagation::Stop
+                    }
                     _ => glib::Propagation::Proceed,
                 }
+            } else if key == gtk4::gdk::Key::F5 {
+                if let Some(w) = window_for_keys.upgrade() {
+                    let _ = w.activate_action("win.refresh", None);
+                }
+                glib::Propagation::Stop
             } else {
                 glib::Propagation::Proceed
             }
@@ -90,120 +164,481 @@ pub fn build_ui(app: &adw::Application, config: Config) {
         window.add_controller(key_ctrl);
     }
 
+    let profile_action = gio::SimpleAction::new_stateful(
+        "profile",
+        Some(&String::static_variant_type()),
+        &config.active().map(|p| p.name.clone()).unwrap_or_default().to_variant(),
+    );
+    window.add_action(&profile_action);
+
+    let state = WindowState {
+        config: Rc::new(RefCell::new(config)),
+        sidebar: list_box.clone(),
+        shares_section,
+        shares_list: shares_list.clone(),
+        shares: Rc::new(RefCell::new(Vec::new())),
+        shown_share: Rc::new(RefCell::new(None)),
+        sidebar_title,
+        profile_menu,
+        profile_button,
+        profile_action: profile_action.clone(),
+        nav_view: nav_view.clone(),
+        toast_overlay: toast_overlay.clone(),
+        offline_banner: offline_banner.clone(),
+        store: store.clone(),
+        editor: EntryEditorContext {
+            store: store.clone(),
+            on_saved: Rc::new(move || {
+                reload_tx.try_send(()).ok();
+            }),
+        },
+    };
+
+    store.connect_changed(clone!(#[weak] offline_banner, #[weak] reference_row, move |store| {
+        update_offline_banner(&offline_banner, store);
+        reference_row.set_visible(store.is_admin());
+    }));
+
+    update_profile_switcher(&state);
+
     // Show loading spinner
     let spinner_page = make_loading_page("Lade Daten...");
     nav_view.push(&spinner_page);
+    load_data(&state, LoadMode::Initial);
 
-    // Async load via async_channel
-    let (tx, rx) = async_channel::bounded::<Result<Box<DataBundle>, String>>(1);
+    let refresh_action = gio::SimpleAction::new("refresh", None);
+    refresh_action.connect_activate(clone!(#[strong] state, move |_, _| {
+        load_data(&state, LoadMode::Refresh);
+    }));
+    window.add_action(&refresh_action);
 
-    {
-        let cfg = config.clone();
-        spawn_task(async move {
-            let result: Result<Box<DataBundle>, String> = async {
-                let client = ApiClient::new(cfg.server_url.clone(), cfg.api_token.clone())
-                    .map_err(|e| e.to_string())?;
-                let user_data = client.get_blood_values().await.map_err(|e| e.to_string())?;
-                let reference_db = client.get_reference().await.map_err(|e| e.to_string())?;
-                // Try /api/auth/me (works after backend fix); fall back to UserData fields
-                let user = client.get_me().await.unwrap_or_else(|_| crate::api::AuthUser {
-                    authenticated: true,
-                    user_id: Some(user_data.user_id.clone()),
-                    display_name: Some(user_data.display_name.clone()),
-                    email: Some(user_data.email.clone()),
-                    gender: user_data.gender.clone(),
-                    is_admin: None,
-                });
-                Ok(Box::new(DataBundle { user, user_data, reference_db }))
-            }.await;
-            tx.send(result).await.ok();
-        });
-    }
+    profile_action.connect_change_state(clone!(#[strong] state, move |_, value| {
+        if let Some(name) = value.and_then(|v| v.get::<String>()) {
+            switch_profile(&state, &name);
+        }
+    }));
 
-    // Receive result on GTK main thread
-    let config_for_spawn = config.clone();
-    glib::MainContext::default().spawn_local(clone!(#[weak] nav_view, #[weak] list_box, #[weak] toast_overlay, #[weak] window, async move {
-        let config = config_for_spawn;
-        if let Ok(result) = rx.recv().await {
-            // Pop loading page
-            nav_view.pop();
-
-            match result {
-                Ok(bundle) => {
-                    let gender = bundle.user.gender.clone();
-                    let ref_db = bundle.reference_db.values.clone();
-
-                    // Build and show dashboard
-                    let dash_page = build_dashboard_page(
-                        &nav_view,
-                        &bundle.user_data,
-                        &ref_db,
-                        gender.as_deref(),
-                    );
-                    nav_view.replace(&[dash_page]);
-
-                    let ai_client = ApiClient::new(
-                        config.server_url.clone(),
-                        config.api_token.clone(),
-                    ).ok();
-
-                    // Sidebar selection
-                    let user_data = bundle.user_data.clone();
-                    let ref_db_clone = ref_db.clone();
-                    let gender_clone = gender.clone();
-
-                    list_box.connect_row_activated(clone!(#[weak] nav_view, move |_, row| {
-                        match row.index() {
-                            0 => {
-                                let dash = build_dashboard_page(
-                                    &nav_view,
-                                    &user_data,
-                                    &ref_db_clone,
-                                    gender_clone.as_deref(),
-                                );
-                                nav_view.replace(&[dash]);
-                            }
-                            1 => {
-                                if let Some(ref client) = ai_client {
-                                    let chat = build_ai_chat_page(client.clone());
-                                    nav_view.replace(&[chat]);
-                                }
-                            }
-                            _ => {}
-                        }
-                    }));
+    // Sidebar selection
+    list_box.connect_row_activated(clone!(#[strong] state, move |_, row| {
+        state.shares_list.unselect_all();
+        state.shown_share.borrow_mut().take();
+        match row.index() {
+            0 => show_dashboard(&state),
+            1 => show_chat(&state),
+            2 => show_shares(&state),
+            3 => show_health_profile(&state),
+            4 => show_reference_editor(&state),
+            _ => {}
+        }
+    }));
 
-                    list_box.select_row(list_box.row_at_index(0).as_ref());
-                }
-                Err(e) => {
-                    let error_page = make_error_page(&e);
-                    nav_view.replace(&[error_page]);
+    list_box.select_row(list_box.row_at_index(0).as_ref());
 
-                    let toast = adw::Toast::new(&format!("Fehler beim Laden: {e}"));
-                    toast.set_timeout(5);
-                    toast_overlay.add_toast(toast);
-                }
-            }
+    shares_list.connect_row_activated(clone!(#[strong] state, move |_, row| {
+        let share = usize::try_from(row.index())
+            .ok()
+            .and_then(|i| state.shares.borrow().get(i).cloned());
+        if let Some(share) = share {
+            state.sidebar.unselect_all();
+            show_shared(&state, share);
+        }
+    }));
+
+    // Dropped lab reports go to the editor of the current (online) session
+    install_drop_target(&toast_overlay, clone!(#[strong] state, move || {
+        state.store.client().map(|_| state.editor.clone())
+    }));
+
+    // Reload user data after edits and refresh the visible pages
+    glib::MainContext::default().spawn_local(clone!(#[strong] state, async move {
+        while reload_rx.recv().await.is_ok() {
+            reload_user_data(&state).await;
         }
     }));
 
-    // Settings button
+    // Offline: manual reconnect plus a periodic retry
+    offline_banner.connect_button_clicked(clone!(#[strong] state, move |_| {
+        load_data(&state, LoadMode::Reconnect);
+    }));
+    let reconnect_timer = glib::timeout_add_seconds_local(RECONNECT_INTERVAL_SECS, clone!(#[strong] state, move || {
+        if let Some(DataSource::Snapshot { saved_at }) = state.store.source() {
+            state.offline_banner.set_title(&offline_banner_title(saved_at));
+            load_data(&state, LoadMode::Reconnect);
+        }
+        glib::ControlFlow::Continue
+    }));
+    // The timer holds the state; stop it together with the window
+    let reconnect_timer = Cell::new(Some(reconnect_timer));
+    window.connect_destroy(move |_| {
+        if let Some(timer) = reconnect_timer.take() {
+            timer.remove();
+        }
+    });
+
+    // Settings, also opened from errors that need a new token
     {
         let window_clone = window.clone();
-        let app_clone = app.clone();
-        let config_for_settings = config.clone();
-
-        settings_btn.connect_clicked(move |_| {
-            show_settings_window(&window_clone, config_for_settings.clone(), {
-                let app = app_clone.clone();
-                move |_new_config| {
-                    app.quit();
+
+        let settings_action = gio::SimpleAction::new("settings", None);
+        window.add_action(&settings_action);
+        settings_action.connect_activate(clone!(#[strong] state, move |_, _| {
+            let config = state.config.borrow().clone();
+            show_settings_window(&window_clone, config, clone!(#[strong] state, move |new_config| {
+                let active_name = |c: &Config| c.active().map(|p| p.name.clone());
+                let same_profile = active_name(&state.config.borrow()) == active_name(&new_config);
+                state.store.set_unit_system(new_config.units);
+                state.store.set_evaluation_mode(new_config.evaluation);
+                *state.config.borrow_mut() = new_config;
+                update_profile_switcher(&state);
+                // Same account: reload in place and keep the open pages
+                if same_profile {
+                    load_data(&state, LoadMode::Refresh);
+                } else {
+                    start_session(&state);
                 }
-            });
-        });
+            }));
+        }));
+    }
+}
+
+#[derive(Clone)]
+struct WindowState {
+    config: Rc<RefCell<Config>>,
+    sidebar: gtk4::ListBox,
+    shares_section: gtk4::Box,
+    shares_list: gtk4::ListBox,
+    /// Received shares, in the order of `shares_list`
+    shares: Rc<RefCell<Vec<ReceivedShare>>>,
+    /// Share whose values are open in the content area
+    shown_share: Rc<RefCell<Option<String>>>,
+    sidebar_title: adw::WindowTitle,
+    profile_menu: gio::Menu,
+    profile_button: gtk4::MenuButton,
+    profile_action: gio::SimpleAction,
+    nav_view: adw::NavigationView,
+    toast_overlay: adw::ToastOverlay,
+    offline_banner: adw::Banner,
+    /// Data of the active profile, observed by the pages
+    store: AppStore,
+    editor: EntryEditorContext,
+}
+
+type LoadResult = anyhow::Result<(Box<DataBundle>, DataSource)>;
+
+/// Loads everything from the server, falling back to the offline snapshot
+/// when the server cannot be reached.
+async fn fetch_or_snapshot(profile: Profile, options: ClientOptions) -> LoadResult {
+    let client = ApiClient::with_options(profile.server_url.clone(), profile.api_token.clone(), options)?;
+
+    match fetch_bundle(&client).await {
+        Ok(bundle) => {
+            if let Err(e) = save_snapshot(&profile, &bundle) {
+                eprintln!("Failed to save offline snapshot: {e}");
+            }
+            Ok((Box::new(bundle), DataSource::Live))
+        }
+        Err(e) if is_network_error(&e) => match load_snapshot(&profile) {
+            Ok(Some(snapshot)) => {
+                let saved_at = snapshot.saved_at;
+                Ok((Box::new(snapshot.into_bundle()), DataSource::Snapshot { saved_at }))
+            }
+            Ok(None) => Err(e),
+            Err(cache_err) => {
+                eprintln!("Failed to load offline snapshot: {cache_err}");
+                Err(e)
+            }
+        },
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LoadMode {
+    /// First load of a profile: shows the error page on failure
+    Initial,
+    /// Retry while offline: stays on the snapshot if still unreachable
+    Reconnect,
+    /// Manual refresh or changed settings: keeps the current pages on failure
+    Refresh,
+}
+
+/// Full load of user, data and reference DB.
+fn load_data(state: &WindowState, mode: LoadMode) {
+    let Some(profile) = state.config.borrow().active().cloned() else { return };
+    let options = state.config.borrow().network;
+
+    let (tx, rx) = async_channel::bounded::<LoadResult>(1);
+    let task_profile = profile.clone();
+    spawn_task(async move {
+        tx.send(fetch_or_snapshot(task_profile, options).await).await.ok();
+    });
+
+    let state = state.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let Ok(result) = rx.recv().await else { return };
+        // The profile was switched while loading
+        if state.config.borrow().active() != Some(&profile) {
+            return;
+        }
+        let has_session = state.store.is_loaded();
+        let source = result.as_ref().ok().map(|(_, source)| *source);
+        match (load_outcome(mode, source, has_session), result) {
+            (LoadOutcome::Apply(message), Ok((bundle, source))) => {
+                apply_bundle(&state, &profile, *bundle, source);
+                load_received_shares(&state);
+                // Open pages update themselves through the store
+                if !has_session {
+                    show_dashboard(&state);
+                }
+                if let Some(message) = message {
+                    let toast = adw::Toast::new(message);
+                    toast.set_timeout(3);
+                    state.toast_overlay.add_toast(toast);
+                }
+            }
+            (LoadOutcome::ErrorToast(title), Err(e)) => {
+                state.toast_overlay.add_toast(error_toast(title, &e));
+            }
+            (LoadOutcome::ErrorPage, Err(e)) => {
+                let error_page = make_error_page(&e);
+                state.nav_view.replace(&[error_page]);
+            }
+            _ => {}
+        }
+    });
+}
+
+/// What a finished load does to the window.
+#[derive(Debug, PartialEq)]
+enum LoadOutcome {
+    /// Still unreachable: stay on the snapshot already shown
+    KeepSnapshot,
+    /// Show the loaded data, with an optional confirmation toast
+    Apply(Option<&'static str>),
+    /// Keep the current pages and report the error in a toast
+    ErrorToast(&'static str),
+    /// Replace the pages with the error page
+    ErrorPage,
+}
+
+/// `source` is `None` if the load failed.
+fn load_outcome(mode: LoadMode, source: Option<DataSource>, has_session: bool) -> LoadOutcome {
+    match (mode, source) {
+        (LoadMode::Reconnect, Some(DataSource::Snapshot { .. })) => LoadOutcome::KeepSnapshot,
+        (LoadMode::Reconnect, Some(_)) => LoadOutcome::Apply(Some("Wieder verbunden")),
+        (LoadMode::Refresh, Some(DataSource::Live)) => LoadOutcome::Apply(Some("Daten aktualisiert")),
+        (_, Some(_)) => LoadOutcome::Apply(None),
+        (LoadMode::Reconnect, None) => LoadOutcome::ErrorToast("Verbindung fehlgeschlagen"),
+        (LoadMode::Refresh, None) if has_session => LoadOutcome::ErrorToast("Aktualisieren fehlgeschlagen"),
+        (_, None) => LoadOutcome::ErrorPage,
     }
 }
 
+fn apply_bundle(state: &WindowState, profile: &Profile, bundle: DataBundle, source: DataSource) {
+    let options = state.config.borrow().network;
+    let Ok(client) = ApiClient::with_options(profile.server_url.clone(), profile.api_token.clone(), options) else {
+        return;
+    };
+    state.store.set_bundle(client, bundle, source);
+}
+
+fn update_offline_banner(banner: &adw::Banner, store: &AppStore) {
+    match store.source() {
+        Some(DataSource::Snapshot { saved_at }) => {
+            banner.set_title(&offline_banner_title(saved_at));
+            banner.set_revealed(true);
+        }
+        _ => banner.set_revealed(false),
+    }
+}
+
+/// Re-fetches only the user data, e.g. after an entry was saved.
+async fn reload_user_data(state: &WindowState) {
+    let Some(client) = state.store.client() else { return };
+    let generation = state.store.generation();
+
+    let (tx, rx) = async_channel::bounded::<Result<UserData, ApiError>>(1);
+    spawn_task(async move {
+        tx.send(client.get_blood_values().await).await.ok();
+    });
+
+    match rx.recv().await {
+        // Ignore data of a session that was replaced in the meantime
+        Ok(Ok(data)) if state.store.generation() == generation => {
+            state.store.set_user_data(data);
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            state.toast_overlay.add_toast(error_toast("Fehler beim Aktualisieren", &anyhow::Error::new(e)));
+        }
+        Err(_) => {}
+    }
+}
+
+/// Activates another profile and loads its data from scratch.
+fn switch_profile(state: &WindowState, name: &str) {
+    let mut config = {
+        let mut config = state.config.borrow_mut();
+        if config.active().is_some_and(|p| p.name == name) || !config.set_active(name) {
+            return;
+        }
+        config.clone()
+    };
+    spawn_task(async move {
+        if let Err(e) = save_config(&mut config).await {
+            eprintln!("Failed to save config: {e}");
+        }
+    });
+    update_profile_switcher(state);
+    start_session(state);
+}
+
+/// Drops the current session and loads the active profile.
+fn start_session(state: &WindowState) {
+    state.store.clear();
+    state.shown_share.borrow_mut().take();
+    set_received_shares(state, Vec::new());
+    state.sidebar.select_row(state.sidebar.row_at_index(0).as_ref());
+    state.nav_view.replace(&[make_loading_page("Lade Daten...")]);
+    load_data(state, LoadMode::Initial);
+}
+
+fn update_profile_switcher(state: &WindowState) {
+    let config = state.config.borrow();
+    let active = config.active().map(|p| p.name.clone()).unwrap_or_default();
+
+    state.profile_menu.remove_all();
+    for name in config.profile_names() {
+        let item = gio::MenuItem::new(Some(&name), None);
+        item.set_action_and_target_value(Some("win.profile"), Some(&name.to_variant()));
+        state.profile_menu.append_item(&item);
+    }
+    state.profile_action.set_state(&active.to_variant());
+    state.profile_button.set_visible(config.profiles.len() > 1);
+    state.sidebar_title.set_subtitle(if config.profiles.len() > 1 { active.as_str() } else { "" });
+}
+
+fn show_dashboard(state: &WindowState) {
+    if !state.store.is_loaded() {
+        return;
+    }
+    let dash = build_dashboard_page(&state.nav_view, &state.store, &state.editor);
+    state.nav_view.replace(&[dash]);
+}
+
+fn show_chat(state: &WindowState) {
+    let page = build_ai_chat_page(&state.store);
+    state.nav_view.replace(&[page]);
+}
+
+fn show_shares(state: &WindowState) {
+    let page = build_shares_page(&state.store, &state.toast_overlay);
+    state.nav_view.replace(&[page]);
+}
+
+fn show_health_profile(state: &WindowState) {
+    let page = build_health_profile_page(&state.store, &state.toast_overlay);
+    state.nav_view.replace(&[page]);
+}
+
+fn show_reference_editor(state: &WindowState) {
+    let page = build_reference_editor_page(&state.nav_view, &state.store, &state.toast_overlay);
+    state.nav_view.replace(&[page]);
+}
+
+/// Lists who shares values with me. Needs a server connection, the list
+/// stays empty while offline.
+fn load_received_shares(state: &WindowState) {
+    let Some(client) = state.store.client() else {
+        set_received_shares(state, Vec::new());
+        return;
+    };
+    let generation = state.store.generation();
+
+    let (tx, rx) = async_channel::bounded::<Result<Vec<ReceivedShare>, ApiError>>(1);
+    spawn_task(async move {
+        tx.send(client.get_received_shares().await).await.ok();
+    });
+
+    let state = state.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let Ok(result) = rx.recv().await else { return };
+        if state.store.generation() != generation {
+            return;
+        }
+        match result {
+            Ok(shares) => set_received_shares(&state, shares),
+            Err(e) => eprintln!("Failed to load received shares: {e}"),
+        }
+    });
+}
+
+fn set_received_shares(state: &WindowState, shares: Vec<ReceivedShare>) {
+    fill_shares_list(&state.shares_section, &state.shares_list, &shares);
+    // Keep the open share selected
+    let shown = state.shown_share.borrow().clone();
+    if let Some(index) = shown.and_then(|id| shares.iter().position(|s| s.share_id == id)) {
+        state.shares_list.select_row(state.shares_list.row_at_index(index as i32).as_ref());
+    }
+    *state.shares.borrow_mut() = shares;
+}
+
+/// Opens the dashboard for the values of a received share, read-only and
+/// with the owner's gender applied to the reference ranges.
+fn show_shared(state: &WindowState, share: ReceivedShare) {
+    let Some(client) = state.store.client() else { return };
+    *state.shown_share.borrow_mut() = Some(share.share_id.clone());
+    state.nav_view.replace(&[make_loading_page(&format!(
+        "Lade Werte von {}...",
+        share.owner_display_name
+    ))]);
+
+    let (tx, rx) = async_channel::bounded::<anyhow::Result<DataBundle>>(1);
+    let task_client = client.clone();
+    let share_id = share.share_id.clone();
+    spawn_task(async move {
+        tx.send(fetch_shared_bundle(&task_client, &share_id).await).await.ok();
+    });
+
+    let state = state.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let Ok(result) = rx.recv().await else { return };
+        // Another page was opened in the meantime
+        if state.shown_share.borrow().as_deref() != Some(share.share_id.as_str()) {
+            return;
+        }
+        match result {
+            Ok(bundle) => {
+                let store = AppStore::new();
+                store.set_unit_system(state.store.unit_system());
+                store.set_evaluation_mode(state.store.evaluation_mode());
+                store.set_bundle(client, bundle, DataSource::Shared);
+                let editor = EntryEditorContext {
+                    store: store.clone(),
+                    on_saved: Rc::new(|| {}),
+                };
+                let page = build_dashboard_page(&state.nav_view, &store, &editor);
+                // Units and evaluation follow the settings while the share is open
+                state.store.connect_changed_for(&page, clone!(#[weak] store, move |main| {
+                    store.set_unit_system(main.unit_system());
+                    store.set_evaluation_mode(main.evaluation_mode());
+                }));
+                state.nav_view.replace(&[page]);
+            }
+            Err(e) => state.nav_view.replace(&[make_error_page(&e)]),
+        }
+    });
+}
+
+fn offline_banner_title(saved_at: DateTime<Utc>) -> String {
+    let local = saved_at.with_timezone(&chrono::Local);
+    format!(
+        "Offline – Daten vom {} ({})",
+        local.format("%d.%m.%Y, %H:%M"),
+        format_age(saved_at, Utc::now())
+    )
+}
+
 fn make_sidebar_row(label: &str, icon_name: &str) -> adw::ActionRow {
     let row = adw::ActionRow::new();
     row.set_title(label);
@@ -236,7 +671,7 @@ fn make_loading_page(msg: &str) -> adw::NavigationPage {
     adw::NavigationPage::new(&toolbar, "Laden")
 }
 
-fn make_error_page(error: &str) -> adw::NavigationPage {
+fn make_error_page(error: &anyhow::Error) -> adw::NavigationPage {
     let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
     vbox.set_valign(gtk4::Align::Center);
     vbox.set_halign(gtk4::Align::Center);
@@ -244,14 +679,21 @@ fn make_error_page(error: &str) -> adw::NavigationPage {
     vbox.set_margin_start(32);
     vbox.set_margin_end(32);
 
-    let icon = gtk4::Image::from_icon_name("network-offline-symbolic");
+    let api_error = api_error(error);
+    let (icon_name, title) = match api_error {
+        Some(ApiError::Unauthorized) => ("dialog-password-symbolic", "Anmeldung fehlgeschlagen"),
+        Some(e) if !e.is_offline() => ("dialog-error-symbolic", "Fehler beim Laden"),
+        _ => ("network-offline-symbolic", "Verbindungsfehler"),
+    };
+
+    let icon = gtk4::Image::from_icon_name(icon_name);
     icon.set_pixel_size(48);
     icon.add_css_class("error");
 
-    let title = gtk4::Label::new(Some("Verbindungsfehler"));
+    let title = gtk4::Label::new(Some(title));
     title.add_css_class("title-2");
 
-    let label = gtk4::Label::new(Some(error));
+    let label = gtk4::Label::new(Some(&error.to_string()));
     label.add_css_class("dim-label");
     label.set_wrap(true);
     label.set_justify(gtk4::Justification::Center);
@@ -260,9 +702,162 @@ fn make_error_page(error: &str) -> adw::NavigationPage {
     vbox.append(&title);
     vbox.append(&label);
 
+    if let Some((label, action)) = api_error.and_then(recovery_action) {
+        let button = gtk4::Button::with_label(label);
+        button.add_css_class("pill");
+        button.add_css_class("suggested-action");
+        button.set_halign(gtk4::Align::Center);
+        button.set_margin_top(12);
+        button.set_action_name(Some(action));
+        vbox.append(&button);
+    }
+
     let toolbar = adw::ToolbarView::new();
     toolbar.add_top_bar(&adw::HeaderBar::new());
     toolbar.set_content(Some(&vbox));
 
     adw::NavigationPage::new(&toolbar, "Fehler")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AuthUser;
+    use crate::cache::snapshot_path;
+    use crate::state::{block_on, init_tokio};
+    use crate::test_support::{temp_home, StubServer};
+
+    fn start_server(port: u16) -> StubServer {
+        let user_data = UserData { user_id: "u1".into(), display_name: "Erika".into(), ..Default::default() };
+        let user = AuthUser { authenticated: true, display_name: Some("Erika M.".into()), ..Default::default() };
+        StubServer::start(
+            port,
+            vec![
+                ("/api/bloodvalues", 200, serde_json::to_string(&user_data).unwrap()),
+                ("/api/reference", 200, include_str!("../../../data/reference_values.json").to_string()),
+                ("/api/auth/me", 200, serde_json::to_string(&user).unwrap()),
+            ],
+        )
+    }
+
+    fn profile(port: u16) -> Profile {
+        Profile {
+            name: "Privat".into(),
+            server_url: format!("http://127.0.0.1:{port}"),
+            api_token: "token".into(),
+            token: None,
+        }
+    }
+
+    fn load(profile: &Profile) -> LoadResult {
+        let options = ClientOptions { connect_timeout_secs: 2, timeout_secs: 5, max_retries: 0, ..Default::default() };
+        init_tokio();
+        block_on(fetch_or_snapshot(profile.clone(), options))
+    }
+
+    /// A port nothing listens on
+    fn closed_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn online_load_writes_snapshot() {
+        let _home = temp_home();
+        let server = start_server(0);
+        let profile = profile(server.port());
+
+        let (bundle, source) = load(&profile).unwrap();
+        assert_eq!(source, DataSource::Live);
+        assert_eq!(bundle.user.display_name.as_deref(), Some("Erika M."));
+        assert!(!bundle.reference_db.values.is_empty());
+
+        let snapshot = load_snapshot(&profile).unwrap().expect("snapshot");
+        assert_eq!(snapshot.user_data.user_id, "u1");
+        assert_eq!(snapshot.reference_db.values.len(), bundle.reference_db.values.len());
+    }
+
+    #[test]
+    fn network_error_falls_back_to_snapshot() {
+        let _home = temp_home();
+        let server = start_server(0);
+        let profile = profile(server.port());
+        load(&profile).unwrap();
+        let saved_at = load_snapshot(&profile).unwrap().expect("snapshot").saved_at;
+        drop(server);
+
+        let (bundle, source) = load(&profile).unwrap();
+        assert_eq!(source, DataSource::Snapshot { saved_at });
+        assert_eq!(bundle.user_data.user_id, "u1");
+    }
+
+    #[test]
+    fn reconnect_returns_to_live() {
+        let _home = temp_home();
+        let server = start_server(0);
+        let port = server.port();
+        let profile = profile(port);
+        load(&profile).unwrap();
+        drop(server);
+
+        let (_, offline) = load(&profile).unwrap();
+        assert!(matches!(offline, DataSource::Snapshot { .. }));
+        assert_eq!(load_outcome(LoadMode::Reconnect, Some(offline), true), LoadOutcome::KeepSnapshot);
+
+        let _server = start_server(port);
+        let (_, source) = load(&profile).unwrap();
+        assert_eq!(source, DataSource::Live);
+        assert_eq!(
+            load_outcome(LoadMode::Reconnect, Some(source), true),
+            LoadOutcome::Apply(Some("Wieder verbunden"))
+        );
+    }
+
+    #[test]
+    fn offline_without_snapshot_is_an_error() {
+        let _home = temp_home();
+        let error = load(&profile(closed_port())).unwrap_err();
+        assert!(is_network_error(&error));
+    }
+
+    #[test]
+    fn offline_with_corrupt_snapshot_is_an_error() {
+        let _home = temp_home();
+        let profile = profile(closed_port());
+        let path = snapshot_path(&profile);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"{\"saved_at\": \"gestern\"").unwrap();
+
+        let error = load(&profile).unwrap_err();
+        assert!(is_network_error(&error));
+    }
+
+    #[test]
+    fn server_errors_do_not_fall_back() {
+        let _home = temp_home();
+        let server = start_server(0);
+        let port = server.port();
+        let profile = profile(port);
+        load(&profile).unwrap();
+        drop(server);
+
+        let _server = StubServer::start(port, vec![("/api/bloodvalues", 401, r#"{"error":"Invalid token"}"#.into())]);
+        let error = load(&profile).unwrap_err();
+        assert!(!is_network_error(&error));
+        assert!(matches!(api_error(&error), Some(ApiError::Unauthorized)));
+    }
+
+    #[test]
+    fn load_outcomes() {
+        let snapshot = Some(DataSource::Snapshot { saved_at: Utc::now() });
+        let live = Some(DataSource::Live);
+
+        assert_eq!(load_outcome(LoadMode::Initial, live, false), LoadOutcome::Apply(None));
+        assert_eq!(load_outcome(LoadMode::Initial, snapshot, false), LoadOutcome::Apply(None));
+        assert_eq!(load_outcome(LoadMode::Initial, None, false), LoadOutcome::ErrorPage);
+        assert_eq!(load_outcome(LoadMode::Refresh, live, true), LoadOutcome::Apply(Some("Daten aktualisiert")));
+        assert_eq!(load_outcome(LoadMode::Refresh, snapshot, true), LoadOutcome::Apply(None));
+        assert_eq!(load_outcome(LoadMode::Refresh, None, true), LoadOutcome::ErrorToast("Aktualisieren fehlgeschlagen"));
+        assert_eq!(load_outcome(LoadMode::Refresh, None, false), LoadOutcome::ErrorPage);
+        assert_eq!(load_outcome(LoadMode::Reconnect, None, true), LoadOutcome::ErrorToast("Verbindung fehlgeschlagen"));
+    }
+}
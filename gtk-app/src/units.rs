@@ -0,0 +1,486 @@
+//! Unit conversion for blood values (mg/dL ↔ mmol/L, g/dL ↔ g/L, …).
+//!
+//! Units are parsed into a dimension and a factor to its base unit (g/L,
+//! mol/L, cells/L, U/L). Converting between mass and amount of substance
+//! needs the molar mass of the analyte, which is looked up by the id of
+//! its reference value.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::api::types::*;
+use crate::derived::derive_values;
+use crate::ui::dashboard::find_reference;
+use crate::validation::{check_value, ValueWarning};
+
+/// Units values are displayed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    /// The unit of the reference database
+    #[default]
+    Reference,
+    /// SI units, e.g. mmol/L
+    Si,
+    /// Conventional units, e.g. mg/dL
+    Conventional,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 3] = [UnitSystem::Reference, UnitSystem::Si, UnitSystem::Conventional];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnitSystem::Reference => "Wie Referenzdatenbank",
+            UnitSystem::Si => "SI-Einheiten (mmol/L)",
+            UnitSystem::Conventional => "Konventionell (mg/dL)",
+        }
+    }
+}
+
+/// Conversion data of one analyte.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Analyte {
+    /// Ids of the reference values this applies to
+    pub ids: &'static [&'static str],
+    /// g/mol; `None` if only mass (or only molar) units are in use
+    pub molar_mass: Option<f64>,
+    pub si_unit: &'static str,
+    pub conventional_unit: &'static str,
+}
+
+/// HbA1c is the only analyte given in % and mmol/mol.
+const HBA1C_IDS: &[&str] = &["hba1c", "hba1c_ifcc"];
+
+const ANALYTES: &[Analyte] = &[
+    Analyte { ids: &["blutzucker_nuechtern", "mittlere_blutglucose"], molar_mass: Some(180.16), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["cholesterin", "hdl_cholesterin", "ldl_cholesterin"], molar_mass: Some(386.65), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["triglyceride"], molar_mass: Some(885.7), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["kreatinin"], molar_mass: Some(113.12), si_unit: "µmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["harnstoff"], molar_mass: Some(60.06), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["harnsaeure"], molar_mass: Some(168.11), si_unit: "µmol/L", conventional_unit: "mg/dL" },
+    // Per heme subunit, so 1 g/dL = 0.6206 mmol/L
+    Analyte { ids: &["haemoglobin", "mchc"], molar_mass: Some(16114.5), si_unit: "mmol/L", conventional_unit: "g/dL" },
+    Analyte { ids: &["bilirubin_gesamt"], molar_mass: Some(584.66), si_unit: "µmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["eisen"], molar_mass: Some(55.845), si_unit: "µmol/L", conventional_unit: "µg/dL" },
+    Analyte { ids: &["calcium"], molar_mass: Some(40.078), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["magnesium"], molar_mass: Some(24.305), si_unit: "mmol/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["homocystein"], molar_mass: Some(135.18), si_unit: "µmol/L", conventional_unit: "mg/L" },
+    Analyte { ids: &["freies_t3"], molar_mass: Some(650.97), si_unit: "pmol/L", conventional_unit: "pg/mL" },
+    Analyte { ids: &["freies_t4"], molar_mass: Some(776.87), si_unit: "pmol/L", conventional_unit: "ng/dL" },
+    Analyte { ids: &["vitamin_d"], molar_mass: Some(400.64), si_unit: "nmol/L", conventional_unit: "ng/mL" },
+    Analyte { ids: &["vitamin_b12"], molar_mass: Some(1355.37), si_unit: "pmol/L", conventional_unit: "pg/mL" },
+    Analyte { ids: HBA1C_IDS, molar_mass: None, si_unit: "mmol/mol", conventional_unit: "%" },
+    Analyte { ids: &["gesamt_eiweiss"], molar_mass: None, si_unit: "g/L", conventional_unit: "g/dL" },
+    Analyte { ids: &["iga", "igg", "igm"], molar_mass: None, si_unit: "g/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["crp"], molar_mass: None, si_unit: "mg/L", conventional_unit: "mg/dL" },
+    Analyte { ids: &["ferritin"], molar_mass: None, si_unit: "µg/L", conventional_unit: "ng/mL" },
+    Analyte { ids: &["leukozyten", "thrombozyten"], molar_mass: None, si_unit: "10⁹/L", conventional_unit: "×10³/µL" },
+    Analyte { ids: &["erythrozyten"], molar_mass: None, si_unit: "10¹²/L", conventional_unit: "×10⁶/µL" },
+];
+
+/// Conversion data for the reference value with `id`.
+pub fn analyte(id: &str) -> Option<&'static Analyte> {
+    ANALYTES.iter().find(|a| a.ids.contains(&id))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    /// Base g/L
+    Mass,
+    /// Base mol/L
+    Molar,
+    /// Base cells/L
+    Count,
+    /// Base U/L
+    Activity,
+    /// HbA1c in % (NGSP/DCCT)
+    Percent,
+    /// HbA1c in mmol/mol (IFCC)
+    MilliMolPerMol,
+    /// Volume of one cell (MCV), base L
+    CellVolume,
+    /// Mass per cell (MCH), base g
+    CellMass,
+    /// Clotting times, base s
+    Time,
+    /// Erythrocyte sedimentation after one hour, base mm
+    Sedimentation,
+    /// eGFR, base mL/min per 1.73 m² body surface
+    FiltrationRate,
+}
+
+/// Lower-case form with a single µ sign and no blanks, so "µmol/L",
+/// "umol/l" and "μmol / L" compare equal.
+pub fn canonical_unit(unit: &str) -> String {
+    unit.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == 'μ' { 'µ' } else { c })
+        .collect::<String>()
+        .to_lowercase()
+        .replace("mcg", "µg")
+        .replace("umol", "µmol")
+        .replace("ug/", "µg/")
+        .replace("/ul", "/µl")
+}
+
+/// Blank or "-", as the lab import writes for values without a unit.
+pub fn is_missing_unit(unit: &str) -> bool {
+    matches!(unit.trim(), "" | "-")
+}
+
+/// Whether `unit` is one the converter understands.
+pub fn is_known_unit(unit: &str) -> bool {
+    parse_unit(unit).is_some()
+}
+
+fn volume_factor(unit: &str) -> Option<f64> {
+    match unit {
+        "l" => Some(1.0),
+        "dl" => Some(1e-1),
+        "ml" => Some(1e-3),
+        "µl" => Some(1e-6),
+        "nl" => Some(1e-9),
+        "pl" => Some(1e-12),
+        _ => None,
+    }
+}
+
+fn prefix_factor(prefix: &str) -> Option<f64> {
+    match prefix {
+        "" => Some(1.0),
+        "m" => Some(1e-3),
+        "µ" => Some(1e-6),
+        "n" => Some(1e-9),
+        "p" => Some(1e-12),
+        "k" => Some(1e3),
+        _ => None,
+    }
+}
+
+/// Dimension and factor to its base unit.
+fn parse_unit(unit: &str) -> Option<(Dimension, f64)> {
+    // "G/l" and "T/l" are cell counts, "g/l" a mass concentration
+    match unit.trim() {
+        "G/l" | "G/L" => return Some((Dimension::Count, 1e9)),
+        "T/l" | "T/L" => return Some((Dimension::Count, 1e12)),
+        _ => {}
+    }
+
+    let unit = canonical_unit(unit);
+    match unit.replace(',', ".").replace('²', "2").as_str() {
+        "%" => return Some((Dimension::Percent, 1.0)),
+        "mmol/mol" => return Some((Dimension::MilliMolPerMol, 1.0)),
+        "fl" => return Some((Dimension::CellVolume, 1e-15)),
+        "pg" => return Some((Dimension::CellMass, 1e-12)),
+        "s" | "sek" => return Some((Dimension::Time, 1.0)),
+        // The lab import writes the one-hour reading as plain "mm"
+        "mm" | "mm/h" | "mm/1h" => return Some((Dimension::Sedimentation, 1.0)),
+        "ml/min/1.73m2" => return Some((Dimension::FiltrationRate, 1.0)),
+        _ => {}
+    }
+
+    let (amount, volume) = unit.split_once('/')?;
+    let per_volume = 1.0 / volume_factor(volume)?;
+
+    let count = match amount.trim_start_matches(['×', 'x', '*']) {
+        "" => Some(1.0),
+        "tsd" | "10³" | "10^3" | "10e3" => Some(1e3),
+        "mio" | "10⁶" | "10^6" | "10e6" => Some(1e6),
+        "10⁹" | "10^9" | "10e9" => Some(1e9),
+        "10¹²" | "10^12" | "10e12" => Some(1e12),
+        _ => None,
+    };
+    if let Some(factor) = count {
+        return Some((Dimension::Count, factor * per_volume));
+    }
+
+    if let Some(prefix) = amount.strip_suffix("mol") {
+        return Some((Dimension::Molar, prefix_factor(prefix)? * per_volume));
+    }
+    if let Some(prefix) = amount.strip_suffix("kat") {
+        // 1 kat = 6·10⁷ U
+        return Some((Dimension::Activity, prefix_factor(prefix)? * 6e7 * per_volume));
+    }
+    if let Some(prefix) = amount.strip_suffix("iu").or_else(|| amount.strip_suffix("ie")).or_else(|| amount.strip_suffix('u')) {
+        return Some((Dimension::Activity, prefix_factor(prefix)? * per_volume));
+    }
+    if let Some(prefix) = amount.strip_suffix('g') {
+        return Some((Dimension::Mass, prefix_factor(prefix)? * per_volume));
+    }
+    None
+}
+
+/// Converts `value` from unit `from` to unit `to`. Mass ↔ molar needs the
+/// molar mass of `analyte`, % ↔ mmol/mol is only defined for HbA1c.
+/// `None` if the units are not convertible.
+pub fn convert(value: f64, from: &str, to: &str, analyte: Option<&Analyte>) -> Option<f64> {
+    if canonical_unit(from) == canonical_unit(to) {
+        return Some(value);
+    }
+    let (from_dim, from_factor) = parse_unit(from)?;
+    let (to_dim, to_factor) = parse_unit(to)?;
+    let base = value * from_factor;
+
+    let converted = match (from_dim, to_dim) {
+        (a, b) if a == b => base,
+        (Dimension::Mass, Dimension::Molar) => base / analyte?.molar_mass?,
+        (Dimension::Molar, Dimension::Mass) => base * analyte?.molar_mass?,
+        // HbA1c, IFCC = (NGSP - 2.15) × 10.929
+        (Dimension::Percent, Dimension::MilliMolPerMol) if is_hba1c(analyte) => (base - 2.15) * 10.929,
+        (Dimension::MilliMolPerMol, Dimension::Percent) if is_hba1c(analyte) => base / 10.929 + 2.15,
+        _ => return None,
+    };
+    Some(converted / to_factor)
+}
+
+fn is_hba1c(analyte: Option<&Analyte>) -> bool {
+    analyte.is_some_and(|a| a.ids == HBA1C_IDS)
+}
+
+/// Unit values of `ref_val` are shown in. Falls back to the reference
+/// unit if the analyte has no conversion to `system`.
+pub fn display_unit(ref_val: &ReferenceValue, system: UnitSystem) -> &str {
+    let Some(analyte) = analyte(&ref_val.id) else { return &ref_val.unit };
+    let target = match system {
+        UnitSystem::Reference => return &ref_val.unit,
+        UnitSystem::Si => analyte.si_unit,
+        UnitSystem::Conventional => analyte.conventional_unit,
+    };
+    if convert(1.0, &ref_val.unit, target, Some(analyte)).is_some() {
+        target
+    } else {
+        &ref_val.unit
+    }
+}
+
+/// `ref_val` with unit and all bounds converted to `unit`; unchanged if the
+/// units are not convertible.
+pub fn reference_in_unit(ref_val: &ReferenceValue, unit: &str) -> ReferenceValue {
+    let analyte = analyte(&ref_val.id);
+    if canonical_unit(&ref_val.unit) == canonical_unit(unit)
+        || convert(1.0, &ref_val.unit, unit, analyte).is_none()
+    {
+        return ref_val.clone();
+    }
+    let conv = |v: Option<f64>| {
+        v.and_then(|v| convert(v, &ref_val.unit, unit, analyte)).map(round_significant)
+    };
+    ReferenceValue {
+        unit: unit.to_string(),
+        ref_min: conv(ref_val.ref_min),
+        ref_max: conv(ref_val.ref_max),
+        ref_min_female: conv(ref_val.ref_min_female),
+        ref_max_female: conv(ref_val.ref_max_female),
+        ref_min_male: conv(ref_val.ref_min_male),
+        ref_max_male: conv(ref_val.ref_max_male),
+        optimal_min: conv(ref_val.optimal_min),
+        optimal_max: conv(ref_val.optimal_max),
+        critical_low: conv(ref_val.critical_low),
+        critical_high: conv(ref_val.critical_high),
+        age_ranges: ref_val
+            .age_ranges
+            .iter()
+            .map(|r| AgeRange {
+                ref_min: conv(r.ref_min),
+                ref_max: conv(r.ref_max),
+                ref_min_female: conv(r.ref_min_female),
+                ref_max_female: conv(r.ref_max_female),
+                ref_min_male: conv(r.ref_min_male),
+                ref_max_male: conv(r.ref_max_male),
+                ..r.clone()
+            })
+            .collect(),
+        ..ref_val.clone()
+    }
+}
+
+/// `bv` converted to the unit of `ref_val`. Values without a unit are taken
+/// to be in that unit already; `None` if the units are not convertible.
+pub fn normalize_value(bv: &BloodValue, ref_val: &ReferenceValue) -> Option<BloodValue> {
+    if is_missing_unit(&bv.unit) {
+        return Some(BloodValue { unit: ref_val.unit.clone(), ..bv.clone() });
+    }
+    let value = convert(bv.value, &bv.unit, &ref_val.unit, analyte(&ref_val.id))?;
+    Some(BloodValue { value, unit: ref_val.unit.clone(), ..bv.clone() })
+}
+
+/// User data and reference database as shown, see [`display_data`].
+#[derive(Debug, Clone, Default)]
+pub struct DisplayData {
+    pub user_data: UserData,
+    pub reference_db: Vec<ReferenceValue>,
+    /// Plausibility warnings by entry id and value name
+    pub warnings: HashMap<(String, String), Vec<ValueWarning>>,
+}
+
+impl DisplayData {
+    pub fn warnings(&self, entry_id: &str, name: &str) -> &[ValueWarning] {
+        self.warnings
+            .get(&(entry_id.to_string(), name.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Every reference value in the unit of `system` and every measurement
+/// converted to the unit of its reference value. Measurements in an
+/// unknown unit stay as they are; the checks of the raw values are
+/// collected in `warnings`. Each entry is completed with the values that
+/// can be calculated from it.
+pub fn display_data(
+    user_data: &UserData,
+    reference_db: &[ReferenceValue],
+    system: UnitSystem,
+    evaluation: &Evaluation,
+) -> DisplayData {
+    let display_db: Vec<ReferenceValue> = reference_db
+        .iter()
+        .map(|r| reference_in_unit(r, display_unit(r, system)))
+        .collect();
+
+    let mut warnings = HashMap::new();
+    let mut user_data = user_data.clone();
+    for entry in &mut user_data.entries {
+        let age = evaluation.age_on(&entry.date);
+        let derived = derive_values(entry, reference_db, evaluation.gender(), age);
+        entry.values.extend(derived);
+
+        for bv in &mut entry.values {
+            if let Some(raw_ref) = find_reference(reference_db, &bv.name).filter(|_| !bv.derived) {
+                let found = check_value(bv, raw_ref);
+                if !found.is_empty() {
+                    warnings.insert((entry.id.clone(), bv.name.clone()), found);
+                }
+            }
+            if let Some(normalized) = find_reference(&display_db, &bv.name).and_then(|r| normalize_value(bv, r)) {
+                *bv = normalized;
+            }
+        }
+    }
+    DisplayData { user_data, reference_db: display_db, warnings }
+}
+
+/// Rounds to 4 significant digits, so converted bounds read "3.885", not
+/// "3.8851...".
+fn round_significant(v: f64) -> f64 {
+    if v == 0.0 || !v.is_finite() {
+        return v;
+    }
+    let magnitude = 10f64.powi(3 - v.abs().log10().floor() as i32);
+    (v * magnitude).round() / magnitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64, tolerance: f64) {
+        let actual = actual.unwrap_or_else(|| panic!("expected {expected}, got None"));
+        assert!((actual - expected).abs() <= tolerance, "expected {expected} ± {tolerance}, got {actual}");
+    }
+
+    fn convert_for(id: &str, value: f64, from: &str, to: &str) -> Option<f64> {
+        convert(value, from, to, analyte(id))
+    }
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_unit("mg/dL"), Some((Dimension::Mass, 1e-3 / 1e-1)));
+        assert_eq!(parse_unit("g/l"), Some((Dimension::Mass, 1.0)));
+        assert_eq!(parse_unit("µmol/L"), Some((Dimension::Molar, 1e-6)));
+        assert_eq!(parse_unit("umol / l"), parse_unit("μmol/L"));
+        assert_eq!(parse_unit("U/l"), Some((Dimension::Activity, 1.0)));
+        assert_eq!(parse_unit("G/l"), Some((Dimension::Count, 1e9)));
+        assert_eq!(parse_unit("T/L"), Some((Dimension::Count, 1e12)));
+        assert_eq!(parse_unit("%"), Some((Dimension::Percent, 1.0)));
+        assert_eq!(parse_unit("mmol/mol"), Some((Dimension::MilliMolPerMol, 1.0)));
+
+        let (dim, factor) = parse_unit("×10³/µL").unwrap();
+        assert_eq!(dim, Dimension::Count);
+        assert!((factor - 1e9).abs() < 1.0);
+        let (dim, factor) = parse_unit("µkat/l").unwrap();
+        assert_eq!(dim, Dimension::Activity);
+        assert!((factor - 60.0).abs() < 1e-9);
+
+        assert_eq!(parse_unit("fL"), Some((Dimension::CellVolume, 1e-15)));
+        assert_eq!(parse_unit("pg"), Some((Dimension::CellMass, 1e-12)));
+        assert_eq!(parse_unit("mm"), parse_unit("mm/h"));
+        assert_eq!(parse_unit("mL/min/1,73m²"), Some((Dimension::FiltrationRate, 1.0)));
+        assert_eq!(parse_unit("Ml/min/1,73m2"), parse_unit("ml/min/1.73m2"));
+
+        for unknown in ["", "mg", "Ratio", "mg/Tag", "ml/min"] {
+            assert_eq!(parse_unit(unknown), None, "{unknown:?}");
+        }
+    }
+
+    #[test]
+    fn converts_glucose() {
+        assert_close(convert_for("blutzucker_nuechtern", 100.0, "mg/dL", "mmol/L"), 5.551, 0.001);
+        assert_close(convert_for("blutzucker_nuechtern", 7.0, "mmol/L", "mg/dL"), 126.1, 0.1);
+    }
+
+    #[test]
+    fn converts_creatinine() {
+        assert_close(convert_for("kreatinin", 1.0, "mg/dL", "µmol/L"), 88.40, 0.01);
+        assert_close(convert_for("kreatinin", 80.0, "umol/l", "mg/dl"), 0.905, 0.001);
+    }
+
+    #[test]
+    fn converts_cholesterol() {
+        assert_close(convert_for("cholesterin", 200.0, "mg/dL", "mmol/L"), 5.173, 0.001);
+        assert_close(convert_for("ldl_cholesterin", 3.0, "mmol/L", "mg/dL"), 116.0, 0.1);
+        assert_close(convert_for("triglyceride", 150.0, "mg/dL", "mmol/L"), 1.694, 0.001);
+    }
+
+    #[test]
+    fn converts_hba1c() {
+        assert_close(convert_for("hba1c", 6.5, "%", "mmol/mol"), 47.5, 0.05);
+        assert_close(convert_for("hba1c_ifcc", 48.0, "mmol/mol", "%"), 6.54, 0.01);
+    }
+
+    #[test]
+    fn percent_is_only_hba1c() {
+        assert_eq!(convert_for("haematokrit", 42.0, "%", "mmol/mol"), None);
+        assert_eq!(convert(42.0, "%", "mmol/mol", None), None);
+        assert_eq!(convert_for("cholesterin", 5.0, "mmol/mol", "%"), None);
+    }
+
+    #[test]
+    fn molar_masses_match_common_factors() {
+        // Conventional → SI factors as printed in lab references
+        let factors = [
+            ("haemoglobin", "g/dL", 0.6206),
+            ("harnstoff", "mg/dL", 0.1665),
+            ("harnsaeure", "mg/dL", 59.48),
+            ("bilirubin_gesamt", "mg/dL", 17.10),
+            ("eisen", "µg/dL", 0.1791),
+            ("calcium", "mg/dL", 0.2495),
+            ("magnesium", "mg/dL", 0.4114),
+            ("vitamin_d", "ng/mL", 2.496),
+            ("vitamin_b12", "pg/mL", 0.7378),
+        ];
+        for (id, unit, factor) in factors {
+            let si = analyte(id).unwrap().si_unit;
+            let actual = convert_for(id, 1.0, unit, si).unwrap();
+            assert!((actual / factor - 1.0).abs() < 0.002, "{id}: expected {factor}, got {actual}");
+        }
+    }
+
+    #[test]
+    fn every_analyte_round_trips() {
+        for a in ANALYTES {
+            let id = a.ids[0];
+            let there = convert_for(id, 5.0, a.conventional_unit, a.si_unit)
+                .unwrap_or_else(|| panic!("{id}: {} → {} not convertible", a.conventional_unit, a.si_unit));
+            assert_close(convert_for(id, there, a.si_unit, a.conventional_unit), 5.0, 1e-9);
+        }
+    }
+
+    #[test]
+    fn mass_to_molar_needs_molar_mass() {
+        assert_eq!(convert(100.0, "mg/dL", "mmol/L", None), None);
+        assert_eq!(convert_for("crp", 5.0, "mg/L", "mmol/L"), None);
+        assert_eq!(convert_for("crp", 5.0, "mg/L", "mg/dL"), Some(0.5));
+        assert_eq!(convert(5.0, "mg/L", "U/L", None), None);
+    }
+}
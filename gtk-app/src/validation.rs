@@ -0,0 +1,234 @@
+//! Plausibility checks of measured values against their reference value:
+//! missing or mismatched units, impossible values and likely decimal-shift
+//! errors (e.g. 1.2 entered as 12 or 1200).
+
+use crate::api::types::*;
+use crate::units::{self, canonical_unit, convert, is_missing_unit};
+
+/// A measurement farther than this factor outside the reference range is
+/// checked for a decimal shift.
+const SHIFT_THRESHOLD: f64 = 5.0;
+/// Beyond this factor above the reference range a value cannot be real.
+const IMPOSSIBLE_FACTOR: f64 = 1000.0;
+/// Decimal shifts that are tried, smallest first.
+const SHIFT_FACTORS: [f64; 6] = [10.0, 0.1, 100.0, 0.01, 1000.0, 0.001];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueWarning {
+    /// No unit (or "-"); the value is taken to be in `expected`
+    MissingUnit { expected: String },
+    /// The unit is not understood at all
+    UnknownUnit { unit: String },
+    /// A known unit that cannot be converted to `expected`
+    UnitMismatch { unit: String, expected: String },
+    /// Negative, above 100 % or far beyond anything measurable
+    Implausible,
+    /// Shifted by a power of ten, `corrected` would be in range
+    DecimalShift { corrected: f64, unit: String },
+}
+
+impl ValueWarning {
+    pub fn message(&self) -> String {
+        match self {
+            ValueWarning::MissingUnit { expected } if expected.is_empty() => {
+                "Keine Einheit angegeben".to_string()
+            }
+            ValueWarning::MissingUnit { expected } => {
+                format!("Keine Einheit angegeben, angenommen: {expected}")
+            }
+            ValueWarning::UnknownUnit { unit } => format!("Unbekannte Einheit „{unit}“"),
+            ValueWarning::UnitMismatch { unit, expected } => {
+                format!("Einheit „{unit}“ passt nicht zum Referenzwert ({expected})")
+            }
+            ValueWarning::Implausible => "Wert ist physiologisch nicht möglich".to_string(),
+            ValueWarning::DecimalShift { corrected, unit } => {
+                format!("Möglicher Kommafehler – gemeint war vielleicht {} {unit}", format_corrected(*corrected))
+            }
+        }
+    }
+}
+
+/// All messages of `warnings`, one per line, e.g. for a tooltip.
+pub fn warnings_text(warnings: &[ValueWarning]) -> String {
+    warnings.iter().map(ValueWarning::message).collect::<Vec<_>>().join("\n")
+}
+
+/// Checks a raw (not yet converted) measurement against `ref_val`.
+pub fn check_value(bv: &BloodValue, ref_val: &ReferenceValue) -> Vec<ValueWarning> {
+    check(bv.value, &bv.unit, ref_val)
+}
+
+/// Like [`check_value`], for a value and unit as typed into a form.
+pub fn check(value: f64, unit: &str, ref_val: &ReferenceValue) -> Vec<ValueWarning> {
+    let mut warnings = Vec::new();
+
+    let normalized = if is_missing_unit(unit) {
+        if !ref_val.unit.trim().is_empty() {
+            warnings.push(ValueWarning::MissingUnit { expected: ref_val.unit.clone() });
+        }
+        Some(value)
+    } else if ref_val.unit.trim().is_empty() {
+        // Ratios and scores have no unit to compare with
+        Some(value)
+    } else {
+        let converted = convert(value, unit, &ref_val.unit, units::analyte(&ref_val.id));
+        if converted.is_none() {
+            if units::is_known_unit(unit) {
+                warnings.push(ValueWarning::UnitMismatch {
+                    unit: unit.to_string(),
+                    expected: ref_val.unit.clone(),
+                });
+            } else {
+                warnings.push(ValueWarning::UnknownUnit { unit: unit.to_string() });
+            }
+        }
+        converted
+    };
+
+    // Without a comparable number only the unit can be judged
+    if let Some(value) = normalized {
+        warnings.extend(check_magnitude(value, ref_val));
+    }
+    warnings
+}
+
+/// Impossible values and decimal shifts, for a value in the unit of `ref_val`.
+fn check_magnitude(value: f64, ref_val: &ReferenceValue) -> Option<ValueWarning> {
+    if value < 0.0 || (canonical_unit(&ref_val.unit) == "%" && value > 100.0) {
+        return Some(ValueWarning::Implausible);
+    }
+
+    let (low, high) = reference_envelope(ref_val)?;
+    // Only a range with a lower bound tells a shift from a very high value
+    if let Some(low) = low.filter(|l| *l > 0.0) {
+        let far_off = value > high * SHIFT_THRESHOLD || value < low / SHIFT_THRESHOLD;
+        let shifted = SHIFT_FACTORS
+            .iter()
+            .map(|f| value * f)
+            .find(|v| (low..=high).contains(v));
+        if let (true, Some(corrected)) = (far_off, shifted) {
+            return Some(ValueWarning::DecimalShift { corrected, unit: ref_val.unit.clone() });
+        }
+    }
+    (value > high * IMPOSSIBLE_FACTOR).then_some(ValueWarning::Implausible)
+}
+
+/// Lowest and highest normal bound over all (gender- and age-specific)
+/// ranges; `None` without an upper bound.
+fn reference_envelope(ref_val: &ReferenceValue) -> Option<(Option<f64>, f64)> {
+    let bands = ref_val.age_ranges.iter();
+    let mins = [ref_val.ref_min, ref_val.ref_min_female, ref_val.ref_min_male]
+        .into_iter()
+        .chain(bands.clone().flat_map(|r| [r.ref_min, r.ref_min_female, r.ref_min_male]));
+    let maxs = [ref_val.ref_max, ref_val.ref_max_female, ref_val.ref_max_male]
+        .into_iter()
+        .chain(bands.flat_map(|r| [r.ref_max, r.ref_max_female, r.ref_max_male]));
+    let low = mins.flatten().reduce(f64::min);
+    let high = maxs.flatten().reduce(f64::max)?;
+    (high > 0.0).then_some((low, high))
+}
+
+fn format_corrected(v: f64) -> String {
+    let s = format!("{v:.3}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(id: &str) -> ReferenceValue {
+        let db: ReferenceDatabase =
+            serde_json::from_str(include_str!("../../data/reference_values.json")).expect("reference database");
+        db.values.into_iter().find(|r| r.id == id).unwrap_or_else(|| panic!("no reference value {id}"))
+    }
+
+    fn range(unit: &str, min: f64, max: f64) -> ReferenceValue {
+        ReferenceValue {
+            id: "test".to_string(),
+            unit: unit.to_string(),
+            ref_min: Some(min),
+            ref_max: Some(max),
+            ..ReferenceValue::default()
+        }
+    }
+
+    #[test]
+    fn accepts_values_within_the_range() {
+        assert_eq!(check(95.0, "mg/dL", &reference("blutzucker_nuechtern")), []);
+        assert_eq!(check(5.2, "mmol/l", &reference("blutzucker_nuechtern")), []);
+        assert_eq!(check(42.0, "%", &reference("haematokrit")), []);
+    }
+
+    #[test]
+    fn accepts_units_of_the_lab_import() {
+        assert_eq!(check(85.0, "ml/min/1.73m2", &reference("egfr")), []);
+        assert_eq!(check(85.0, "Ml/min/1,73m2", &reference("egfr")), []);
+        assert_eq!(check(8.0, "mm", &reference("bsg")), []);
+        assert_eq!(check(90.0, "fl", &reference("mcv")), []);
+        assert_eq!(check(30.0, "pg", &reference("mch")), []);
+        assert_eq!(check(5.0, "/nl", &reference("leukozyten")), []);
+        assert_eq!(check(1.0, "U/l", &range("U/L", 0.5, 2.0)), []);
+    }
+
+    #[test]
+    fn reports_unknown_and_mismatched_units() {
+        let ldl = reference("ldl_cholesterin");
+        assert_eq!(check(120.0, "mg/Tag", &ldl), [ValueWarning::UnknownUnit { unit: "mg/Tag".to_string() }]);
+        assert_eq!(
+            check(120.0, "U/l", &ldl),
+            [ValueWarning::UnitMismatch { unit: "U/l".to_string(), expected: ldl.unit.clone() }]
+        );
+    }
+
+    #[test]
+    fn missing_unit_assumes_the_reference_unit() {
+        let ldl = reference("ldl_cholesterin");
+        assert_eq!(check(120.0, "-", &ldl), [ValueWarning::MissingUnit { expected: ldl.unit.clone() }]);
+        // Ratios have no unit to miss
+        assert_eq!(check(2.0, "", &range("", 0.0, 3.5)), []);
+    }
+
+    #[test]
+    fn reports_impossible_values() {
+        let r = range("mg/dL", 10.0, 50.0);
+        assert_eq!(check(-1.0, "mg/dL", &r), [ValueWarning::Implausible]);
+        assert_eq!(check(60_000.0, "mg/dL", &r), [ValueWarning::Implausible]);
+        assert_eq!(check(120.0, "%", &reference("haematokrit")), [ValueWarning::Implausible]);
+        // High, but possible
+        assert_eq!(check(200.0, "mg/dL", &r), []);
+    }
+
+    #[test]
+    fn suggests_decimal_shifts() {
+        let r = range("mg/dL", 0.5, 1.2);
+        for entered in [9.0, 0.009] {
+            match check(entered, "mg/dL", &r).as_slice() {
+                [ValueWarning::DecimalShift { corrected, unit }] => {
+                    assert!((corrected - 0.9).abs() < 1e-9, "{entered}: corrected to {corrected}");
+                    assert_eq!(unit, "mg/dL");
+                }
+                other => panic!("{entered}: expected a decimal shift, got {other:?}"),
+            }
+        }
+        // Within the shift threshold it is just a high value
+        assert_eq!(check(3.0, "mg/dL", &r), []);
+    }
+
+    #[test]
+    fn envelope_covers_gender_and_age_ranges() {
+        let r = ReferenceValue {
+            ref_min: Some(20.0),
+            ref_max: Some(100.0),
+            ref_max_male: Some(150.0),
+            age_ranges: vec![AgeRange { max_age: Some(18.0), ref_min: Some(5.0), ..AgeRange::default() }],
+            ..range("µg/L", 20.0, 100.0)
+        };
+        assert_eq!(reference_envelope(&r), Some((Some(5.0), 150.0)));
+        assert_eq!(check(6.0, "µg/L", &r), []);
+        assert_eq!(check(140.0, "µg/L", &r), []);
+
+        let open = ReferenceValue { ref_max: None, ..range("mg/L", 1.0, 0.0) };
+        assert_eq!(reference_envelope(&open), None);
+    }
+}